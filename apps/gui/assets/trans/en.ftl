@@ -11,12 +11,26 @@ CreatedTime = Created Time
 
 query-field-root = Search root directory
 query-field-key = Keyword match
+query-field-title = Keyword in the file name
+query-field-body = Keyword in the file content
+query-field-tag = Keyword in the AI-generated tags
+query-field-phrase = Exact phrase (append ~N to allow N words in between)
+query-field-fuzzy = Typo-tolerant keyword
 query-field-r = Regular expression pattern
 query-field-glob = Glob/filename pattern
+query-field-ext = File extension
+query-field-type = File type group or extension
 query-field-atime = Access time range
 query-field-mtime = Modified time range
 query-field-ctime = Creation time range
 query-field-s = File size range
+query-field-dupes = Files with (yes) or without (no) identical copies
+query-type-document = Documents
+query-type-code = Source code
+query-type-text = Plain text
+query-type-spreadsheet = Spreadsheets
+query-type-image = Images
+query-type-custom = Configured group
 
 
 # Query Result Field
@@ -38,3 +52,6 @@ qrfd-ctime = Creation time
 # Search Result Viewer
 no-results = No results found
 select-file-preview = Select a file to preview
+preview-page = Page
+preview-line = Line
+result-identical-copies = Identical copies
@@ -12,12 +12,26 @@ CreatedTime = 创建时间
 
 query-field-root = 搜索根目录
 query-field-key = 关键词匹配
+query-field-title = 文件名中的关键词
+query-field-body = 文件内容中的关键词
+query-field-tag = AI 标签中的关键词
+query-field-phrase = 精确短语（追加 ~N 允许词间相隔 N 个位置）
+query-field-fuzzy = 容错关键词
 query-field-r = 正则表达式模式
 query-field-glob = Glob/文件名模式
+query-field-ext = 文件扩展名
+query-field-type = 文件类型分组或扩展名
 query-field-atime = 访问时间范围
 query-field-mtime = 修改时间范围
 query-field-ctime = 创建时间范围
 query-field-s = 文件大小范围
+query-field-dupes = 有（yes）或没有（no）内容相同副本的文件
+query-type-document = 文档
+query-type-code = 源代码
+query-type-text = 纯文本
+query-type-spreadsheet = 电子表格
+query-type-image = 图片
+query-type-custom = 自定义分组
 
 
 qrf-file-name = 名称
@@ -33,3 +47,7 @@ qrfd-score = 自然语言搜索评分（越大越好）
 qrfd-mtime = 修改时间
 qrfd-atime = 访问时间
 qrfd-ctime = 创建时间
+
+preview-page = 页码
+preview-line = 行号
+result-identical-copies = 相同副本
@@ -3,10 +3,10 @@ use crate::app::UserCommand;
 use crate::constants;
 use crate::ui::icon::file_icon_from_path;
 use crate::util::{
-    SearchResultStore, SortConfig, SortMode, time::timestamp_to_local_string,
+    SearchResultStore, SortConfig, SortMode, highlight_preview, time::timestamp_to_local_string,
 };
 use egui_i18n::tr;
-use rpc::search::{SearchHit, SearchMode};
+use rpc::search::{PreviewLocation, SearchHit, SearchMode};
 use std::cell::Cell;
 
 #[derive(Default)]
@@ -171,12 +171,29 @@ fn render_result_card(
                     ui.add_space(16.0);
                     
                     // Modified time
-                    let time_text = timestamp_to_local_string(hit.modified_time as i64);
+                    let time_text = hit.modified_time
+                        .map(timestamp_to_local_string)
+                        .unwrap_or_else(|| "未知".to_string());
                     ui.label(
                         egui::RichText::new(format!("修改于 {}", time_text))
                             .small()
                             .color(ui.visuals().weak_text_color())
                     );
+                    
+                    // Identical copies collapsed into this hit, listed on hover
+                    if !hit.duplicates.is_empty() {
+                        ui.add_space(16.0);
+                        let paths = hit.duplicates.iter()
+                            .map(|path| path.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.label(
+                            egui::RichText::new(format!("{}: {}", tr!("result-identical-copies"), hit.duplicates.len()))
+                                .small()
+                                .color(ui.visuals().weak_text_color())
+                        )
+                        .on_hover_text(paths);
+                    }
                 });
             });
         })
@@ -314,7 +331,16 @@ impl StatefulComponent for SearchResultViewer {
                     ui.take_available_space();
                     if let Some(idx) = self.selected_index {
                         if let Some(hit) = self.store.get_sorted(idx) {
-                            ui.label(&hit.preview);
+                            match hit.preview_location {
+                                Some(PreviewLocation::Page(page)) => {
+                                    ui.weak(format!("{} {}", tr!("preview-page"), page));
+                                }
+                                Some(PreviewLocation::Line(line)) => {
+                                    ui.weak(format!("{} {}", tr!("preview-line"), line));
+                                }
+                                None => {}
+                            }
+                            ui.label(highlight_preview(ui.style(), hit));
                         }
                     } else {
                         ui.label(tr!("select-file-preview"));
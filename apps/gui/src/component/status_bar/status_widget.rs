@@ -145,7 +145,7 @@ impl StatusBarStatusWidget<'_> {
             },
 
             SearchStatus::Failed(err) => StatusDisplay {
-                text: format!("Error: {:?}", err),
+                text: err.to_string(),
                 prefix: StatusPrefix::Icon(StatusIcon::Error),
             },
         }
@@ -6,9 +6,10 @@ use super::{
     session::CompletionSession,
 };
 use egui_i18n::tr;
+use query::validator::{FieldDef, FieldKind};
 use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
 use tokio::sync::Mutex;
 use tokio_stream::{Stream, StreamExt};
@@ -17,6 +18,8 @@ pub struct CompletionManager {
     path_completer: PathCompleter,
     /// Current active session (if any)
     current_session: Arc<Mutex<Option<CompletionSession>>>,
+    /// `type:` groups configured on the server, offered next to the built-in ones
+    configured_file_types: RwLock<Vec<String>>,
 }
 
 impl CompletionManager {
@@ -24,6 +27,7 @@ impl CompletionManager {
         Self {
             path_completer: PathCompleter::new(cwd),
             current_session: Arc::new(Mutex::new(None)),
+            configured_file_types: RwLock::new(Vec::new()),
         }
     }
 
@@ -31,9 +35,15 @@ impl CompletionManager {
         Ok(Self {
             path_completer: PathCompleter::with_current_dir()?,
             current_session: Arc::new(Mutex::new(None)),
+            configured_file_types: RwLock::new(Vec::new()),
         })
     }
 
+    /// Replace the `type:` groups configured on the server
+    pub fn set_configured_file_types(&self, names: Vec<String>) {
+        *self.configured_file_types.write().unwrap() = names;
+    }
+
     /// Start a new completion session, cancelling any existing one
     pub async fn start_session(
         &self,
@@ -111,11 +121,14 @@ impl CompletionManager {
                 field,
                 value,
                 value_start,
-            } => match field.as_str() {
-                "root" => {
+            } => match FieldDef::find_by_alias(&field).map(|def| def.kind) {
+                Some(FieldKind::Root) => {
                     let stream = self.path_completer.complete(&value).await;
                     Box::pin(WrapperRangeStream::new(stream, value_start..cursor_pos))
                 }
+                Some(FieldKind::FileType) => Box::pin(tokio_stream::iter(
+                    self.file_type_completions(&value, value_start..cursor_pos),
+                )),
                 _ => Box::pin(tokio_stream::empty()),
             },
 
@@ -156,6 +169,38 @@ impl CompletionManager {
             })
             .collect()
     }
+
+    /// Complete `type:` values with the built-in and the configured file type groups
+    fn file_type_completions(
+        &self,
+        partial: &str,
+        range: ReplacementRange,
+    ) -> Vec<CompletionItem> {
+        let partial_lower = partial.to_lowercase();
+        let configured = self.configured_file_types.read().unwrap();
+
+        // A configured group with a built-in name overrides it, keep the built-in label
+        let built_in = query::FILE_TYPE_GROUPS.iter().map(|group| {
+            (group.name.to_string(), tr!(&format!("query-type-{}", group.name)))
+        });
+        let custom = configured
+            .iter()
+            .filter(|name| query::FileTypeGroup::find(name).is_none())
+            .map(|name| (name.clone(), tr!("query-type-custom")));
+
+        built_in
+            .chain(custom)
+            .filter(|(name, _)| name.to_lowercase().starts_with(&partial_lower))
+            .map(|(name, description)| CompletionItem {
+                label: format!("{} - {}", name, description),
+                replacement: Replacement {
+                    range: range.clone(),
+                    text: name,
+                },
+                source: CompletionSource::Keyword,
+            })
+            .collect()
+    }
 }
 
 /// A stream that wrapped the original stream, changes its replacement range information
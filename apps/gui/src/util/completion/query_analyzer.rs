@@ -133,6 +133,15 @@ impl QueryAnalyzer {
                 }
             }
 
+            // `~N`/`^N` modifiers - nothing to complete until the term is finished
+            Token::Tilde(_) | Token::Caret(_) => {
+                if ends_with_space {
+                    CompletionContext::AfterTerm
+                } else {
+                    CompletionContext::InQuotedString
+                }
+            }
+
             Token::And | Token::Or | Token::Not => CompletionContext::AfterOperator,
 
             Token::LParen => {
@@ -1,4 +1,5 @@
 mod query_highlighter;
+mod preview_highlighter;
 pub mod completion;
 mod thread;
 mod search_result_store;
@@ -7,6 +8,7 @@ mod search_status;
 pub mod time;
 
 pub use query_highlighter::MemoizedQueryHighligher;
+pub use preview_highlighter::highlight_preview;
 pub use thread::UniversalEventHandlerThread;
 pub use sort::{SortMode, SortDirection, SortConfig};
 pub use search_result_store::SearchResultStore;
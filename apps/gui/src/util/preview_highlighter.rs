@@ -0,0 +1,35 @@
+use egui::text::{LayoutJob, TextFormat};
+use rpc::search::SearchHit;
+
+/// Lay out a search hit's preview with the matched ranges emphasized.
+/// Ranges that are out of bounds, split a character or overlap a previous
+/// range are ignored.
+pub fn highlight_preview(egui_style: &egui::Style, hit: &SearchHit) -> LayoutJob {
+    let mut job = LayoutJob::default();
+
+    let font_id = egui::TextStyle::Body.resolve(egui_style);
+    let normal = TextFormat {
+        font_id: font_id.clone(),
+        color: egui_style.visuals.text_color(),
+        ..Default::default()
+    };
+    let highlighted = TextFormat {
+        font_id,
+        color: egui_style.visuals.strong_text_color(),
+        background: egui_style.visuals.selection.bg_fill.gamma_multiply(0.5),
+        ..Default::default()
+    };
+
+    let text = hit.preview.as_str();
+    let mut last_end = 0;
+    for range in &hit.highlights {
+        if range.start < last_end || text.get(range.clone()).is_none() {
+            continue;
+        }
+        job.append(&text[last_end..range.start], 0.0, normal.clone());
+        job.append(&text[range.clone()], 0.0, highlighted.clone());
+        last_end = range.end;
+    }
+    job.append(&text[last_end..], 0.0, normal);
+    job
+}
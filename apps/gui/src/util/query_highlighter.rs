@@ -60,6 +60,7 @@ fn highlight_query(egui_style: &egui::Style, text: &str) -> LayoutJob {
                     Token::Colon => delimeter_color,
                     Token::LParen | Token::RParen => delimeter_color,
                     Token::QuotedText(_) => text_color,
+                    Token::Tilde(_) | Token::Caret(_) => delimeter_color,
                     Token::Text(_) => {
                         if matches!(tokens.get(i + 1), Some((Ok(Token::Colon), _))) {
                             // field
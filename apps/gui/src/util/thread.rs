@@ -2,6 +2,7 @@ use crate::app::{Request, Response};
 use crate::backend::{BackendEvent, handle_backend_request, init_trpc_client};
 use crate::util::completion::{CompletionManager, CompletionRequest, CompletionResponse};
 use rpc::WorldClient;
+use tarpc::context;
 use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::Arc;
@@ -65,10 +66,15 @@ impl UniversalEventHandlerThread {
                     }
                 };
 
-                if rpc_client.is_some() {
+                if let Some(client) = &rpc_client {
                     info!("Backend thread connected to RPC server.");
                     let _ = tx_response.send(Response::Backend(BackendEvent::Connected));
                     egui_ctx.request_repaint();
+
+                    match client.file_type_groups(context::current()).await {
+                        Ok(names) => completion_manager.set_configured_file_types(names),
+                        Err(e) => error!("Failed to fetch file type groups: {}", e),
+                    }
                 }
 
                 while let Ok(req) = rx_request.recv() {
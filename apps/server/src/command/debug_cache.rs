@@ -4,6 +4,7 @@
 use super::Command;
 use crate::config::Config;
 use crate::error::Result;
+use search_core::FileCatalog;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -20,14 +21,6 @@ struct CacheEntry {
     keywords: Vec<String>,
 }
 
-/// 文件元数据缓存条目
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct FileMetaEntry {
-    file_size: u64,
-    mtime: u64,
-    indexed: bool,
-}
-
 impl DebugCacheCommand {
     pub fn new(config: Config, filter: Option<String>, limit: usize) -> Self {
         Self { config, filter, limit }
@@ -43,9 +36,9 @@ impl DebugCacheCommand {
         }
     }
 
-    fn format_time(timestamp: u64) -> String {
+    fn format_time(timestamp: i64) -> String {
         use chrono::{TimeZone, Utc, Local};
-        match Local.timestamp_opt(timestamp as i64, 0) {
+        match Local.timestamp_opt(timestamp, 0) {
             chrono::LocalResult::Single(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string(),
             _ => format!("时间戳: {}", timestamp),
         }
@@ -88,7 +81,6 @@ impl Command for DebugCacheCommand {
         };
         
         let mut keyword_count = 0;
-        let mut meta_count = 0;
         let mut displayed = 0;
 
         println!("📋 关键词缓存列表:");
@@ -98,12 +90,6 @@ impl Command for DebugCacheCommand {
             let (key, value) = item?;
             let key_str = String::from_utf8_lossy(&key);
 
-            // 跳过元数据条目
-            if key_str.starts_with("meta:") {
-                meta_count += 1;
-                continue;
-            }
-
             // 应用过滤条件
             if let Some(ref filter) = self.filter {
                 if !key_str.contains(filter) {
@@ -143,7 +129,10 @@ impl Command for DebugCacheCommand {
         println!("────────────────────────────────────────────────────────────");
         println!("📊 统计信息:");
         println!("   • 关键词缓存条目: {}", keyword_count);
-        println!("   • 文件元数据条目: {}", meta_count);
+        match FileCatalog::open(&db) {
+            Ok(catalog) => println!("   • 文件目录条目: {}", catalog.len()),
+            Err(e) => println!("   • 文件目录读取失败: {}", e),
+        }
         println!("   • 显示条目数: {} / {}", displayed, self.limit);
         
         // 数据库大小
@@ -155,13 +144,13 @@ impl Command for DebugCacheCommand {
         println!("💡 提示:");
         println!("   • 使用 --filter <关键词> 过滤文件路径");
         println!("   • 使用 --limit <数量> 限制显示条目数");
-        println!("   • 使用 --show-meta 显示文件元数据");
+        println!("   • 使用 --show-meta 显示文件目录");
 
         Ok(())
     }
 }
 
-/// 带元数据显示的调试命令
+/// 显示文件目录（已索引文件的状态）的调试命令
 pub struct DebugCacheMetaCommand {
     config: Config,
     filter: Option<String>,
@@ -180,7 +169,7 @@ impl Command for DebugCacheMetaCommand {
         let cache_dir = &self.config.cache_dir;
         let embedding_cache_path = cache_dir.join("embedding_cache");
 
-        println!("🔍 文件元数据缓存调试工具");
+        println!("🔍 文件目录调试工具");
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
         println!("📂 缓存目录: {:?}", embedding_cache_path);
         println!();
@@ -201,17 +190,14 @@ impl Command for DebugCacheMetaCommand {
             }
         };
         
+        let catalog = FileCatalog::open(&db)
+            .map_err(|e| color_eyre::eyre::eyre!("读取文件目录失败: {}", e))?;
         let mut displayed = 0;
-        let meta_prefix = b"meta:";
 
-        println!("📋 文件元数据列表:");
+        println!("📋 文件目录列表 (索引代数 {}):", catalog.generation());
         println!("────────────────────────────────────────────────────────────");
 
-        for item in db.scan_prefix(meta_prefix) {
-            let (key, value) = item?;
-            let key_str = String::from_utf8_lossy(&key);
-            let file_path = key_str.strip_prefix("meta:").unwrap_or(&key_str);
-
+        for (file_path, entry) in catalog.iter() {
             // 应用过滤条件
             if let Some(ref filter) = self.filter {
                 if !file_path.contains(filter) {
@@ -223,27 +209,24 @@ impl Command for DebugCacheMetaCommand {
                 break;
             }
 
-            match bincode::deserialize::<FileMetaEntry>(&value) {
-                Ok(meta) => {
-                    println!();
-                    println!("📄 文件: {}", file_path);
-                    println!("   📏 大小: {}", DebugCacheCommand::format_size(meta.file_size));
-                    println!("   🕐 修改时间: {}", DebugCacheCommand::format_time(meta.mtime));
-                    println!("   📝 已索引: {}", if meta.indexed { "是" } else { "否" });
-                    
-                    let path = Path::new(file_path);
-                    if path.exists() {
-                        println!("   ✅ 文件存在");
-                    } else {
-                        println!("   ⚠️  文件已删除");
-                    }
-                    
-                    displayed += 1;
-                }
-                Err(e) => {
-                    println!("   ❌ 解析失败: {}", e);
-                }
+            println!();
+            println!("📄 文件: {}", file_path);
+            println!("   📏 大小: {}", DebugCacheCommand::format_size(entry.stat.file_size));
+            println!("   🕐 修改时间: {}", DebugCacheCommand::format_time(entry.stat.mtime_ns.div_euclid(1_000_000_000)));
+            println!("   🔢 inode: {}", entry.stat.inode);
+            println!("   #️⃣  内容哈希: {:016x}", entry.content_hash);
+            if entry.generation != catalog.generation() {
+                println!("   ⚠️  记录已失效，下次扫描时重新索引");
+            }
+
+            let path = Path::new(&file_path);
+            if path.exists() {
+                println!("   ✅ 文件存在");
+            } else {
+                println!("   ⚠️  文件已删除");
             }
+
+            displayed += 1;
         }
 
         println!();
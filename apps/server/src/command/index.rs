@@ -45,18 +45,19 @@ impl Command for IndexCommand {
     async fn execute(&self) -> Result<()> {
         // 构建搜索引擎配置
         let search_config = SearchConfig {
-            watch_paths: self.config.watch_paths.iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect(),
+            watch_paths: self.config.watch_paths.clone(),
             index: search_core::IndexConfig {
                 storage_path: self.config.cache_dir.join("index").to_string_lossy().to_string(),
                 writer_memory: 50_000_000,
+                ..Default::default()
             },
             ai: search_core::AiConfig {
                 model_path: self.config.cache_dir.join("model").to_string_lossy().to_string(),
                 keyword_count: 3,
+                ..Default::default()
             },
             cache_path: self.config.cache_dir.join("embedding_cache").to_string_lossy().to_string(),
+            file_types: self.config.file_types.clone(),
             ..Default::default()
         };
         
@@ -68,7 +69,7 @@ impl Command for IndexCommand {
         let paths_to_index = if let Some(ref path) = self.root_path {
             vec![path.clone()]
         } else {
-            self.config.watch_paths.clone()
+            self.config.watch_paths.iter().map(|watch| watch.path.clone()).collect()
         };
         
         if paths_to_index.is_empty() {
@@ -38,29 +38,17 @@ impl Server {
     /// 将内部 SearchResultItem 转换为 RPC SearchHit
     fn convert_to_hits(results: Vec<rpc_compat::SearchResultItem>) -> Vec<SearchHit> {
         results.into_iter().map(|hit| {
-            let modified_secs = hit.modified_time
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            
-            let created_secs = hit.created_time
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            
-            let accessed_secs = hit.accessed_time
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            
             SearchHit {
                 file_path: hit.path,
                 score: Some(hit.score),
                 preview: hit.preview,
+                highlights: hit.highlights,
+                preview_location: hit.preview_location,
                 file_size: hit.file_size,
-                access_time: accessed_secs,
-                modified_time: modified_secs,
-                create_time: created_secs,
+                access_time: hit.accessed_time,
+                modified_time: hit.modified_time,
+                create_time: hit.created_time,
+                duplicates: hit.duplicates,
             }
         }).collect()
     }
@@ -71,6 +59,11 @@ impl World for Server {
         "Pong".to_string()
     }
 
+    async fn file_type_groups(self, _c: Context) -> Vec<String> {
+        // `type:` 按搜索引擎的配置展开，这里返回同一份分组
+        self.engine.config.file_type_groups()
+    }
+
     async fn start_search(self, _c: Context, req: SearchRequest) -> SResult<Uuid> {
         info!("收到搜索请求: query='{}', mode={:?}", req.query, req.search_mode);
         
@@ -79,7 +72,7 @@ impl World for Server {
                 query::ValidationError::new(
                     query::empty_span(),
                     query::ValidationErrorKind::EmptyValue,
-                )
+                ).into()
             ));
         }
         
@@ -116,22 +109,8 @@ impl World for Server {
                 }
                 Err(e) => {
                     info!("搜索失败: {}", e);
-                    // 将错误转换为 SearchErrorKind
-                    let error_kind = match e {
-                        rpc_compat::QuerySearchError::ParseError(msg) |
-                        rpc_compat::QuerySearchError::ValidationError(msg) => {
-                            SearchErrorKind::InvalidQuery(
-                                query::ValidationError::new(
-                                    query::empty_span(),
-                                    query::ValidationErrorKind::InvalidRange { reason: msg },
-                                )
-                            )
-                        }
-                        rpc_compat::QuerySearchError::ExecutionError(_) => {
-                            SearchErrorKind::OperateOnAlreadyFailedSearch
-                        }
-                    };
-                    sessions.mark_failed(session_id, error_kind);
+                    // 错误原样转换为 SearchErrorKind
+                    sessions.mark_failed(session_id, e.into());
                 }
             }
         });
@@ -205,18 +184,19 @@ impl Command for ServeCommand {
         
         // 构建搜索引擎配置
         let search_config = SearchConfig {
-            watch_paths: self.config.watch_paths.iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect(),
+            watch_paths: self.config.watch_paths.clone(),
             index: search_core::IndexConfig {
                 storage_path: self.config.cache_dir.join("index").to_string_lossy().to_string(),
                 writer_memory: 50_000_000,
+                ..Default::default()
             },
             ai: search_core::AiConfig {
                 model_path: self.config.cache_dir.join("model").to_string_lossy().to_string(),
                 keyword_count: 3,
+                ..Default::default()
             },
             cache_path: self.config.cache_dir.join("embedding_cache").to_string_lossy().to_string(),
+            file_types: self.config.file_types.clone(),
             ..Default::default()
         };
         
@@ -230,29 +210,29 @@ impl Command for ServeCommand {
         let sessions = Arc::new(SessionManager::new(1800));
         
         // 启动文件监控（如果配置了 watch_paths）
-        let _watcher_handles: Vec<_> = self.config.watch_paths.iter()
-            .filter(|p| p.exists())
-            .map(|watch_path| {
-                info!("启动文件监控: {:?}", watch_path);
-                
-                let scan_complete_tx = start_file_watcher(
-                    watch_path.clone(),
-                    engine.index.clone(),
-                    engine.schema.clone(),
-                    engine.bert.clone(),
-                    engine.cache.clone(),
-                    engine.registry.clone(),
-                );
-                
-                // 执行初始扫描
-                let _ = engine.scan_directory(watch_path);
-                
-                // 通知监控线程扫描完成
-                let _ = scan_complete_tx.send(());
-                
-                scan_complete_tx
-            })
-            .collect();
+        let mut _watcher_handles = Vec::new();
+        for watch_path in self.config.watch_paths.iter().map(|watch| &watch.path).filter(|p| p.exists()) {
+            info!("启动文件监控: {:?}", watch_path);
+            
+            // 初始扫描和文件监控共用同一组排除规则
+            let filter = engine.path_filter(watch_path)
+                .map_err(|e| color_eyre::eyre::eyre!("watch-paths 规则无效 {:?}: {}", watch_path, e))?;
+            let scan_complete_tx = start_file_watcher(
+                watch_path.clone(),
+                filter,
+                engine.reader.clone(),
+                engine.index_context(),
+                &engine.config.watcher,
+            );
+            
+            // 执行初始扫描
+            let _ = engine.scan_directory(watch_path);
+            
+            // 通知监控线程扫描完成
+            let _ = scan_complete_tx.send(());
+            
+            _watcher_handles.push(scan_complete_tx);
+        }
         
         if self.config.watch_paths.is_empty() {
             info!("⚠️  未配置 watch-paths，文件监控未启动");
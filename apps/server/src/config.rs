@@ -1,6 +1,8 @@
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use search_core::WatchPath;
 use config::{create_strategy, resolve_dir, AppStrategy};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -8,8 +10,12 @@ use config::{create_strategy, resolve_dir, AppStrategy};
 pub struct Config {
     pub runtime_dir: PathBuf,
     pub cache_dir: PathBuf,
-    /// 要监控和索引的目录列表
-    pub watch_paths: Vec<PathBuf>,
+    /// 要监控和索引的目录列表，每项为路径或带规则的表
+    #[serde(deserialize_with = "search_core::config::deserialize_watch_paths")]
+    pub watch_paths: Vec<WatchPath>,
+    /// `type:` 的文件类型分组（组名 → 扩展名），同名时覆盖内置分组
+    #[serde(deserialize_with = "search_core::config::deserialize_file_types")]
+    pub file_types: HashMap<String, Vec<String>>,
 }
 
 
@@ -24,6 +30,7 @@ fn default_config() -> Config {
             Some(s.cache_dir())
         }),
         watch_paths: vec![],  // 默认为空，要求用户配置
+        file_types: HashMap::new(),
     }
 }
     
@@ -64,9 +71,16 @@ impl Config {
 
 # 要监控和索引的目录列表
 # 建议配置你经常需要搜索的目录
+#
+# 每项可以直接写路径，也可以写成带规则的表（规则使用 gitignore 语法，相对于 path 匹配）：
+#   include        只索引匹配的文件，如 ["*.md", "docs/"]
+#   exclude        额外排除的文件和目录，如 ["vendor/", "*.min.js"]
+#   max-file-size  超过该大小的文件不索引，如 "20MB"
+#   ignore-file    是否读取目录中的 .unnamedignore（默认 true）
+#   respect-gitignore  是否遵循 .gitignore（默认 true）
 watch-paths = [
     # "/Users/yourname/Documents",
-    # "/Users/yourname/Projects",
+    # { path = "/Users/yourname/Projects", exclude = ["vendor/"], max-file-size = "20MB" },
 ]
 
 # 可选：自定义运行时目录
@@ -74,6 +88,10 @@ watch-paths = [
 
 # 可选：自定义缓存目录
 # cache-dir = "/custom/cache/path"
+
+# 可选：`type:` 的文件类型分组（组名 → 扩展名），同名时覆盖内置分组
+# [file-types]
+# notes = ["md", "org"]
 "#;
 
         let mut file = std::fs::File::create(config_path)?;
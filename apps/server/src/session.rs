@@ -227,10 +227,13 @@ mod tests {
             file_path: PathBuf::from(path),
             score: Some(score),
             preview: "test snippet".to_string(),
+            highlights: Vec::new(),
+            preview_location: None,
             file_size: 1024,
-            access_time: 0,
-            modified_time: 0,
-            create_time: 0,
+            access_time: None,
+            modified_time: None,
+            create_time: None,
+            duplicates: Vec::new(),
         }
     }
 
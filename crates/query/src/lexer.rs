@@ -1,5 +1,7 @@
 use logos::{Logos, Lexer};
 
+use crate::validator::FieldDef;
+
 /// Raw tokens used internally by logos
 #[derive(Logos, Debug, PartialEq, Clone)]
 #[logos(skip r"[ \t\n\f]+")]
@@ -28,7 +30,13 @@ enum RawToken {
     #[regex(r#""([^"\\]|\\.)*""#, quoted_text_inner_string)]
     QuotedText(String),
 
-    #[regex(r#"[^ \t\n\f:"()!&|]+"#, |lex| lex.slice().to_string())]
+    #[regex(r"~[0-9]*", |lex| lex.slice()[1..].to_string())]
+    Tilde(String),
+
+    #[regex(r"\^[0-9.]*", |lex| lex.slice()[1..].to_string())]
+    Caret(String),
+
+    #[regex(r#"[^ \t\n\f:"()!&|~^]+"#, |lex| lex.slice().to_string())]
     Text(String),
 }
 
@@ -37,6 +45,38 @@ fn quoted_text_inner_string(lex: &mut Lexer<RawToken>) -> String {
     slice.get(1..slice.len() - 1).unwrap().to_string()
 }
 
+/// Length of an unquoted field value without its trailing `~N` and `^N` modifiers
+///
+/// Only a `~` or `^` followed by a number is a modifier, so values such as
+/// `title:~draft` or `tag:c^` keep them. Only applies to fields whose kind
+/// accepts modifiers, see `field_accepts_modifiers`.
+pub(crate) fn value_len_without_modifiers(value: &str) -> usize {
+    let mut len = value.len();
+    loop {
+        let head = &value[..len];
+        let number_start = head.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.').len();
+        let number = &head[number_start..];
+        let is_modifier = match head[..number_start].chars().next_back() {
+            Some('~') => !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()),
+            Some('^') => number.bytes().any(|b| b.is_ascii_digit()),
+            _ => false,
+        };
+        // The value itself must not be empty
+        if !is_modifier || number_start <= 1 {
+            return len;
+        }
+        len = number_start - 1;
+    }
+}
+
+/// Whether a trailing `~N`/`^N` after an unquoted value of `field` is a modifier
+///
+/// Only text fields take modifiers; regex, glob, path and other values are
+/// kept verbatim so that `regex:a|^404` or `name:PROGRA~1` lex as one value.
+pub(crate) fn field_accepts_modifiers(field: &str) -> bool {
+    FieldDef::find_by_alias(field).is_some_and(|def| def.kind.accepts_modifiers())
+}
+
 /// Value tokens - used after `:` where operators are treated as text
 #[derive(Logos, Debug, PartialEq, Clone)]
 enum ValueToken {
@@ -66,6 +106,10 @@ pub enum Token {
     LParen,
     RParen,
     QuotedText(String),
+    /// Proximity/fuzziness modifier `~N`, holding the (possibly empty) digits
+    Tilde(String),
+    /// Boost modifier `^N`, holding the (possibly empty) number
+    Caret(String),
     Text(String),
 }
 
@@ -73,6 +117,10 @@ pub enum Token {
 pub struct QueryLexer<'source> {
     lexer: Lexer<'source, RawToken>,
     after_colon: bool,
+    /// The last token was unquoted text, a field name if a `:` follows
+    last_text: Option<String>,
+    /// Whether the value after the current `:` may end with modifiers
+    value_modifiers: bool,
     current_span: std::ops::Range<usize>,
 }
 
@@ -81,6 +129,8 @@ impl<'source> QueryLexer<'source> {
         Self {
             lexer: RawToken::lexer(source),
             after_colon: false,
+            last_text: None,
+            value_modifiers: false,
             current_span: 0..0,
         }
     }
@@ -118,10 +168,23 @@ impl<'source> Iterator for QueryLexer<'source> {
                     Some(Ok(Token::QuotedText(s.into())))
                 }
                 Ok(ValueToken::Text) => {
-                    let s = value_lexer.slice().to_string();
-                    self.current_span = value_lexer.span();
-                    self.lexer = value_lexer.morph();
-                    Some(Ok(Token::Text(s)))
+                    let span = value_lexer.span();
+                    let value = value_lexer.slice();
+                    let value_end = if self.value_modifiers {
+                        span.start + value_len_without_modifiers(value)
+                    } else {
+                        span.end
+                    };
+                    self.current_span = span.start..value_end;
+                    if value_end < span.end {
+                        // Lex the trailing modifiers in normal mode
+                        let mut lexer = RawToken::lexer(value_lexer.source());
+                        lexer.bump(value_end);
+                        self.lexer = lexer;
+                    } else {
+                        self.lexer = value_lexer.morph();
+                    }
+                    Some(Ok(Token::Text(value[..value_end - span.start].to_string())))
                 }
                 Ok(ValueToken::Whitespace) => {
                     // No value after colon, continue in normal mode
@@ -149,10 +212,12 @@ impl<'source> Iterator for QueryLexer<'source> {
 
         let result = self.lexer.next()?;
         self.current_span = self.lexer.span();
+        let last_text = self.last_text.take();
 
         match result {
             Ok(RawToken::Colon) => {
                 self.after_colon = true;
+                self.value_modifiers = last_text.as_deref().is_some_and(field_accepts_modifiers);
                 Some(Ok(Token::Colon))
             }
             Ok(RawToken::And) => Some(Ok(Token::And)),
@@ -161,7 +226,12 @@ impl<'source> Iterator for QueryLexer<'source> {
             Ok(RawToken::LParen) => Some(Ok(Token::LParen)),
             Ok(RawToken::RParen) => Some(Ok(Token::RParen)),
             Ok(RawToken::QuotedText(s)) => Some(Ok(Token::QuotedText(s))),
-            Ok(RawToken::Text(s)) => Some(Ok(Token::Text(s))),
+            Ok(RawToken::Tilde(s)) => Some(Ok(Token::Tilde(s))),
+            Ok(RawToken::Caret(s)) => Some(Ok(Token::Caret(s))),
+            Ok(RawToken::Text(s)) => {
+                self.last_text = Some(s.clone());
+                Some(Ok(Token::Text(s)))
+            }
             Err(_) => Some(Err(())),
         }
     }
@@ -316,7 +386,115 @@ mod test {
                 Token::Colon => assert_eq!(slice, ":"),
                 Token::LParen => assert_eq!(slice, "("),
                 Token::RParen => assert_eq!(slice, ")"),
+                Token::Tilde(digits) | Token::Caret(digits) => assert_eq!(&slice[1..], digits),
             }
         }
     }
+
+    #[test]
+    fn test_tilde_modifiers() {
+        let input = r#"word~1 "a b"~3 root:~/docs body:word~2"#;
+        let tokens: Vec<(Token, std::ops::Range<usize>)> = QueryLexer::new(input)
+            .spanned()
+            .filter_map(|(result, span)| result.ok().map(|t| (t, span)))
+            .collect();
+
+        // `~` inside a field value is part of the value unless digits follow it
+        assert_eq!(tokens, vec![
+            (Token::Text("word".into()), 0..4),
+            (Token::Tilde("1".into()), 4..6),
+            (Token::QuotedText("a b".into()), 7..12),
+            (Token::Tilde("3".into()), 12..14),
+            (Token::Text("root".into()), 15..19),
+            (Token::Colon, 19..20),
+            (Token::Text("~/docs".into()), 20..26),
+            (Token::Text("body".into()), 27..31),
+            (Token::Colon, 31..32),
+            (Token::Text("word".into()), 32..36),
+            (Token::Tilde("2".into()), 36..38),
+        ]);
+
+        let tokens: Vec<_> = QueryLexer::new("name:*~ root:~1").collect();
+        assert_eq!(tokens, vec![
+            Ok(Token::Text("name".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("*~".into())),
+            Ok(Token::Text("root".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("~1".into())),
+        ]);
+    }
+
+    #[test]
+    fn test_boost_modifiers() {
+        let input = r#"word^2 "a b"~3^1.5 title:report^0.5 regex:^foo"#;
+        let tokens: Vec<(Token, std::ops::Range<usize>)> = QueryLexer::new(input)
+            .spanned()
+            .filter_map(|(result, span)| result.ok().map(|t| (t, span)))
+            .collect();
+
+        assert_eq!(tokens, vec![
+            (Token::Text("word".into()), 0..4),
+            (Token::Caret("2".into()), 4..6),
+            (Token::QuotedText("a b".into()), 7..12),
+            (Token::Tilde("3".into()), 12..14),
+            (Token::Caret("1.5".into()), 14..18),
+            (Token::Text("title".into()), 19..24),
+            (Token::Colon, 24..25),
+            (Token::Text("report".into()), 25..31),
+            (Token::Caret("0.5".into()), 31..35),
+            (Token::Text("regex".into()), 36..41),
+            (Token::Colon, 41..42),
+            (Token::Text("^foo".into()), 42..46),
+        ]);
+
+        let tokens: Vec<_> = QueryLexer::new("body:word~1^2").collect();
+        assert_eq!(tokens, vec![
+            Ok(Token::Text("body".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("word".into())),
+            Ok(Token::Tilde("1".into())),
+            Ok(Token::Caret("2".into())),
+        ]);
+    }
+
+    #[test]
+    fn test_non_text_values_keep_modifier_suffix() {
+        // Regex, glob and path values never end in a modifier
+        let tokens: Vec<_> = QueryLexer::new("regex:ERROR|^404 name:PROGRA~1 root:/tmp/a~2 glob:*.bak^1 size:>1MB^2").collect();
+        assert_eq!(tokens, vec![
+            Ok(Token::Text("regex".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("ERROR|^404".into())),
+            Ok(Token::Text("name".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("PROGRA~1".into())),
+            Ok(Token::Text("root".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("/tmp/a~2".into())),
+            Ok(Token::Text("glob".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("*.bak^1".into())),
+            Ok(Token::Text("size".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text(">1MB^2".into())),
+        ]);
+
+        // Text fields, including aliases, still take modifiers
+        let tokens: Vec<_> = QueryLexer::new("content:word~1 tags:rust^2 fuzzy:word~2").collect();
+        assert_eq!(tokens, vec![
+            Ok(Token::Text("content".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("word".into())),
+            Ok(Token::Tilde("1".into())),
+            Ok(Token::Text("tags".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("rust".into())),
+            Ok(Token::Caret("2".into())),
+            Ok(Token::Text("fuzzy".into())),
+            Ok(Token::Colon),
+            Ok(Token::Text("word".into())),
+            Ok(Token::Tilde("2".into())),
+        ]);
+    }
 }
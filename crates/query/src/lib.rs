@@ -1,15 +1,33 @@
 pub mod lexer;
+pub mod optimizer;
 pub mod parser;
 pub mod validator;
 
 pub use lexer::{QueryLexer, Token};
-pub use parser::{parse_query, parser, Span};
+pub use optimizer::{Optimized, OptimizerWarning, optimize};
+pub use parser::{parse_query, parse_query_recovering, parser, Span};
 pub use validator::{
-    FIELD_DEFINITIONS, Query, Term, ValidationError, ValidationErrorKind,
-    ValidationResult, validate_query,
+    Diagnostics, FIELD_DEFINITIONS, FILE_TYPE_GROUPS, FileTypeGroup, Query, Term, TextField, ValidationError, ValidationErrorKind,
+    ValidationResult, ValidationWarning, ValidationWarningKind, validate_query,
+    validate_query_with,
 };
 
 pub fn empty_span() -> Span {
     use chumsky::span::Span as _;
     Span::new((), 0..0)
 }
+
+/// Parse and validate a query string, collecting every error and warning
+///
+/// The query is only returned when there are no errors.
+pub fn check_query(input: &str) -> (Option<Query>, Diagnostics) {
+    let (parsed, errors) = parse_query_recovering(input);
+    let mut diagnostics = Diagnostics {
+        errors,
+        warnings: Vec::new(),
+    };
+    let query = parsed.and_then(|parsed| validate_query_with(&parsed, &mut diagnostics));
+    diagnostics.errors.sort_by_key(|error| error.span.start);
+    let query = query.filter(|_| diagnostics.errors.is_empty());
+    (query, diagnostics)
+}
@@ -0,0 +1,483 @@
+//! Query normalization and optimization
+//!
+//! [`optimize`] rewrites a validated [`Query`] before execution:
+//! - nested `And`/`Or` groups are flattened and `NOT NOT x` becomes `x`
+//! - duplicated terms are removed
+//! - time and size ranges on the same field are intersected (`And`) or merged
+//!   when they overlap (`Or`), and nested `root:` terms keep the narrower
+//!   (`And`) or wider (`Or`) directory
+//! - contradictions such as `size:>1MB AND size:<1KB`, two different `ext:`
+//!   values or `x AND NOT x` are reported as warnings; a contradictory group
+//!   matches nothing and is pruned
+//! - cheap filters are moved ahead of text terms and regexes, so candidates are
+//!   rejected by the cheap checks before a regex scans their content
+//!
+//! A query that can never match becomes an empty `Or`, and one that matches
+//! every file becomes an empty `And`.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::validator::{Query, SizeRange, Term, TimeRange};
+
+/// An optimized query with the problems found while optimizing it
+#[derive(Debug)]
+pub struct Optimized {
+    pub query: Query,
+    pub warnings: Vec<OptimizerWarning>,
+}
+
+/// A part of the query that can never match
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum OptimizerWarning {
+    /// Ranges on the same field that do not overlap
+    EmptyRange { field: String },
+    /// Two `root:` directories where neither contains the other
+    DisjointRoots { first: String, second: String },
+    /// Two different `ext:` values
+    ConflictingExtensions { first: String, second: String },
+    /// A term combined with its own negation
+    NegatedSelf { term: String },
+}
+
+impl fmt::Display for OptimizerWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptimizerWarning::EmptyRange { field } => {
+                write!(f, "'{}' ranges do not overlap and never match", field)
+            }
+            OptimizerWarning::DisjointRoots { first, second } => {
+                write!(f, "roots '{}' and '{}' do not overlap and never match", first, second)
+            }
+            OptimizerWarning::ConflictingExtensions { first, second } => {
+                write!(f, "a file cannot have both extension '{}' and '{}'", first, second)
+            }
+            OptimizerWarning::NegatedSelf { term } => {
+                write!(f, "'{}' combined with its negation never matches", term)
+            }
+        }
+    }
+}
+
+impl Query {
+    /// Whether the query is the optimizer's representation of "matches nothing"
+    pub fn matches_nothing(&self) -> bool {
+        matches!(self, Query::Or(items) if items.is_empty())
+    }
+}
+
+/// Normalize and optimize a validated query
+pub fn optimize(query: Query) -> Optimized {
+    let mut warnings = Vec::new();
+    let query = match simplify(query, &mut warnings) {
+        Node::Never => Query::Or(Vec::new()),
+        Node::Always => Query::And(Vec::new()),
+        Node::Query(query) => query,
+    };
+    Optimized { query, warnings }
+}
+
+/// A simplified subquery
+enum Node {
+    Never,
+    Always,
+    Query(Query),
+}
+
+fn simplify(query: Query, warnings: &mut Vec<OptimizerWarning>) -> Node {
+    match query {
+        Query::Term(term) => Node::Query(Query::Term(term)),
+        Query::Not(inner) => match simplify(*inner, warnings) {
+            Node::Never => Node::Always,
+            Node::Always => Node::Never,
+            Node::Query(Query::Not(inner)) => Node::Query(*inner),
+            Node::Query(inner) => Node::Query(Query::Not(Box::new(inner))),
+        },
+        Query::And(items) => simplify_and(items, warnings),
+        Query::Or(items) => simplify_or(items, warnings),
+    }
+}
+
+fn simplify_and(items: Vec<Query>, warnings: &mut Vec<OptimizerWarning>) -> Node {
+    let mut flat = Vec::new();
+    for item in items {
+        match simplify(item, warnings) {
+            Node::Never => return Node::Never,
+            Node::Always => {}
+            Node::Query(Query::And(inner)) => flat.extend(inner),
+            Node::Query(query) => flat.push(query),
+        }
+    }
+
+    let Some(flat) = merge_ranges(dedup(flat), Combine::And, warnings) else {
+        return Node::Never;
+    };
+    let Some(mut flat) = merge_roots(flat, Combine::And, warnings) else {
+        return Node::Never;
+    };
+
+    let mut extensions = flat.iter().filter_map(|item| match item {
+        Query::Term(Term::Extension(ext)) => Some(ext),
+        _ => None,
+    });
+    if let Some(first) = extensions.next()
+        && let Some(second) = extensions.find(|ext| *ext != first)
+    {
+        warnings.push(OptimizerWarning::ConflictingExtensions {
+            first: first.clone(),
+            second: second.clone(),
+        });
+        return Node::Never;
+    }
+
+    if let Some(term) = negated_self(&flat) {
+        warnings.push(OptimizerWarning::NegatedSelf { term });
+        return Node::Never;
+    }
+
+    flat.sort_by_key(cost);
+    match flat.len() {
+        0 => Node::Always,
+        1 => Node::Query(flat.remove(0)),
+        _ => Node::Query(Query::And(flat)),
+    }
+}
+
+fn simplify_or(items: Vec<Query>, warnings: &mut Vec<OptimizerWarning>) -> Node {
+    let mut flat = Vec::new();
+    for item in items {
+        match simplify(item, warnings) {
+            Node::Never => {}
+            Node::Always => return Node::Always,
+            Node::Query(Query::Or(inner)) => flat.extend(inner),
+            Node::Query(query) => flat.push(query),
+        }
+    }
+
+    let Some(flat) = merge_ranges(dedup(flat), Combine::Or, warnings) else {
+        return Node::Never;
+    };
+    let Some(mut flat) = merge_roots(flat, Combine::Or, warnings) else {
+        return Node::Never;
+    };
+
+    if negated_self(&flat).is_some() {
+        return Node::Always;
+    }
+
+    flat.sort_by_key(cost);
+    match flat.len() {
+        0 => Node::Never,
+        1 => Node::Query(flat.remove(0)),
+        _ => Node::Query(Query::Or(flat)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Combine {
+    And,
+    Or,
+}
+
+/// Remove repeated items, keeping the first occurrence
+fn dedup(items: Vec<Query>) -> Vec<Query> {
+    let mut unique: Vec<Query> = Vec::with_capacity(items.len());
+    for item in items {
+        if !unique.contains(&item) {
+            unique.push(item);
+        }
+    }
+    unique
+}
+
+/// The DSL of an item whose negation is also in `items`
+fn negated_self(items: &[Query]) -> Option<String> {
+    items.iter().find_map(|item| match item {
+        Query::Not(inner) if items.contains(inner) => Some(inner.to_dsl()),
+        _ => None,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RangeField {
+    AccessTime,
+    ModifiedTime,
+    CreatedTime,
+    Size,
+}
+
+/// Inclusive bounds, `None` means unbounded
+///
+/// Widened to `i128` so signed timestamps and unsigned sizes share the merge
+/// logic.
+type Bounds = (Option<i128>, Option<i128>);
+
+fn widen<T: Into<i128>>(min: Option<T>, max: Option<T>) -> Bounds {
+    (min.map(Into::into), max.map(Into::into))
+}
+
+/// Merged bounds are always one of the original bounds, so they fit back
+fn narrow<T: TryFrom<i128>>((min, max): Bounds) -> (Option<T>, Option<T>) {
+    let narrow = |value: i128| {
+        T::try_from(value).unwrap_or_else(|_| unreachable!("merged bound {value} is out of range"))
+    };
+    (min.map(narrow), max.map(narrow))
+}
+
+impl RangeField {
+    fn of(term: &Term) -> Option<(RangeField, Bounds)> {
+        match term {
+            Term::AccessTime(r) => Some((RangeField::AccessTime, widen(r.min, r.max))),
+            Term::ModifiedTime(r) => Some((RangeField::ModifiedTime, widen(r.min, r.max))),
+            Term::CreatedTime(r) => Some((RangeField::CreatedTime, widen(r.min, r.max))),
+            Term::Size(r) => Some((RangeField::Size, widen(r.min, r.max))),
+            _ => None,
+        }
+    }
+
+    fn term(self, bounds: Bounds) -> Query {
+        let time = || {
+            let (min, max) = narrow(bounds);
+            TimeRange { min, max }
+        };
+        Query::Term(match self {
+            RangeField::AccessTime => Term::AccessTime(time()),
+            RangeField::ModifiedTime => Term::ModifiedTime(time()),
+            RangeField::CreatedTime => Term::CreatedTime(time()),
+            RangeField::Size => {
+                let (min, max) = narrow(bounds);
+                Term::Size(SizeRange { min, max })
+            }
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RangeField::AccessTime => "atime",
+            RangeField::ModifiedTime => "mtime",
+            RangeField::CreatedTime => "ctime",
+            RangeField::Size => "size",
+        }
+    }
+}
+
+/// Intersect (`And`) or union (`Or`) the ranges on each field
+///
+/// Returns `None` when an intersection is empty.
+fn merge_ranges(
+    items: Vec<Query>,
+    combine: Combine,
+    warnings: &mut Vec<OptimizerWarning>,
+) -> Option<Vec<Query>> {
+    let mut ranges: Vec<(RangeField, Vec<Bounds>)> = Vec::new();
+    let mut rest = Vec::with_capacity(items.len());
+    for item in items {
+        let Query::Term(term) = &item else {
+            rest.push(item);
+            continue;
+        };
+        match RangeField::of(term) {
+            Some((field, bounds)) => match ranges.iter_mut().find(|(f, _)| *f == field) {
+                Some((_, all)) => all.push(bounds),
+                None => ranges.push((field, vec![bounds])),
+            },
+            None => rest.push(item),
+        }
+    }
+
+    let mut merged = Vec::with_capacity(ranges.len() + rest.len());
+    for (field, all) in ranges {
+        match combine {
+            Combine::And => {
+                let bounds = all.into_iter().reduce(intersect).expect("at least one range");
+                if let (Some(min), Some(max)) = bounds
+                    && min > max
+                {
+                    warnings.push(OptimizerWarning::EmptyRange { field: field.name().into() });
+                    return None;
+                }
+                merged.push(field.term(bounds));
+            }
+            Combine::Or => merged.extend(union(all).into_iter().map(|bounds| field.term(bounds))),
+        }
+    }
+    merged.extend(rest);
+    Some(merged)
+}
+
+fn intersect(a: Bounds, b: Bounds) -> Bounds {
+    let min = a.0.max(b.0);
+    let max = match (a.1, b.1) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    (min, max)
+}
+
+/// Merge overlapping or adjacent ranges
+fn union(mut all: Vec<Bounds>) -> Vec<Bounds> {
+    // `None` sorts first, i.e. unbounded below
+    all.sort_by_key(|bounds| bounds.0);
+    let mut merged: Vec<Bounds> = Vec::with_capacity(all.len());
+    for bounds in all {
+        if let Some(last) = merged.last_mut() {
+            let touches = match (last.1, bounds.0) {
+                (Some(max), Some(min)) => min <= max.saturating_add(1),
+                _ => true,
+            };
+            if touches {
+                last.1 = match (last.1, bounds.1) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+                continue;
+            }
+        }
+        merged.push(bounds);
+    }
+    merged
+}
+
+/// Keep the narrowest (`And`) or widest (`Or`) of nested `root:` directories
+///
+/// Returns `None` when two roots of an `And` do not overlap.
+fn merge_roots(
+    items: Vec<Query>,
+    combine: Combine,
+    warnings: &mut Vec<OptimizerWarning>,
+) -> Option<Vec<Query>> {
+    let mut roots = Vec::new();
+    let mut rest = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            Query::Term(Term::Root(root)) => roots.push(root),
+            item => rest.push(item),
+        }
+    }
+    let within = |inner: &String, outer: &String| Path::new(inner).starts_with(outer);
+
+    let roots: Vec<String> = match combine {
+        Combine::And => {
+            // Every root must be an ancestor of (or equal to) the narrowest one
+            let mut narrowest: Option<String> = None;
+            for root in roots {
+                match narrowest {
+                    Some(ref current) if within(current, &root) => {}
+                    Some(ref current) if !within(&root, current) => {
+                        warnings.push(OptimizerWarning::DisjointRoots {
+                            first: current.clone(),
+                            second: root,
+                        });
+                        return None;
+                    }
+                    _ => narrowest = Some(root),
+                }
+            }
+            narrowest.into_iter().collect()
+        }
+        Combine::Or => roots
+            .iter()
+            .enumerate()
+            .filter(|(i, root)| {
+                // Drop roots inside another root, keeping the first of equal ones
+                !roots.iter().enumerate().any(|(j, other)| {
+                    j != *i && within(root, other) && (j < *i || !within(other, root))
+                })
+            })
+            .map(|(_, root)| root.clone())
+            .collect(),
+    };
+
+    let mut merged: Vec<Query> = roots.into_iter().map(|root| Query::Term(Term::Root(root))).collect();
+    merged.extend(rest);
+    Some(merged)
+}
+
+/// Relative cost of checking a query against a candidate document
+fn cost(query: &Query) -> u8 {
+    match query {
+        Query::Term(term) => term_cost(term),
+        Query::Not(inner) => cost(inner),
+        Query::And(items) | Query::Or(items) => items.iter().map(cost).max().unwrap_or(0),
+    }
+}
+
+fn term_cost(term: &Term) -> u8 {
+    match term {
+        Term::Root(_)
+        | Term::Extension(_)
+        | Term::FileType(_)
+        | Term::AccessTime(_)
+        | Term::ModifiedTime(_)
+        | Term::CreatedTime(_)
+        | Term::Size(_)
+        | Term::Duplicates(_) => 0,
+        Term::Glob(_) => 1,
+        Term::KeyWord(_) | Term::FieldText { .. } | Term::Phrase { .. } => 2,
+        Term::Fuzzy { .. } => 3,
+        Term::Boosted { term, .. } => term_cost(term),
+        Term::Regex(_) => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_query;
+    use crate::validator::validate_query;
+    use rstest::rstest;
+
+    fn optimize_str(input: &str) -> Optimized {
+        let parsed = parse_query(input).expect("Parse failed");
+        optimize(validate_query(&parsed).expect("Validation failed"))
+    }
+
+    #[rstest]
+    #[case("NOT NOT foo", "foo")]
+    #[case("NOT NOT NOT foo", "NOT foo")]
+    #[case("a AND (b AND (c d))", "a AND b AND c AND d")]
+    #[case("a OR (b OR c)", "a OR b OR c")]
+    #[case("a a AND (b OR b)", "a AND b")]
+    #[case("size:>1KB AND size:<1MB", "s:1001..999999")]
+    #[case("size:<1KB OR size:500..2000 OR size:>1MB", "s:..2000 OR s:1000001..")]
+    #[case("mtime:100..200 mtime:150..300", "mtime:00150..00200")]
+    #[case("root:/a AND root:/a", "root:/a")]
+    #[case("root:/a AND root:/a/b", "root:/a/b")]
+    #[case("root:/a/b OR root:/a", "root:/a")]
+    #[case("root:/a OR root:/b", "root:/a OR root:/b")]
+    #[case("regex:foo AND bar AND ext:rs", "ext:rs AND bar AND r:foo")]
+    #[case("regex:foo OR name:*.rs", "glob:*.rs OR r:foo")]
+    fn test_rewrites(#[case] input: &str, #[case] expected: &str) {
+        let optimized = optimize_str(input);
+        assert_eq!(optimized.query.to_dsl(), expected);
+        assert!(optimized.warnings.is_empty());
+    }
+
+    #[rstest]
+    #[case("size:>1MB AND size:<1KB", OptimizerWarning::EmptyRange { field: "size".into() })]
+    #[case("mtime:<1w AND mtime:>1d", OptimizerWarning::EmptyRange { field: "mtime".into() })]
+    #[case("root:/a root:/b", OptimizerWarning::DisjointRoots { first: "/a".into(), second: "/b".into() })]
+    #[case("ext:pdf AND ext:rs", OptimizerWarning::ConflictingExtensions { first: "pdf".into(), second: "rs".into() })]
+    #[case("foo AND NOT foo", OptimizerWarning::NegatedSelf { term: "foo".into() })]
+    fn test_contradictions(#[case] input: &str, #[case] warning: OptimizerWarning) {
+        let optimized = optimize_str(input);
+        assert!(optimized.query.matches_nothing());
+        assert_eq!(optimized.warnings, vec![warning]);
+    }
+
+    #[test]
+    fn test_contradictory_branches_are_pruned() {
+        let optimized = optimize_str("(size:>1MB AND size:<1KB) OR foo");
+        assert_eq!(optimized.query.to_dsl(), "foo");
+        assert_eq!(optimized.warnings.len(), 1);
+
+        // The negation of a contradiction matches everything
+        let optimized = optimize_str("NOT (ext:pdf AND ext:rs)");
+        assert!(matches!(optimized.query, Query::And(ref items) if items.is_empty()));
+
+        let optimized = optimize_str("foo OR NOT foo OR bar");
+        assert!(matches!(optimized.query, Query::And(ref items) if items.is_empty()));
+        assert!(optimized.warnings.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
-use chumsky::{input::ValueInput, prelude::*};
+use chumsky::{error::RichPattern, input::ValueInput, prelude::*};
 
+use crate::validator::{ValidationError, ValidationErrorKind};
 use crate::{QueryLexer, lexer::Token};
 
 pub type Span = SimpleSpan;
@@ -18,12 +19,21 @@ pub enum ParsedQuery {
 
     /// Logical Not
     Not(Box<Spanned<ParsedQuery>>),
+
+    /// A part of the query that failed to parse, only produced by
+    /// [`parse_query_recovering`] along with the error describing it
+    Error,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParsedTerm {
     pub field: Option<Spanned<String>>,
     pub value: Spanned<ParsedTermValue>,
+    /// Digits of a trailing `~N` modifier (proximity for phrases, edit
+    /// distance for fuzzy terms), empty when only `~` is given
+    pub modifier: Option<Spanned<String>>,
+    /// Number of a trailing `^N` boost, empty when only `^` is given
+    pub boost: Option<Spanned<String>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,23 +72,43 @@ impl ParsedTermValue {
 /// and_expr    := not_expr ((AND)? not_expr)*
 /// not_expr    := NOT* atom
 /// atom        := term | '(' query ')'
-/// term        := (field ':')? value
+/// term        := (field ':')? value modifier? boost?
 /// value       := Text | QuotedText
+/// modifier    := Tilde
+/// boost       := Caret
 /// ```
+///
+/// A `~` inside an unquoted field value is part of the value (e.g.
+/// `root:~/docs`) unless it is followed by digits only (e.g. `body:word~1`).
+///
+/// The parser recovers from syntax errors so that all of them are reported:
+/// unexpected tokens are skipped, a missing operand after `AND`/`OR` or a bad
+/// group becomes [`ParsedQuery::Error`], a missing `)` at the end is assumed
+/// and anything left after the query is skipped.
 pub fn parser<'tokens, I>()
 -> impl Parser<'tokens, I, Spanned<ParsedQuery>, extra::Err<Rich<'tokens, Token>>>
 where
     I: ValueInput<'tokens, Token = Token, Span = SimpleSpan>,
 {
     recursive(|query| {
-        let field_with_span =
-            select! { Token::Text(s) => s }.map_with(|s, e| (s, e.span()));
+        let field_with_span = select! { Token::Text(s) => s }
+            .map_with(|s, e| (s, e.span()))
+            .labelled("term");
 
         let value_with_span = select! {
             Token::Text(s) => ParsedTermValue::Text(s),
             Token::QuotedText(s) => ParsedTermValue::QuotedText(s),
         }
-        .map_with(|v, e| (v, e.span()));
+        .map_with(|v, e| (v, e.span()))
+        .labelled("value");
+
+        let modifier = select! { Token::Tilde(digits) => digits }
+            .map_with(|digits, e| (digits, e.span()))
+            .or_not();
+
+        let boost = select! { Token::Caret(number) => number }
+            .map_with(|number, e| (number, e.span()))
+            .or_not();
 
         let term = field_with_span
             .clone()
@@ -91,12 +121,16 @@ where
                 Some(value) => ParsedTerm {
                     field: Some(field_spanned),
                     value,
+                    modifier: None,
+                    boost: None,
                 },
                 None => {
                     let (text, span) = field_spanned;
                     ParsedTerm {
                         field: None,
                         value: (ParsedTermValue::Text(text), span),
+                        modifier: None,
+                        boost: None,
                     }
                 }
             })
@@ -105,14 +139,39 @@ where
                     .map_with(|v, e| ParsedTerm {
                         field: None,
                         value: (v, e.span()),
-                    }),
+                        modifier: None,
+                        boost: None,
+                    })
+                    .labelled("term"),
             )
+            .then(modifier)
+            .then(boost)
+            .map(|((term, modifier), boost)| ParsedTerm { modifier, boost, ..term })
             .map(ParsedQuery::Term)
             .map_with(|q, e| (q, e.span()));
 
-        let atom = term.or(query
-            .clone()
-            .delimited_by(just(Token::LParen), just(Token::RParen)));
+        let group = just(Token::LParen)
+            .ignore_then(query.clone())
+            .then_ignore(just(Token::RParen).ignored().recover_with(via_parser(end())))
+            .recover_with(via_parser(nested_delimiters(
+                Token::LParen,
+                Token::RParen,
+                [],
+                |span| (ParsedQuery::Error, span),
+            )));
+
+        let atom = term.or(group).recover_with(skip_then_retry_until(
+            any().ignored(),
+            one_of([Token::RParen, Token::Or]).ignored().or(end()),
+        ));
+
+        // An operand that is missing, e.g. at the end of `a AND`
+        let missing = || {
+            empty().map_with(|_, e| {
+                let span: SimpleSpan = e.span();
+                (ParsedQuery::Error, (span.start..span.start).into())
+            })
+        };
 
         let not_expr = just(Token::Not).map_with(|_, e| e.span()).repeated().foldr(
             atom,
@@ -124,7 +183,8 @@ where
 
         let and_expr = not_expr.clone().foldl(
             choice((
-                just(Token::And).ignore_then(not_expr.clone()),
+                just(Token::And)
+                    .ignore_then(not_expr.clone().recover_with(via_parser(missing()))),
                 not_expr.clone(),
             ))
             .repeated(),
@@ -141,7 +201,9 @@ where
         );
 
         and_expr.clone().foldl(
-            just(Token::Or).ignore_then(and_expr).repeated(),
+            just(Token::Or)
+                .ignore_then(and_expr.recover_with(via_parser(missing())))
+                .repeated(),
             |lhs: Spanned<ParsedQuery>, rhs: Spanned<ParsedQuery>| {
                 let span = (lhs.1.start..rhs.1.end).into();
                 match lhs {
@@ -154,20 +216,91 @@ where
             },
         )
     })
+    // Skip whatever is left after the query, e.g. a stray `)`
+    .then_ignore(end().recover_with(skip_then_retry_until(any().ignored(), end())))
+}
+
+/// Helper function to parse a query string, failing on any syntax error
+pub fn parse_query(input: &str) -> Result<Spanned<ParsedQuery>, Vec<ValidationError>> {
+    match parse_query_recovering(input) {
+        (Some(query), errors) if errors.is_empty() => Ok(query),
+        (_, errors) => Err(errors),
+    }
 }
 
-/// Helper function to parse a query string and return the result
-pub fn parse_query(input: &str) -> Result<Spanned<ParsedQuery>, Vec<Rich<'_, Token>>> {
+/// Parse a query string, recovering from syntax errors
+///
+/// Returns the query with [`ParsedQuery::Error`] in place of the parts that
+/// failed to parse, together with every syntax error sorted by position.
+pub fn parse_query_recovering(
+    input: &str,
+) -> (Option<Spanned<ParsedQuery>>, Vec<ValidationError>) {
     use chumsky::input::Stream;
 
-    let token_iter = QueryLexer::new(input)
-        .spanned()
-        .map(|(tok, span)| (tok.expect("Lexer error"), SimpleSpan::from(span)));
+    let mut errors = Vec::new();
+    let mut tokens = Vec::new();
+    for (token, span) in QueryLexer::new(input).spanned() {
+        let text = &input[span.clone()];
+        let span = SimpleSpan::from(span);
+        match token {
+            Ok(token) => tokens.push((token, span)),
+            Err(()) => {
+                errors.push(ValidationError::new(
+                    span,
+                    ValidationErrorKind::InvalidToken { token: text.to_string() },
+                ));
+                // Parse it as text so that it does not cause more errors
+                tokens.push((Token::Text(text.to_string()), span));
+            }
+        }
+    }
 
-    let token_stream = Stream::from_iter(token_iter)
-        .map((0..input.len()).into(), |(t, s): (_, _)| (t, s));
+    let end = input.len();
+    let token_stream = Stream::from_iter(tokens).map((end..end).into(), |(t, s): (_, _)| (t, s));
 
-    parser().parse(token_stream).into_result()
+    let (query, parse_errors) = parser().parse(token_stream).into_output_errors();
+    errors.extend(parse_errors.iter().map(|error| syntax_error(input, error)));
+    errors.sort_by_key(|error| error.span.start);
+    (query, errors)
+}
+
+/// Convert a chumsky error to a validation error pointing at the same span
+fn syntax_error(input: &str, error: &Rich<'_, Token>) -> ValidationError {
+    let span = *error.span();
+    let found = error
+        .found()
+        .map(|_| input[span.start..span.end].to_string());
+    let mut expected: Vec<String> = Vec::new();
+    for pattern in error.expected().filter_map(describe_pattern) {
+        if !expected.contains(&pattern) {
+            expected.push(pattern);
+        }
+    }
+    ValidationError::new(span, ValidationErrorKind::UnexpectedToken { found, expected })
+}
+
+fn describe_pattern(pattern: &RichPattern<'_, Token>) -> Option<String> {
+    match pattern {
+        RichPattern::Token(token) => Some(describe_token(token).into()),
+        RichPattern::Label(label) => Some(label.to_string()),
+        RichPattern::EndOfInput => Some("end of query".into()),
+        _ => None,
+    }
+}
+
+fn describe_token(token: &Token) -> &'static str {
+    match token {
+        Token::And => "AND",
+        Token::Or => "OR",
+        Token::Not => "NOT",
+        Token::Colon => "':'",
+        Token::LParen => "'('",
+        Token::RParen => "')'",
+        Token::QuotedText(_) => "quoted text",
+        Token::Tilde(_) => "'~'",
+        Token::Caret(_) => "'^'",
+        Token::Text(_) => "text",
+    }
 }
 
 #[cfg(test)]
@@ -376,10 +509,101 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_modifiers() {
+        let result = parse_query(r#""a b"~3 word~ title:x"#).unwrap();
+        let ParsedQuery::And(items) = &result.0 else {
+            panic!("Expected And at top level");
+        };
+        let modifiers: Vec<_> = items
+            .iter()
+            .map(|(q, _)| match q {
+                ParsedQuery::Term(term) => term.modifier.clone(),
+                _ => panic!("Expected Term"),
+            })
+            .collect();
+
+        assert_eq!(modifiers[0].as_ref().unwrap().0, "3");
+        assert_eq!(modifiers[0].as_ref().unwrap().1, (5..7).into());
+        assert_eq!(modifiers[1].as_ref().unwrap().0, "");
+        assert!(modifiers[2].is_none());
+        // The term span covers the modifier
+        assert_eq!(items[0].1, (0..7).into());
+    }
+
+    #[test]
+    fn test_boost() {
+        let result = parse_query(r#"title:report^2 "a b"~1^0.5 word"#).unwrap();
+        let ParsedQuery::And(items) = &result.0 else {
+            panic!("Expected And at top level");
+        };
+        let terms: Vec<&ParsedTerm> = items
+            .iter()
+            .map(|(q, _)| match q {
+                ParsedQuery::Term(term) => term,
+                _ => panic!("Expected Term"),
+            })
+            .collect();
+
+        assert_eq!(terms[0].boost.as_ref().unwrap().0, "2");
+        assert_eq!(terms[0].boost.as_ref().unwrap().1, (12..14).into());
+        assert_eq!(terms[1].modifier.as_ref().unwrap().0, "1");
+        assert_eq!(terms[1].boost.as_ref().unwrap().0, "0.5");
+        assert!(terms[2].boost.is_none());
+
+        // A boost must follow the modifier
+        assert!(parse_query("word^2~1").is_err());
+    }
+
     #[test]
     fn test_complex_query1() {
         let result =
             parse_query("(size:>100MB AND mtime:>30d) OR (name:*.tmp AND mtime:>7d)");
         assert!(result.is_ok());
     }
+
+    fn error_spans(input: &str) -> Vec<std::ops::Range<usize>> {
+        let (_, errors) = parse_query_recovering(input);
+        errors.iter().map(|e| e.range()).collect()
+    }
+
+    #[test]
+    fn test_recovery_reports_every_error() {
+        assert_eq!(error_spans("foo AND AND bar"), vec![8..11]);
+        assert_eq!(error_spans("foo AND"), vec![7..7]);
+        assert_eq!(error_spans("(a OR ) AND b)"), vec![6..7, 13..14]);
+        assert_eq!(error_spans("(foo OR bar"), vec![11..11]);
+        assert_eq!(error_spans("a & b \"open"), vec![2..3, 6..11]);
+
+        // Valid parts are kept around the errors
+        let (query, _) = parse_query_recovering("foo AND AND bar OR");
+        let (ParsedQuery::Or(items), _) = query.unwrap() else {
+            panic!("Expected Or at top level");
+        };
+        assert!(matches!(&items[0].0, ParsedQuery::And(and) if and.len() == 2));
+        assert_eq!(items[1], (ParsedQuery::Error, (18..18).into()));
+    }
+
+    #[test]
+    fn test_syntax_error_kinds() {
+        let errors = parse_query("a ) b").unwrap_err();
+        let ValidationErrorKind::UnexpectedToken { found, expected } = &errors[0].kind else {
+            panic!("Expected UnexpectedToken");
+        };
+        assert_eq!(found.as_deref(), Some(")"));
+        assert!(expected.contains(&"end of query".to_string()));
+
+        let errors = parse_query("title:").unwrap_err();
+        assert_eq!(
+            errors[0].kind,
+            ValidationErrorKind::UnexpectedToken { found: None, expected: vec!["value".into()] }
+        );
+
+        let errors = parse_query(r#"title:"open"#).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].kind,
+            ValidationErrorKind::InvalidToken { token: r#""open"#.into() }
+        );
+    }
 }
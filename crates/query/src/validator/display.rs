@@ -0,0 +1,336 @@
+//! Canonical query DSL rendering
+//!
+//! `Display` for [`Query`] and [`Term`] emits a string that parses and validates
+//! back to an equal AST:
+//! - fields are written with their primary alias (`regexp:` becomes `r:`)
+//! - values are quoted only when they would not lex as a single value
+//! - parentheses are only added where operator precedence requires them
+//! - time and size ranges are written as inclusive `min..max` bounds in Unix
+//!   seconds and bytes, since the AST no longer knows the original units.
+//!   Timestamps are padded to five digits so they don't read back as years
+//!
+//! Values containing a backslash directly before a quote, or ending in a
+//! backslash, cannot be quoted and do not round-trip.
+
+use std::fmt;
+
+use super::{FIELD_DEFINITIONS, FieldKind, Query, Term, TextField, TimeRange};
+use crate::lexer::value_len_without_modifiers;
+
+impl Query {
+    /// Render the query in canonical DSL syntax
+    pub fn to_dsl(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Term {
+    /// Render the term in canonical DSL syntax
+    pub fn to_dsl(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Term(term) => write!(f, "{}", term),
+            // The parser merges a leading group of the same operator into the
+            // outer one, so nested groups always need their parentheses
+            Query::And(items) => write_joined(f, items, " AND ", |item| {
+                matches!(item, Query::And(_) | Query::Or(_))
+            }),
+            Query::Or(items) => write_joined(f, items, " OR ", |item| matches!(item, Query::Or(_))),
+            Query::Not(inner) => {
+                f.write_str("NOT ")?;
+                write_operand(f, inner, matches!(**inner, Query::And(_) | Query::Or(_)))
+            }
+        }
+    }
+}
+
+fn write_joined(
+    f: &mut fmt::Formatter<'_>,
+    items: &[Query],
+    separator: &str,
+    needs_parens: impl Fn(&Query) -> bool,
+) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
+        }
+        write_operand(f, item, needs_parens(item))?;
+    }
+    Ok(())
+}
+
+fn write_operand(f: &mut fmt::Formatter<'_>, query: &Query, parens: bool) -> fmt::Result {
+    if parens {
+        write!(f, "({})", query)
+    } else {
+        write!(f, "{}", query)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::KeyWord(keyword) if is_bare_word(keyword) => f.write_str(keyword),
+            Term::KeyWord(keyword) => write_field(f, FieldKind::KeyWord, keyword),
+            Term::FieldText { field, text } => write_field(f, text_field_kind(*field), text),
+            Term::Phrase { field, text, slop } => {
+                if let Some(field) = field {
+                    write!(f, "{}:", primary_alias(text_field_kind(*field)))?;
+                }
+                write_quoted(f, text)?;
+                if *slop != 0 {
+                    write!(f, "~{}", slop)?;
+                }
+                Ok(())
+            }
+            Term::Fuzzy { field, word, distance } => {
+                match field {
+                    None if is_bare_word(word) => f.write_str(word)?,
+                    None => write_field(f, FieldKind::Fuzzy, word)?,
+                    Some(field) => write_field(f, text_field_kind(*field), word)?,
+                }
+                write!(f, "~{}", distance)
+            }
+            Term::Boosted { term, boost } => write!(f, "{}^{}", term, boost),
+            Term::Root(root) => write_field(f, FieldKind::Root, root),
+            Term::Regex(regex) => write_field(f, FieldKind::Regex, regex.as_str()),
+            Term::Glob(pattern) => write_field(f, FieldKind::Glob, pattern),
+            Term::Extension(ext) => write_field(f, FieldKind::Extension, ext),
+            Term::FileType(name) => write_field(f, FieldKind::FileType, name),
+            Term::AccessTime(range) => {
+                write_field(f, FieldKind::AccessTime, &format_time_range(range))
+            }
+            Term::ModifiedTime(range) => {
+                write_field(f, FieldKind::ModifiedTime, &format_time_range(range))
+            }
+            Term::CreatedTime(range) => {
+                write_field(f, FieldKind::CreatedTime, &format_time_range(range))
+            }
+            Term::Size(range) => write_field(f, FieldKind::Size, &format_range(range.min, range.max)),
+            Term::Duplicates(yes) => {
+                write_field(f, FieldKind::Duplicates, if *yes { "yes" } else { "no" })
+            }
+        }
+    }
+}
+
+/// Write `field:value`, quoting the value when it would not lex as one value
+fn write_field(f: &mut fmt::Formatter<'_>, kind: FieldKind, value: &str) -> fmt::Result {
+    write!(f, "{}:", primary_alias(kind))?;
+    if is_plain_value(kind, value) {
+        f.write_str(value)
+    } else {
+        write_quoted(f, value)
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"{}\"", value.replace('"', r#"\""#))
+}
+
+fn primary_alias(kind: FieldKind) -> &'static str {
+    FIELD_DEFINITIONS
+        .iter()
+        .find(|def| def.kind == kind)
+        .map(|def| def.aliases[0])
+        .expect("every field kind has a definition")
+}
+
+fn text_field_kind(field: TextField) -> FieldKind {
+    match field {
+        TextField::Title => FieldKind::Title,
+        TextField::Body => FieldKind::Body,
+        TextField::Tags => FieldKind::Tags,
+    }
+}
+
+fn format_range(min: Option<u64>, max: Option<u64>) -> String {
+    let bound = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+    format!("{}..{}", bound(min), bound(max))
+}
+
+/// Four-digit numbers read back as years, so timestamps are padded to at
+/// least five characters
+fn format_time_range(range: &TimeRange) -> String {
+    let bound = |value: Option<i64>| value.map(|v| format!("{v:05}")).unwrap_or_default();
+    format!("{}..{}", bound(range.min), bound(range.max))
+}
+
+/// Whether `word` lexes as a single bare term outside a field value
+fn is_bare_word(word: &str) -> bool {
+    !word.is_empty()
+        && !matches!(word, "AND" | "OR" | "NOT")
+        && !word.contains([' ', '\t', '\n', '\x0c', ':', '"', '(', ')', '!', '&', '|', '~', '^'])
+}
+
+/// Whether `value` lexes as a single unquoted value of a `kind` field
+fn is_plain_value(kind: FieldKind, value: &str) -> bool {
+    !value.is_empty()
+        && !value.contains([' ', '\t', '\n', '\x0c', '"', '(', ')'])
+        && (!kind.accepts_modifiers() || value_len_without_modifiers(value) == value.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_query;
+    use crate::validator::{SizeRange, TimeRange, validate_query};
+    use proptest::prelude::*;
+    use regex::Regex;
+    use rstest::rstest;
+
+    fn reparse(dsl: &str) -> Query {
+        let parsed = parse_query(dsl).unwrap_or_else(|e| panic!("failed to parse {dsl:?}: {e:?}"));
+        validate_query(&parsed).unwrap_or_else(|e| panic!("failed to validate {dsl:?}: {e}"))
+    }
+
+    #[rstest]
+    #[case("regexp:ERROR", "r:ERROR")]
+    #[case("size:>1MB", "s:1000001..")]
+    #[case("mtime:100..200", "mtime:00100..00200")]
+    #[case("mtime:-5..", "mtime:-0005..")]
+    #[case("ext:.PDF", "ext:pdf")]
+    #[case("kind:Code", "type:code")]
+    #[case("dupe:TRUE", "dupes:yes")]
+    #[case(r#"path:"/my docs""#, r#"root:"/my docs""#)]
+    #[case(r#"root:"/tmp/a~1""#, "root:/tmp/a~1")]
+    #[case("regex:ERROR|^404", "r:ERROR|^404")]
+    #[case("name:PROGRA~1", "glob:PROGRA~1")]
+    #[case("keyword:AND", "key:AND")]
+    #[case("key:a!b", "key:a!b")]
+    #[case("content:report", "body:report")]
+    #[case(r#""say \"hi\"""#, r#""say \"hi\"""#)]
+    #[case(r#""a b"~0"#, r#""a b""#)]
+    #[case(r#""a b"~"#, r#""a b"~1"#)]
+    #[case("word~", "word~1")]
+    #[case(r#"fuzzy:"a b"~2"#, r#"fuzzy:"a b"~2"#)]
+    #[case("tags:word~2^1.5", "tag:word~2^1.5")]
+    fn test_canonical_terms(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(reparse(input).to_dsl(), expected);
+    }
+
+    #[rstest]
+    #[case("a b c", "a AND b AND c")]
+    #[case("a || b", "a OR b")]
+    #[case("a OR (b AND c)", "a OR b AND c")]
+    #[case("(a OR b) c", "(a OR b) AND c")]
+    #[case("a (b c)", "a AND (b AND c)")]
+    #[case("!(a OR b)", "NOT (a OR b)")]
+    #[case("NOT NOT a", "NOT NOT a")]
+    fn test_minimal_parentheses(#[case] input: &str, #[case] expected: &str) {
+        let query = reparse(input);
+        assert_eq!(query.to_dsl(), expected);
+        assert_eq!(reparse(expected), query);
+    }
+
+    fn text_field() -> impl Strategy<Value = TextField> {
+        prop_oneof![Just(TextField::Title), Just(TextField::Body), Just(TextField::Tags)]
+    }
+
+    /// Values of text terms never end in something that reads as a modifier
+    fn text_value() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-zA-Z0-9_.*-]{1,8}",
+            "[a-z:!&|/]{1,6}",
+            Just("AND".to_string()),
+        ]
+    }
+
+    fn range<T: Ord + Copy + fmt::Debug>(
+        bound: impl Strategy<Value = T> + Clone,
+    ) -> impl Strategy<Value = (Option<T>, Option<T>)> {
+        let bound = prop::option::of(bound);
+        (bound.clone(), bound).prop_map(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => (Some(a.min(b)), Some(a.max(b))),
+            other => other,
+        })
+    }
+
+    fn time_range() -> impl Strategy<Value = TimeRange> {
+        range(-(1i64 << 40)..(1i64 << 40)).prop_map(|(min, max)| TimeRange { min, max })
+    }
+
+    fn size_range() -> impl Strategy<Value = SizeRange> {
+        // Sizes are parsed through `f64`, so keep bounds exactly representable
+        range(0..(1u64 << 50)).prop_map(|(min, max)| SizeRange { min, max })
+    }
+
+    fn text_term() -> impl Strategy<Value = Term> {
+        prop_oneof![
+            text_value().prop_map(Term::KeyWord),
+            (text_field(), text_value()).prop_map(|(field, text)| Term::FieldText { field, text }),
+            (prop::option::of(text_field()), "[a-z \"]{1,10}", 0..10u32)
+                .prop_map(|(field, text, slop)| Term::Phrase { field, text, slop }),
+            (prop::option::of(text_field()), text_value(), 0..=2u8)
+                .prop_map(|(field, word, distance)| Term::Fuzzy { field, word, distance }),
+            ("[a-z]{1,3} [a-z]{1,3}", 0..=2u8).prop_map(|(word, distance)| Term::Fuzzy {
+                field: None,
+                word,
+                distance
+            }),
+        ]
+    }
+
+    fn term() -> impl Strategy<Value = Term> {
+        prop_oneof![
+            text_term(),
+            (text_term(), 1..100u32).prop_map(|(term, boost)| Term::Boosted {
+                term: Box::new(term),
+                boost: boost as f32 / 4.0,
+            }),
+            "[a-z0-9/~^ ]{1,8}".prop_map(Term::Root),
+            "[a-z| ]{1,6}".prop_map(|pattern| Term::Regex(Regex::new(&pattern).unwrap())),
+            "!?[a-z*?.]{1,6}".prop_map(Term::Glob),
+            "[a-z0-9]{1,4}".prop_map(Term::Extension),
+            "[a-z0-9]{1,8}".prop_map(Term::FileType),
+            time_range().prop_map(Term::ModifiedTime),
+            time_range().prop_map(Term::AccessTime),
+            time_range().prop_map(Term::CreatedTime),
+            size_range().prop_map(Term::Size),
+            any::<bool>().prop_map(Term::Duplicates),
+        ]
+    }
+
+    /// Splice children of the same operator, as the parser does for leading groups
+    fn flatten(items: Vec<Query>, same: fn(Query) -> Result<Vec<Query>, Query>) -> Vec<Query> {
+        items
+            .into_iter()
+            .flat_map(|item| same(item).unwrap_or_else(|other| vec![other]))
+            .collect()
+    }
+
+    fn query() -> impl Strategy<Value = Query> {
+        term().prop_map(Query::Term).prop_recursive(3, 16, 3, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 2..4).prop_map(|items| {
+                    Query::And(flatten(items, |q| match q {
+                        Query::And(v) => Ok(v),
+                        q => Err(q),
+                    }))
+                }),
+                prop::collection::vec(inner.clone(), 2..4).prop_map(|items| {
+                    Query::Or(flatten(items, |q| match q {
+                        Query::Or(v) => Ok(v),
+                        q => Err(q),
+                    }))
+                }),
+                inner.prop_map(|q| Query::Not(Box::new(q))),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(query in query()) {
+            let dsl = query.to_dsl();
+            let reparsed = reparse(&dsl);
+            prop_assert_eq!(&reparsed, &query, "printed as {}", dsl);
+            prop_assert_eq!(reparsed.to_dsl(), dsl);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::{Span, ValidationError, ValidationErrorKind, ValidationResult};
+
+/// A named group of file extensions accepted by `type:` (e.g. `type:code`)
+#[derive(Debug)]
+pub struct FileTypeGroup {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Lowercased extensions without the leading dot
+    pub extensions: &'static [&'static str],
+}
+
+/// Built-in file type groups, the search engine configuration may add to or
+/// override them
+pub static FILE_TYPE_GROUPS: &[FileTypeGroup] = &[
+    FileTypeGroup {
+        name: "document",
+        description: "Documents",
+        extensions: &["pdf", "doc", "docx", "odt", "rtf", "epub", "ppt", "pptx", "odp"],
+    },
+    FileTypeGroup {
+        name: "code",
+        description: "Source code",
+        extensions: &[
+            "rs", "py", "js", "ts", "c", "cpp", "h", "hpp", "java", "go", "rb", "php", "swift",
+            "kt", "scala", "sh", "bash", "zsh", "toml", "yaml", "yml", "json", "xml", "html",
+            "css", "scss", "sass", "less",
+        ],
+    },
+    FileTypeGroup {
+        name: "text",
+        description: "Plain text",
+        extensions: &["txt", "md", "markdown", "rst", "org", "log", "tex"],
+    },
+    FileTypeGroup {
+        name: "spreadsheet",
+        description: "Spreadsheets",
+        extensions: &["xls", "xlsx", "ods", "csv", "tsv"],
+    },
+    FileTypeGroup {
+        name: "image",
+        description: "Images",
+        extensions: &["png", "jpg", "jpeg", "gif", "bmp", "webp", "svg", "tiff", "ico", "heic"],
+    },
+];
+
+impl FileTypeGroup {
+    /// Find a built-in group by name
+    pub fn find(name: &str) -> Option<&'static FileTypeGroup> {
+        FILE_TYPE_GROUPS.iter().find(|group| group.name == name)
+    }
+}
+
+/// Normalize a `type:` value, a group name or an extension
+pub(super) fn validate_file_type(value: String, span: Span) -> ValidationResult<String> {
+    let name = value.trim().trim_start_matches('.').to_lowercase();
+    if name.is_empty() {
+        return Err(ValidationError::new(span, ValidationErrorKind::EmptyValue));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn test_span() -> Span {
+        Span { start: 0, end: 0, context: () }
+    }
+
+    #[rstest]
+    #[case("pdf", "pdf")]
+    #[case(".PDF", "pdf")]
+    #[case("Code", "code")]
+    #[case(" image ", "image")]
+    fn test_validate_file_type(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(validate_file_type(input.into(), test_span()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_empty_file_type() {
+        assert!(validate_file_type(".".into(), test_span()).is_err());
+    }
+
+    #[test]
+    fn test_find_group() {
+        assert!(FileTypeGroup::find("code").unwrap().extensions.contains(&"rs"));
+        assert!(FileTypeGroup::find("pdf").is_none());
+    }
+}
@@ -1,13 +1,17 @@
+mod display;
 mod file_size;
+mod file_type;
 mod time;
 
-use crate::parser::{ParsedQuery, ParsedTerm, Span, Spanned};
-pub use file_size::SizeRange;
+use crate::parser::{ParsedQuery, ParsedTerm, ParsedTermValue, Span, Spanned};
+pub use file_size::{SizeRange, parse_size_value};
+pub use file_type::{FILE_TYPE_GROUPS, FileTypeGroup};
 use regex::Regex;
 use std::fmt;
+use std::path::Path;
 pub use time::TimeRange;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Query {
     Term(Term),
     And(Vec<Query>),
@@ -21,10 +25,25 @@ pub enum Term {
     Root(String),
     /// A keyword that must be matched in file content
     KeyWord(String),
+    /// A keyword that must be matched in a single text field (e.g. `title:report`)
+    FieldText { field: TextField, text: String },
+    /// Words that must appear in order, at most `slop` positions apart
+    /// (e.g. `"a b"`, `"a b"~3`), in `field` or in file content
+    Phrase { field: Option<TextField>, text: String, slop: u32 },
+    /// A keyword matched within `distance` edits (e.g. `word~1`), in `field`
+    /// or in file content
+    Fuzzy { field: Option<TextField>, word: String, distance: u8 },
+    /// A text term whose relevance score is multiplied by `boost` (e.g. `title:report^2`)
+    Boosted { term: Box<Term>, boost: f32 },
     /// Regular Expression
     Regex(Regex),
     /// Glob pattern (e.g. `*.pdf`, `!*.rs`)
     Glob(String),
+    /// File extension, lowercased and without the leading dot (e.g. `rs`, `pdf`)
+    Extension(String),
+    /// File type, a named group of extensions or a single extension, lowercased
+    /// (e.g. `code`, `pdf`)
+    FileType(String),
     /// Access time range (Unix timestamp in seconds)
     AccessTime(TimeRange),
     /// Modified time range (Unix timestamp in seconds)
@@ -33,8 +52,82 @@ pub enum Term {
     CreatedTime(TimeRange),
     /// File size range (in bytes)
     Size(SizeRange),
+    /// Whether the file content is identical to another indexed file
+    /// (`dupes:yes`), or unique (`dupes:no`)
+    Duplicates(bool),
 }
 
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // `Regex` has no `PartialEq`, patterns are compared instead
+            (Term::Regex(a), Term::Regex(b)) => a.as_str() == b.as_str(),
+            (Term::Root(a), Term::Root(b))
+            | (Term::KeyWord(a), Term::KeyWord(b))
+            | (Term::Glob(a), Term::Glob(b))
+            | (Term::Extension(a), Term::Extension(b))
+            | (Term::FileType(a), Term::FileType(b)) => a == b,
+            (
+                Term::FieldText { field: f1, text: t1 },
+                Term::FieldText { field: f2, text: t2 },
+            ) => f1 == f2 && t1 == t2,
+            (
+                Term::Phrase { field: f1, text: t1, slop: s1 },
+                Term::Phrase { field: f2, text: t2, slop: s2 },
+            ) => f1 == f2 && t1 == t2 && s1 == s2,
+            (
+                Term::Fuzzy { field: f1, word: w1, distance: d1 },
+                Term::Fuzzy { field: f2, word: w2, distance: d2 },
+            ) => f1 == f2 && w1 == w2 && d1 == d2,
+            (
+                Term::Boosted { term: t1, boost: b1 },
+                Term::Boosted { term: t2, boost: b2 },
+            ) => t1 == t2 && b1 == b2,
+            (Term::AccessTime(a), Term::AccessTime(b))
+            | (Term::ModifiedTime(a), Term::ModifiedTime(b))
+            | (Term::CreatedTime(a), Term::CreatedTime(b)) => a == b,
+            (Term::Size(a), Term::Size(b)) => a == b,
+            (Term::Duplicates(a), Term::Duplicates(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Term {
+    /// Whether the term is matched against indexed text (keywords, phrases and fuzzy terms)
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Term::KeyWord(_)
+                | Term::FieldText { .. }
+                | Term::Phrase { .. }
+                | Term::Fuzzy { .. }
+                | Term::Boosted { .. }
+        )
+    }
+}
+
+/// An indexed text field that field-scoped terms search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextField {
+    /// File name without extension
+    Title,
+    /// Extracted file content
+    Body,
+    /// AI-generated tags
+    Tags,
+}
+
+impl TextField {
+    /// Name of the field, matching its primary alias
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextField::Title => "title",
+            TextField::Body => "body",
+            TextField::Tags => "tag",
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -77,6 +170,14 @@ pub enum ValidationErrorKind {
     InvalidSizeSpec { value: String, reason: String },
     EmptyValue,
     InvalidRange { reason: String },
+    InvalidModifier { value: String, reason: String },
+    InvalidBoost { value: String, reason: String },
+    /// A value that is not one of the accepted keywords of its field
+    InvalidValue { value: String, reason: String },
+    /// Text that is not a token, e.g. an unterminated quote
+    InvalidToken { token: String },
+    /// A token in the wrong place, `found` is `None` at the end of the query
+    UnexpectedToken { found: Option<String>, expected: Vec<String> },
 }
 
 impl fmt::Display for ValidationErrorKind {
@@ -101,29 +202,186 @@ impl fmt::Display for ValidationErrorKind {
             ValidationErrorKind::InvalidRange { reason } => {
                 write!(f, "invalid range: {}", reason)
             }
+            ValidationErrorKind::InvalidModifier { value, reason } => {
+                write!(f, "invalid modifier '~{}': {}", value, reason)
+            }
+            ValidationErrorKind::InvalidBoost { value, reason } => {
+                write!(f, "invalid boost '^{}': {}", value, reason)
+            }
+            ValidationErrorKind::InvalidValue { value, reason } => {
+                write!(f, "invalid value '{}': {}", value, reason)
+            }
+            ValidationErrorKind::InvalidToken { token } => {
+                write!(f, "invalid token '{}'", token)
+            }
+            ValidationErrorKind::UnexpectedToken { found, expected } => {
+                match found {
+                    Some(found) => write!(f, "unexpected '{}'", found)?,
+                    None => write!(f, "unexpected end of query")?,
+                }
+                if !expected.is_empty() {
+                    write!(f, ", expected {}", expected.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A valid part of the query that probably does not do what was meant
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct ValidationWarning {
+    pub span: Span,
+    pub kind: ValidationWarningKind,
+}
+
+impl ValidationWarning {
+    pub fn new(span: Span, kind: ValidationWarningKind) -> Self {
+        Self { span, kind }
+    }
+
+    /// Get the byte range of the warning in the original input
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.span.start..self.span.end
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at position {}..{})",
+            self.kind, self.span.start, self.span.end
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ValidationWarningKind {
+    /// A regex that matches empty text, and so every file
+    BroadRegex { pattern: String },
+    /// A `root:` directory that does not exist
+    RootNotFound { path: String },
+}
+
+impl fmt::Display for ValidationWarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationWarningKind::BroadRegex { pattern } => {
+                write!(f, "regex '{}' matches empty text, so it matches every file", pattern)
+            }
+            ValidationWarningKind::RootNotFound { path } => {
+                write!(f, "root path '{}' does not exist", path)
+            }
         }
     }
 }
 
+/// Every error and warning found in a query
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Diagnostics {
+    pub errors: Vec<ValidationError>,
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl From<ValidationError> for Diagnostics {
+    fn from(error: ValidationError) -> Self {
+        Self {
+            errors: vec![error],
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self.errors.iter().map(ToString::to_string);
+        let warnings = self.warnings.iter().map(ToString::to_string);
+        let messages: Vec<String> = errors.chain(warnings).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
 pub type ValidationResult<T> = Result<T, ValidationError>;
 
+/// Edit distance of a fuzzy term written without digits (`word~`)
+pub const DEFAULT_FUZZY_DISTANCE: u8 = 1;
+/// Largest supported fuzzy edit distance
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+/// Slop of a proximity phrase written without digits (`"a b"~`)
+pub const DEFAULT_PHRASE_SLOP: u32 = 1;
+
 /// Validate a parsed query and convert it to a semantic query
+///
+/// Returns the first error, use [`validate_query_with`] to collect all errors
+/// and warnings.
 pub fn validate_query(query: &Spanned<ParsedQuery>) -> ValidationResult<Query> {
+    let mut diagnostics = Diagnostics::default();
+    match validate_query_with(query, &mut diagnostics) {
+        Some(query) => Ok(query),
+        None => Err(diagnostics
+            .errors
+            .into_iter()
+            .next()
+            .expect("only parts that failed to parse are invalid without an error")),
+    }
+}
+
+/// Validate a parsed query, adding every error and warning to `diagnostics`
+///
+/// Returns `None` if any part of the query is invalid. [`ParsedQuery::Error`]
+/// parts are invalid too, but were already reported by the parser.
+pub fn validate_query_with(
+    query: &Spanned<ParsedQuery>,
+    diagnostics: &mut Diagnostics,
+) -> Option<Query> {
     let (parsed, _span) = query;
     match parsed {
-        ParsedQuery::Term(term) => validate_term(term).map(Query::Term),
-        ParsedQuery::And(items) => {
-            let validated: Result<Vec<_>, _> = items.iter().map(validate_query).collect();
-            validated.map(Query::And)
-        }
-        ParsedQuery::Or(items) => {
-            let validated: Result<Vec<_>, _> = items.iter().map(validate_query).collect();
-            validated.map(Query::Or)
+        ParsedQuery::Term(term) => match validate_term(term) {
+            Ok(validated) => {
+                check_term(&validated, term.value.1, &mut diagnostics.warnings);
+                Some(Query::Term(validated))
+            }
+            Err(error) => {
+                diagnostics.errors.push(error);
+                None
+            }
+        },
+        ParsedQuery::And(items) => validate_all(items, diagnostics).map(Query::And),
+        ParsedQuery::Or(items) => validate_all(items, diagnostics).map(Query::Or),
+        ParsedQuery::Not(inner) => {
+            validate_query_with(inner, diagnostics).map(|q| Query::Not(Box::new(q)))
         }
-        ParsedQuery::Not(inner) => validate_query(inner).map(|q| Query::Not(Box::new(q))),
+        ParsedQuery::Error => None,
     }
 }
 
+/// Validate every item, so that errors after the first one are reported too
+fn validate_all(items: &[Spanned<ParsedQuery>], diagnostics: &mut Diagnostics) -> Option<Vec<Query>> {
+    let validated: Vec<Option<Query>> = items
+        .iter()
+        .map(|item| validate_query_with(item, diagnostics))
+        .collect();
+    validated.into_iter().collect()
+}
+
+/// Warn about a valid term that probably does not do what was meant
+fn check_term(term: &Term, span: Span, warnings: &mut Vec<ValidationWarning>) {
+    let kind = match term {
+        Term::Regex(re) if re.is_match("") => ValidationWarningKind::BroadRegex {
+            pattern: re.as_str().to_string(),
+        },
+        Term::Root(root) if !Path::new(root).exists() => {
+            ValidationWarningKind::RootNotFound { path: root.clone() }
+        }
+        _ => return,
+    };
+    warnings.push(ValidationWarning::new(span, kind));
+}
+
 pub struct FieldDef {
     pub kind: FieldKind,
     pub aliases: &'static [&'static str],
@@ -144,22 +402,73 @@ impl FieldDef {
 pub enum FieldKind {
     Root,
     KeyWord,
+    Title,
+    Body,
+    Tags,
+    Phrase,
+    Fuzzy,
     Regex,
     Glob,
+    Extension,
+    FileType,
     AccessTime,
     ModifiedTime,
     CreatedTime,
     Size,
+    Duplicates,
 }
 
 impl FieldKind {
+    /// Whether unquoted values of this kind may end in `~N`/`^N` modifiers
+    ///
+    /// Regex, glob, path and range values keep such suffixes verbatim.
+    pub fn accepts_modifiers(&self) -> bool {
+        matches!(
+            self,
+            FieldKind::KeyWord
+                | FieldKind::Title
+                | FieldKind::Body
+                | FieldKind::Tags
+                | FieldKind::Phrase
+                | FieldKind::Fuzzy
+        )
+    }
+
+    /// The text field searched by a field-scoped kind
+    pub fn text_field(&self) -> Option<TextField> {
+        match self {
+            FieldKind::Title => Some(TextField::Title),
+            FieldKind::Body => Some(TextField::Body),
+            FieldKind::Tags => Some(TextField::Tags),
+            _ => None,
+        }
+    }
+
     /// Parse a value string into a Term based on the field kind
     pub fn parse_value(&self, value: String, span: Span) -> ValidationResult<Term> {
         match self {
             FieldKind::Root => Ok(Term::Root(value)),
             FieldKind::KeyWord => Ok(Term::KeyWord(value)),
+            FieldKind::Title | FieldKind::Body | FieldKind::Tags => Ok(Term::FieldText {
+                field: self.text_field().expect("text field kind"),
+                text: value,
+            }),
+            FieldKind::Phrase => Ok(Term::Phrase {
+                field: None,
+                text: value,
+                slop: 0,
+            }),
+            FieldKind::Fuzzy => Ok(Term::Fuzzy {
+                field: None,
+                word: value,
+                distance: DEFAULT_FUZZY_DISTANCE,
+            }),
             FieldKind::Regex => validate_regex(value, span).map(Term::Regex),
             FieldKind::Glob => Ok(Term::Glob(value)),
+            FieldKind::Extension => validate_extension(value, span).map(Term::Extension),
+            FieldKind::FileType => {
+                file_type::validate_file_type(value, span).map(Term::FileType)
+            }
             FieldKind::AccessTime => {
                 time::validate_time(value, span).map(Term::AccessTime)
             }
@@ -170,6 +479,7 @@ impl FieldKind {
                 time::validate_time(value, span).map(Term::CreatedTime)
             }
             FieldKind::Size => file_size::validate_size(value, span).map(Term::Size),
+            FieldKind::Duplicates => validate_duplicates(value, span).map(Term::Duplicates),
         }
     }
 }
@@ -183,7 +493,32 @@ pub static FIELD_DEFINITIONS: &[FieldDef] = &[
     FieldDef {
         aliases: &["key", "keyword"],
         description: "Keyword match",
-        kind: FieldKind::Regex,
+        kind: FieldKind::KeyWord,
+    },
+    FieldDef {
+        kind: FieldKind::Title,
+        aliases: &["title"],
+        description: "Keyword in the file name",
+    },
+    FieldDef {
+        kind: FieldKind::Body,
+        aliases: &["body", "content"],
+        description: "Keyword in the file content",
+    },
+    FieldDef {
+        kind: FieldKind::Tags,
+        aliases: &["tag", "tags"],
+        description: "Keyword in the AI-generated tags",
+    },
+    FieldDef {
+        kind: FieldKind::Phrase,
+        aliases: &["phrase"],
+        description: "Exact phrase, `~N` allows N positions between words",
+    },
+    FieldDef {
+        kind: FieldKind::Fuzzy,
+        aliases: &["fuzzy"],
+        description: "Typo-tolerant keyword",
     },
     FieldDef {
         aliases: &["r", "re", "regex", "regexp"],
@@ -195,6 +530,16 @@ pub static FIELD_DEFINITIONS: &[FieldDef] = &[
         aliases: &["glob", "name", "filename", "file"],
         description: "Glob/filename pattern",
     },
+    FieldDef {
+        kind: FieldKind::Extension,
+        aliases: &["ext", "extension"],
+        description: "File extension",
+    },
+    FieldDef {
+        kind: FieldKind::FileType,
+        aliases: &["type", "kind", "filetype"],
+        description: "File type group (document, code, ...) or extension",
+    },
     FieldDef {
         kind: FieldKind::AccessTime,
         aliases: &["atime", "access", "accessed"],
@@ -215,10 +560,45 @@ pub static FIELD_DEFINITIONS: &[FieldDef] = &[
         aliases: &["s", "size", "bytes"],
         description: "File size range",
     },
+    FieldDef {
+        kind: FieldKind::Duplicates,
+        aliases: &["dupes", "dupe", "duplicates"],
+        description: "Files with (`yes`) or without (`no`) identical copies",
+    },
 ];
 
 /// Validate a parsed term and convert it to a semantic term
 fn validate_term(term: &ParsedTerm) -> ValidationResult<Term> {
+    let validated = validate_unboosted_term(term)?;
+    let Some((number, boost_span)) = &term.boost else {
+        return Ok(validated);
+    };
+    if !validated.is_text() {
+        return Err(ValidationError::new(
+            *boost_span,
+            ValidationErrorKind::InvalidBoost {
+                value: number.clone(),
+                reason: "only keywords, phrases and fuzzy terms accept `^`".into(),
+            },
+        ));
+    }
+    match number.parse::<f32>() {
+        Ok(boost) if boost.is_finite() && boost > 0.0 => Ok(Term::Boosted {
+            term: Box::new(validated),
+            boost,
+        }),
+        _ => Err(ValidationError::new(
+            *boost_span,
+            ValidationErrorKind::InvalidBoost {
+                value: number.clone(),
+                reason: "boost must be a positive number".into(),
+            },
+        )),
+    }
+}
+
+/// Validate a parsed term without its `^N` boost
+fn validate_unboosted_term(term: &ParsedTerm) -> ValidationResult<Term> {
     let (value, value_span) = &term.value;
     let value_string = value.to_string();
 
@@ -229,17 +609,86 @@ fn validate_term(term: &ParsedTerm) -> ValidationResult<Term> {
         ));
     }
 
-    match &term.field {
-        None => Ok(Term::KeyWord(value_string.into())),
+    let kind = match &term.field {
+        None => FieldKind::KeyWord,
         Some((field, field_span)) => match FieldDef::find_by_alias(field) {
-            Some(def) => def.kind.parse_value(value_string, *value_span),
-            None => Err(ValidationError::new(
-                *field_span,
-                ValidationErrorKind::UnknownField {
-                    field: field.clone(),
-                },
-            )),
+            Some(def) => def.kind,
+            None => {
+                return Err(ValidationError::new(
+                    *field_span,
+                    ValidationErrorKind::UnknownField {
+                        field: field.clone(),
+                    },
+                ));
+            }
         },
+    };
+
+    let quoted = matches!(value, ParsedTermValue::QuotedText(_));
+    let text_kind = matches!(
+        kind,
+        FieldKind::KeyWord | FieldKind::Title | FieldKind::Body | FieldKind::Tags
+    );
+    match (kind, &term.modifier) {
+        // Quoted keywords are phrases, `~N` turns them into proximity phrases
+        (_, None) if text_kind && quoted => Ok(Term::Phrase {
+            field: kind.text_field(),
+            text: value_string,
+            slop: 0,
+        }),
+        (_, Some(modifier)) if (text_kind && quoted) || kind == FieldKind::Phrase => {
+            Ok(Term::Phrase {
+                field: kind.text_field(),
+                text: value_string,
+                slop: parse_slop(modifier)?,
+            })
+        }
+        (_, Some(modifier)) if text_kind || kind == FieldKind::Fuzzy => Ok(Term::Fuzzy {
+            field: kind.text_field(),
+            word: value_string,
+            distance: parse_fuzzy_distance(modifier)?,
+        }),
+        (_, Some((digits, modifier_span))) => Err(ValidationError::new(
+            *modifier_span,
+            ValidationErrorKind::InvalidModifier {
+                value: digits.clone(),
+                reason: "only keywords, phrases and fuzzy terms accept `~`".into(),
+            },
+        )),
+        (kind, None) => kind.parse_value(value_string, *value_span),
+    }
+}
+
+/// Parse the slop of a proximity phrase `"a b"~N`
+fn parse_slop((digits, span): &Spanned<String>) -> ValidationResult<u32> {
+    if digits.is_empty() {
+        return Ok(DEFAULT_PHRASE_SLOP);
+    }
+    digits.parse().map_err(|_| {
+        ValidationError::new(
+            *span,
+            ValidationErrorKind::InvalidModifier {
+                value: digits.clone(),
+                reason: "proximity is too large".into(),
+            },
+        )
+    })
+}
+
+/// Parse the edit distance of a fuzzy term `word~N`
+fn parse_fuzzy_distance((digits, span): &Spanned<String>) -> ValidationResult<u8> {
+    if digits.is_empty() {
+        return Ok(DEFAULT_FUZZY_DISTANCE);
+    }
+    match digits.parse::<u8>() {
+        Ok(distance) if distance <= MAX_FUZZY_DISTANCE => Ok(distance),
+        _ => Err(ValidationError::new(
+            *span,
+            ValidationErrorKind::InvalidModifier {
+                value: digits.clone(),
+                reason: format!("edit distance must be at most {}", MAX_FUZZY_DISTANCE),
+            },
+        )),
     }
 }
 
@@ -256,6 +705,28 @@ fn validate_regex(pattern: String, span: Span) -> ValidationResult<Regex> {
     }
 }
 
+fn validate_extension(value: String, span: Span) -> ValidationResult<String> {
+    let ext = value.trim().trim_start_matches('.').to_lowercase();
+    if ext.is_empty() {
+        return Err(ValidationError::new(span, ValidationErrorKind::EmptyValue));
+    }
+    Ok(ext)
+}
+
+fn validate_duplicates(value: String, span: Span) -> ValidationResult<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "yes" | "y" | "true" | "only" => Ok(true),
+        "no" | "n" | "false" | "none" => Ok(false),
+        _ => Err(ValidationError::new(
+            span,
+            ValidationErrorKind::InvalidValue {
+                value,
+                reason: "expected `yes` or `no`".into(),
+            },
+        )),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -282,9 +753,113 @@ mod test {
     }
 
     #[test]
-    fn test_quoted_keyword() {
+    fn test_quoted_phrase() {
         let query = validate(r#""hello world""#).unwrap();
-        assert!(matches!(query, Query::Term(Term::KeyWord(k)) if k == "hello world"));
+        assert!(matches!(query, Query::Term(Term::Phrase { field: None, text, slop: 0 }) if text == "hello world"));
+    }
+
+    // ==================== Phrase / Fuzzy Tests ====================
+
+    #[rstest]
+    #[case(r#""a b"~3"#, 3)]
+    #[case(r#""a b"~"#, DEFAULT_PHRASE_SLOP)]
+    #[case(r#"phrase:"a b""#, 0)]
+    #[case(r#"phrase:"a b"~2"#, 2)]
+    fn test_proximity(#[case] input: &str, #[case] expected: u32) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::Phrase { field: None, text, slop }) if text == "a b" && slop == expected));
+    }
+
+    #[rstest]
+    #[case("word~1", 1)]
+    #[case("word~", DEFAULT_FUZZY_DISTANCE)]
+    #[case("word~0", 0)]
+    #[case("fuzzy:word", DEFAULT_FUZZY_DISTANCE)]
+    #[case(r#"fuzzy:"word"~2"#, 2)]
+    fn test_fuzzy(#[case] input: &str, #[case] expected: u8) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::Fuzzy { field: None, word, distance }) if word == "word" && distance == expected));
+    }
+
+    #[test]
+    fn test_invalid_modifiers() {
+        let err = validate("word~3").unwrap_err();
+        assert!(matches!(err.kind, ValidationErrorKind::InvalidModifier { ref value, .. } if value == "3"));
+        assert_eq!(err.range(), 4..6);
+
+        let err = validate(r#""a b"~99999999999"#).unwrap_err();
+        assert!(matches!(err.kind, ValidationErrorKind::InvalidModifier { .. }));
+
+        // Other fields do not accept modifiers
+        let err = validate(r#"name:"*.rs"~1"#).unwrap_err();
+        assert!(matches!(err.kind, ValidationErrorKind::InvalidModifier { .. }));
+        assert_eq!(err.range(), 11..13);
+    }
+
+    // ==================== Field-Scoped Text Tests ====================
+
+    #[rstest]
+    #[case("key:hello")]
+    #[case("keyword:hello")]
+    fn test_keyword_aliases(#[case] input: &str) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::KeyWord(k)) if k == "hello"));
+    }
+
+    #[rstest]
+    #[case("title:report", TextField::Title)]
+    #[case("body:report", TextField::Body)]
+    #[case("content:report", TextField::Body)]
+    #[case("tag:report", TextField::Tags)]
+    #[case("tags:report", TextField::Tags)]
+    fn test_field_text(#[case] input: &str, #[case] expected: TextField) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::FieldText { field, text }) if field == expected && text == "report"));
+    }
+
+    #[test]
+    fn test_field_scoped_phrase_and_fuzzy() {
+        let query = validate(r#"title:"annual report"~2"#).unwrap();
+        assert!(matches!(
+            query,
+            Query::Term(Term::Phrase { field: Some(TextField::Title), text, slop: 2 }) if text == "annual report"
+        ));
+
+        let query = validate("body:report~1").unwrap();
+        assert!(matches!(
+            query,
+            Query::Term(Term::Fuzzy { field: Some(TextField::Body), word, distance: 1 }) if word == "report"
+        ));
+
+        let query = validate(r#"key:"a b""#).unwrap();
+        assert!(matches!(query, Query::Term(Term::Phrase { field: None, slop: 0, .. })));
+    }
+
+    // ==================== Boost Tests ====================
+
+    #[rstest]
+    #[case("word^2", 2.0)]
+    #[case("title:report^0.5", 0.5)]
+    #[case(r#""a b"~2^1.5"#, 1.5)]
+    #[case("body:word~1^3", 3.0)]
+    fn test_boost(#[case] input: &str, #[case] expected: f32) {
+        let query = validate(input).unwrap();
+        let Query::Term(Term::Boosted { term, boost }) = query else {
+            panic!("Expected boosted term");
+        };
+        assert_eq!(boost, expected);
+        assert!(term.is_text());
+    }
+
+    #[rstest]
+    #[case("word^", 4..5)]
+    #[case("word^0", 4..6)]
+    #[case("word^1.2.3", 4..10)]
+    #[case(r#"size:">1MB"^2"#, 11..13)]
+    fn test_invalid_boost(#[case] input: &str, #[case] range: std::ops::Range<usize>) {
+        let err = validate(input).unwrap_err();
+        assert!(matches!(err.kind, ValidationErrorKind::InvalidBoost { .. }));
+        assert_eq!(err.range(), range);
     }
 
     #[test]
@@ -323,6 +898,41 @@ mod test {
         assert!(matches!(query, Query::Term(Term::Glob(g)) if g == "*.rs"));
     }
 
+    #[rstest]
+    #[case("ext:rs")]
+    #[case("extension:rs")]
+    #[case("ext:.RS")]
+    fn test_extension_aliases(#[case] input: &str) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::Extension(e)) if e == "rs"));
+    }
+
+    #[rstest]
+    #[case("type:code", "code")]
+    #[case("kind:Document", "document")]
+    #[case("filetype:.pdf", "pdf")]
+    fn test_file_type_aliases(#[case] input: &str, #[case] expected: &str) {
+        let query = validate(input).unwrap();
+        assert!(matches!(query, Query::Term(Term::FileType(t)) if t == expected));
+    }
+
+    #[rstest]
+    #[case("dupes:yes", true)]
+    #[case("dupe:TRUE", true)]
+    #[case("duplicates:no", false)]
+    fn test_duplicates_aliases(#[case] input: &str, #[case] expected: bool) {
+        let query = validate(input).unwrap();
+        assert_eq!(query, Query::Term(Term::Duplicates(expected)));
+    }
+
+    #[test]
+    fn test_invalid_duplicates_value() {
+        assert!(matches!(
+            validate_err("dupes:maybe"),
+            ValidationErrorKind::InvalidValue { value, .. } if value == "maybe"
+        ));
+    }
+
     #[rstest]
     #[case("atime:>1d")]
     #[case("access:>1d")]
@@ -534,6 +1144,42 @@ mod test {
         assert_eq!(err.range(), 5..10);
     }
 
+    #[test]
+    fn test_collects_every_error() {
+        let (query, diagnostics) = crate::check_query("size:abc AND (foo:bar OR ) mtime:xyz");
+        assert!(query.is_none());
+        let errors: Vec<_> = diagnostics.errors.iter().map(|e| (e.range(), &e.kind)).collect();
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(&errors[0], (r, ValidationErrorKind::InvalidSizeSpec { .. }) if *r == (5..8)));
+        assert!(matches!(&errors[1], (r, ValidationErrorKind::UnknownField { .. }) if *r == (14..17)));
+        assert!(matches!(&errors[2], (r, ValidationErrorKind::UnexpectedToken { .. }) if *r == (25..26)));
+        assert!(matches!(&errors[3], (r, ValidationErrorKind::InvalidTimeSpec { .. }) if *r == (33..36)));
+
+        // `validate_query` still returns the first error
+        let parsed = parse_query("size:abc mtime:xyz").unwrap();
+        assert!(matches!(validate_query(&parsed).unwrap_err().kind, ValidationErrorKind::InvalidSizeSpec { .. }));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let (query, diagnostics) = crate::check_query("regex:a* r:a+ root:/ root:/no/such/dir");
+        assert!(query.is_some());
+        assert!(diagnostics.errors.is_empty());
+        assert_eq!(
+            diagnostics.warnings,
+            vec![
+                ValidationWarning::new(
+                    (6..8).into(),
+                    ValidationWarningKind::BroadRegex { pattern: "a*".into() },
+                ),
+                ValidationWarning::new(
+                    (26..38).into(),
+                    ValidationWarningKind::RootNotFound { path: "/no/such/dir".into() },
+                ),
+            ]
+        );
+    }
+
     #[rstest]
     #[case(ValidationErrorKind::UnknownField { field: "x".into() }, "unknown field")]
     #[case(ValidationErrorKind::InvalidRegex { pattern: "[".into(), reason: "err".into() }, "invalid regex")]
@@ -542,6 +1188,12 @@ mod test {
     #[case(ValidationErrorKind::InvalidSizeSpec { value: "bad".into(), reason: "err".into() }, "invalid size")]
     #[case(ValidationErrorKind::EmptyValue, "empty value")]
     #[case(ValidationErrorKind::InvalidRange { reason: "err".into() }, "invalid range")]
+    #[case(ValidationErrorKind::InvalidModifier { value: "3".into(), reason: "err".into() }, "invalid modifier '~3'")]
+    #[case(ValidationErrorKind::InvalidBoost { value: "0".into(), reason: "err".into() }, "invalid boost '^0'")]
+    #[case(ValidationErrorKind::InvalidValue { value: "x".into(), reason: "err".into() }, "invalid value 'x'")]
+    #[case(ValidationErrorKind::InvalidToken { token: "&".into() }, "invalid token '&'")]
+    #[case(ValidationErrorKind::UnexpectedToken { found: None, expected: vec!["value".into()] }, "unexpected end of query, expected value")]
+    #[case(ValidationErrorKind::UnexpectedToken { found: Some(")".into()), expected: vec![] }, "unexpected ')'")]
     fn test_error_kind_display(
         #[case] kind: ValidationErrorKind,
         #[case] expected_substr: &str,
@@ -589,8 +1241,8 @@ mod test {
             min: None,
             max: None,
         };
-        assert!(range.contains(0));
-        assert!(range.contains(u64::MAX));
+        assert!(range.contains(i64::MIN));
+        assert!(range.contains(i64::MAX));
     }
 
     // ==================== SizeRange Tests ====================
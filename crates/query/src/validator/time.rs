@@ -1,46 +1,50 @@
 use super::{Span, ValidationError, ValidationErrorKind, ValidationResult};
-use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+};
 
 const ABSOLUTE_DATE_FORMAT_LEN: usize = 10;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TimeRange {
-    /// Minimum timestamp (inclusive), None means no lower bound
-    pub min: Option<u64>,
-    /// Maximum timestamp (inclusive), None means no upper bound
-    pub max: Option<u64>,
+    /// Minimum Unix timestamp (inclusive), None means no lower bound. Times
+    /// before 1970 are negative
+    pub min: Option<i64>,
+    /// Maximum Unix timestamp (inclusive), None means no upper bound
+    pub max: Option<i64>,
 }
 
 impl TimeRange {
-    pub fn at_least(min: u64) -> Self {
+    pub fn at_least(min: i64) -> Self {
         Self {
             min: Some(min),
             max: None,
         }
     }
 
-    pub fn at_most(max: u64) -> Self {
+    pub fn at_most(max: i64) -> Self {
         Self {
             min: None,
             max: Some(max),
         }
     }
 
-    pub fn between(min: u64, max: u64) -> Self {
+    pub fn between(min: i64, max: i64) -> Self {
         Self {
             min: Some(min),
             max: Some(max),
         }
     }
 
-    pub fn contains(&self, value: u64) -> bool {
+    pub fn contains(&self, value: i64) -> bool {
         let above_min = self.min.map_or(true, |min| value >= min);
         let below_max = self.max.map_or(true, |max| value <= max);
         above_min && below_max
     }
 }
 
-/// Validate a time specification with optional operators.
+/// Validate a time specification with optional operators, relative to the
+/// current local time.
 ///
 /// Supported formats:
 /// - `>1d` - more recent than 1 day ago
@@ -51,7 +55,25 @@ impl TimeRange {
 /// - `2024-01-01..2024-12-31` - date range
 /// - `..1w` - up to 1 week ago (no lower bound)
 /// - `1d..` - from 1 day ago onwards (no upper bound)
+/// - `today`, `yesterday`, `this-week`, `last-week`, `this-month`,
+///   `last-month`, `this-year`, `last-year` - calendar periods, also written
+///   with spaces or underscores (`"last week"`)
+/// - `2024-03`, `2024` - a calendar month or year
+///
+/// Dates and periods cover the whole day, month or year: `>` and `<` exclude
+/// it, `>=`, `<=` and ranges include it. Weeks start on Monday.
 pub fn validate_time(value: String, span: Span) -> ValidationResult<TimeRange> {
+    validate_time_at(value, span, &Local::now())
+}
+
+/// Validate a time specification as if the current time were `now`.
+///
+/// Calendar periods are aligned to days in the time zone of `now`.
+pub fn validate_time_at<Tz: TimeZone>(
+    value: String,
+    span: Span,
+    now: &DateTime<Tz>,
+) -> ValidationResult<TimeRange> {
     let value = value.trim();
     
     if value.is_empty() {
@@ -63,13 +85,13 @@ pub fn validate_time(value: String, span: Span) -> ValidationResult<TimeRange> {
         let min = if left.trim().is_empty() {
             None
         } else {
-            Some(parse_time_value(left.trim(), span)?)
+            Some(parse_time_value(left.trim(), span, now)?.start)
         };
 
         let max = if right.trim().is_empty() {
             None
         } else {
-            Some(parse_time_value(right.trim(), span)?)
+            Some(parse_time_value(right.trim(), span, now)?.end)
         };
 
         if let (Some(min_val), Some(max_val)) = (min, max) {
@@ -86,44 +108,50 @@ pub fn validate_time(value: String, span: Span) -> ValidationResult<TimeRange> {
     }
 
     if let Some(rest) = value.strip_prefix(">=") {
-        let ts = parse_time_value(rest.trim(), span)?;
-        return Ok(TimeRange::at_least(ts));
+        let period = parse_time_value(rest.trim(), span, now)?;
+        return Ok(TimeRange::at_least(period.start));
     }
 
     if let Some(rest) = value.strip_prefix("<=") {
-        let ts = parse_time_value(rest.trim(), span)?;
-        return Ok(TimeRange::at_most(ts));
+        let period = parse_time_value(rest.trim(), span, now)?;
+        return Ok(TimeRange::at_most(period.end));
     }
 
     if let Some(rest) = value.strip_prefix('>') {
-        let ts = parse_time_value(rest.trim(), span)?;
+        let period = parse_time_value(rest.trim(), span, now)?;
         // 对于相对时间（如 7d），ts 已经是 "now - 7天"
         // >7d 表示访问时间晚于7天前，即 atime > (now - 7天)
-        return Ok(TimeRange::at_least(ts));
+        return Ok(TimeRange::at_least(period.end.saturating_add(1)));
     }
 
     if let Some(rest) = value.strip_prefix('<') {
-        let ts = parse_time_value(rest.trim(), span)?;
+        let period = parse_time_value(rest.trim(), span, now)?;
         // 对于相对时间（如 7d），ts 已经是 "now - 7天"
         // <7d 表示访问时间早于7天前，即 atime < (now - 7天)
-        return Ok(TimeRange::at_most(ts));
+        return Ok(TimeRange::at_most(period.start.saturating_sub(1)));
     }
 
-    if let Some(rest) = value.strip_prefix('=') {
-        let ts = parse_time_value(rest.trim(), span)?;
-        return Ok(TimeRange::between(ts, ts));
-    }
+    let value = value.strip_prefix('=').unwrap_or(value);
+    // for dates and periods, match the entire period; for timestamps, exact match
+    let period = parse_time_value(value.trim(), span, now)?;
+    Ok(TimeRange::between(period.start, period.end))
+}
+
+/// A resolved time value: a single instant, or a calendar period covering
+/// `start..=end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Period {
+    start: i64,
+    end: i64,
+}
 
-    // Plain value
-    // for dates, match the entire day; for timestamps, exact match
-    let ts = parse_time_value(value, span)?;
-    if value.len() <= ABSOLUTE_DATE_FORMAT_LEN {
-        return Ok(TimeRange::between(ts, ts.saturating_add(86399)));
+impl Period {
+    fn instant(ts: i64) -> Self {
+        Self { start: ts, end: ts }
     }
-    Ok(TimeRange::between(ts, ts))
 }
 
-fn parse_time_value(s: &str, span: Span) -> ValidationResult<u64> {
+fn parse_time_value<Tz: TimeZone>(s: &str, span: Span, now: &DateTime<Tz>) -> ValidationResult<Period> {
     let s = s.trim();
     if s.is_empty() {
         return Err(ValidationError::new(
@@ -135,19 +163,29 @@ fn parse_time_value(s: &str, span: Span) -> ValidationResult<u64> {
         ));
     }
 
-    // Try as unix timestamp first (plain number)
-    if let Ok(ts) = s.parse::<u64>() {
-        return Ok(ts);
+    // A four-digit number is a year rather than a timestamp in 1970
+    if let Some(period) = parse_calendar_period(s, &now.timezone()) {
+        return Ok(period);
+    }
+
+    // Try as unix timestamp (plain number, negative before 1970)
+    if let Ok(ts) = s.parse::<i64>() {
+        return Ok(Period::instant(ts));
     }
 
     // Try as relative time (e.g., "1d", "2h", "30min")
-    if let Some(ts) = parse_relative_time(s) {
-        return Ok(ts);
+    if let Some(ts) = parse_relative_time(s, now) {
+        return Ok(Period::instant(ts));
+    }
+
+    // Try as a named period (e.g., "today", "last week")
+    if let Some(period) = parse_named_period(s, now) {
+        return Ok(period);
     }
 
     // Try as absolute date/time
-    if let Some(ts) = parse_absolute_time(s) {
-        return Ok(ts);
+    if let Some(period) = parse_absolute_time(s, &now.timezone()) {
+        return Ok(period);
     }
 
     Err(ValidationError::new(
@@ -155,15 +193,17 @@ fn parse_time_value(s: &str, span: Span) -> ValidationResult<u64> {
         ValidationErrorKind::InvalidTimeSpec {
             value: s.to_string(),
             reason: "unrecognized time format. Expected: relative (1d, 2h, 1w), \
-                     absolute (2024-01-15), or unix timestamp"
+                     absolute (2024-01-15, 2024-03, 2024), named period (today, \
+                     yesterday, last-week, this-month), or unix timestamp"
                 .to_string(),
         },
     ))
 }
 
 /// Parse a relative time string like "1d", "2h", "30min" into a Unix timestamp.
-/// The result is `now - duration`, representing a point in the past.
-fn parse_relative_time(s: &str) -> Option<u64> {
+/// The result is `now - duration`, representing a point in the past. Months
+/// and years are calendar months and years.
+fn parse_relative_time<Tz: TimeZone>(s: &str, now: &DateTime<Tz>) -> Option<i64> {
     let s = s.trim();
 
     // Find where digits end
@@ -180,21 +220,96 @@ fn parse_relative_time(s: &str) -> Option<u64> {
         "h" | "hr" | "hrs" | "hour" | "hours" => num.checked_mul(3600)?,
         "d" | "day" | "days" => num.checked_mul(86400)?,
         "w" | "wk" | "wks" | "week" | "weeks" => num.checked_mul(604800)?,
-        "mo" | "mon" | "month" | "months" => num.checked_mul(2592000)?, // 30 days
-        "y" | "yr" | "yrs" | "year" | "years" => num.checked_mul(31536000)?, // 365 days
+        "mo" | "mon" | "month" | "months" => return months_ago(now, num),
+        "y" | "yr" | "yrs" | "year" | "years" => return months_ago(now, num.checked_mul(12)?),
         _ => return None,
     };
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .ok()?
-        .as_secs();
+    now.timestamp().checked_sub(i64::try_from(seconds).ok()?)
+}
 
-    Some(now.saturating_sub(seconds))
+/// The same local time `months` calendar months before `now`, clamped to the
+/// end of shorter months
+fn months_ago<Tz: TimeZone>(now: &DateTime<Tz>, months: u64) -> Option<i64> {
+    let months = Months::new(u32::try_from(months).ok()?);
+    Some(now.clone().checked_sub_months(months)?.timestamp())
 }
 
-/// Parse an absolute date/time string into a Unix timestamp
-fn parse_absolute_time(s: &str) -> Option<u64> {
+/// Parse a named calendar period like "today" or "last week"
+fn parse_named_period<Tz: TimeZone>(s: &str, now: &DateTime<Tz>) -> Option<Period> {
+    let name = s
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+
+    let today = now.date_naive();
+    let monday = today.checked_sub_days(Days::new(today.weekday().num_days_from_monday().into()))?;
+    let month = today.with_day(1)?;
+    let year = NaiveDate::from_ymd_opt(today.year(), 1, 1)?;
+    let (first, next) = match name.as_str() {
+        "today" => (today, today.checked_add_days(Days::new(1))?),
+        "yesterday" => (today.checked_sub_days(Days::new(1))?, today),
+        "this-week" => (monday, monday.checked_add_days(Days::new(7))?),
+        "last-week" => (monday.checked_sub_days(Days::new(7))?, monday),
+        "this-month" => (month, month.checked_add_months(Months::new(1))?),
+        "last-month" => (month.checked_sub_months(Months::new(1))?, month),
+        "this-year" => (year, year.checked_add_months(Months::new(12))?),
+        "last-year" => (year.checked_sub_months(Months::new(12))?, year),
+        _ => return None,
+    };
+    local_period(&now.timezone(), first, next)
+}
+
+/// Parse a calendar year like "2024" or month like "2024-03"
+fn parse_calendar_period<Tz: TimeZone>(s: &str, tz: &Tz) -> Option<Period> {
+    let is_number = |s: &str, len: std::ops::RangeInclusive<usize>| {
+        len.contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit())
+    };
+
+    let (year, month) = match s.split_once(['-', '/', '.']) {
+        Some((year, month)) => (year, Some(month)),
+        None => (s, None),
+    };
+    if !is_number(year, 4..=4) {
+        return None;
+    }
+    let year: i32 = year.parse().ok()?;
+
+    let (first, next) = match month {
+        None => (
+            NaiveDate::from_ymd_opt(year, 1, 1)?,
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        ),
+        Some(month) if is_number(month, 1..=2) => {
+            let first = NaiveDate::from_ymd_opt(year, month.parse().ok()?, 1)?;
+            (first, first.checked_add_months(Months::new(1))?)
+        }
+        Some(_) => return None,
+    };
+    local_period(tz, first, next)
+}
+
+/// The period from the start of local day `first` up to the start of `next`
+fn local_period<Tz: TimeZone>(tz: &Tz, first: NaiveDate, next: NaiveDate) -> Option<Period> {
+    let start = start_of_day(tz, first)?;
+    let end = start_of_day(tz, next)?.checked_sub(1)?;
+    Some(Period { start, end })
+}
+
+/// Unix timestamp of the first instant of a local day
+fn start_of_day<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> Option<i64> {
+    // Where midnight falls into a DST gap the day starts an hour later
+    let start = [NaiveTime::MIN, NaiveTime::from_hms_opt(1, 0, 0)?]
+        .into_iter()
+        .find_map(|time| tz.from_local_datetime(&date.and_time(time)).earliest())?;
+    Some(start.timestamp())
+}
+
+/// Parse an absolute date or date/time string. A date covers the whole local
+/// day, a date/time is a single instant
+fn parse_absolute_time<Tz: TimeZone>(s: &str, tz: &Tz) -> Option<Period> {
     let s = s.trim();
 
     let date_formats = ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"];
@@ -211,8 +326,8 @@ fn parse_absolute_time(s: &str) -> Option<u64> {
                 ];
                 for fmt in fmts {
                     if let Ok(dt) = NaiveDateTime::parse_from_str(s, &fmt) {
-                        if let Some(local) = Local.from_local_datetime(&dt).single() {
-                            return Some(local.timestamp() as u64);
+                        if let Some(local) = tz.from_local_datetime(&dt).single() {
+                            return Some(Period::instant(local.timestamp()));
                         }
                     }
                 }
@@ -222,11 +337,7 @@ fn parse_absolute_time(s: &str) -> Option<u64> {
         // Date only
         for fmt in &date_formats {
             if let Ok(date) = NaiveDate::parse_from_str(s, fmt) {
-                if let Some(dt) = date.and_hms_opt(0, 0, 0) {
-                    if let Some(local) = Local.from_local_datetime(&dt).single() {
-                        return Some(local.timestamp() as u64);
-                    }
-                }
+                return local_period(tz, date, date.checked_add_days(Days::new(1))?);
             }
         }
     }
@@ -237,6 +348,7 @@ fn parse_absolute_time(s: &str) -> Option<u64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::FixedOffset;
     use rstest::*;
 
     #[fixture]
@@ -248,6 +360,24 @@ mod tests {
         }
     }
 
+    /// Friday 2024-03-15 10:30 in UTC+8
+    #[fixture]
+    fn now() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 15, 10, 30, 0)
+            .unwrap()
+    }
+
+    /// Timestamp of a local time in the time zone of `now`
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        now().timezone().with_ymd_and_hms(y, m, d, h, min, 0).unwrap().timestamp()
+    }
+
+    fn midnight(y: i32, m: u32, d: u32) -> i64 {
+        local(y, m, d, 0, 0)
+    }
+
     // ==================== parse_relative_time ====================
 
     #[rstest]
@@ -260,7 +390,7 @@ mod tests {
     #[case("1y")]
     fn test_parse_relative_time_valid(#[case] input: &str) {
         assert!(
-            parse_relative_time(input).is_some(),
+            parse_relative_time(input, &Local::now()).is_some(),
             "Expected '{}' to parse as valid relative time",
             input
         );
@@ -274,7 +404,7 @@ mod tests {
     #[case("1x")]
     fn test_parse_relative_time_invalid(#[case] input: &str) {
         assert!(
-            parse_relative_time(input).is_none(),
+            parse_relative_time(input, &Local::now()).is_none(),
             "Expected '{}' to be invalid relative time",
             input
         );
@@ -289,7 +419,7 @@ mod tests {
     #[case("2024-01-15 10:30:00")]
     fn test_parse_absolute_time_valid(#[case] input: &str) {
         assert!(
-            parse_absolute_time(input).is_some(),
+            parse_absolute_time(input, &Local).is_some(),
             "Expected '{}' to parse as valid absolute time",
             input
         );
@@ -301,12 +431,86 @@ mod tests {
     #[case("not-a-date")]
     fn test_parse_absolute_time_invalid(#[case] input: &str) {
         assert!(
-            parse_absolute_time(input).is_none(),
+            parse_absolute_time(input, &Local).is_none(),
             "Expected '{}' to be invalid absolute time",
             input
         );
     }
 
+    #[rstest]
+    #[case("30min", local(2024, 3, 15, 10, 0))]
+    #[case("2d", local(2024, 3, 13, 10, 30))]
+    #[case("1mo", local(2024, 2, 15, 10, 30))]
+    #[case("1y", local(2023, 3, 15, 10, 30))]
+    fn test_parse_relative_time_calendar(#[case] input: &str, #[case] expected: i64) {
+        assert_eq!(parse_relative_time(input, &now()), Some(expected));
+    }
+
+    #[test]
+    fn test_relative_month_clamps_to_month_end() {
+        let end_of_march = now().timezone().with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap();
+        assert_eq!(parse_relative_time("1mo", &end_of_march), Some(local(2024, 2, 29, 12, 0)));
+    }
+
+    // ==================== calendar periods ====================
+
+    #[rstest]
+    #[case("today", midnight(2024, 3, 15), midnight(2024, 3, 16))]
+    #[case("Yesterday", midnight(2024, 3, 14), midnight(2024, 3, 15))]
+    #[case("this-week", midnight(2024, 3, 11), midnight(2024, 3, 18))]
+    #[case("last week", midnight(2024, 3, 4), midnight(2024, 3, 11))]
+    #[case("this-month", midnight(2024, 3, 1), midnight(2024, 4, 1))]
+    #[case("last_month", midnight(2024, 2, 1), midnight(2024, 3, 1))]
+    #[case("this-year", midnight(2024, 1, 1), midnight(2025, 1, 1))]
+    #[case("last-year", midnight(2023, 1, 1), midnight(2024, 1, 1))]
+    #[case("2024-02", midnight(2024, 2, 1), midnight(2024, 3, 1))]
+    #[case("2023/12", midnight(2023, 12, 1), midnight(2024, 1, 1))]
+    #[case("2024", midnight(2024, 1, 1), midnight(2025, 1, 1))]
+    #[case("2024-03-15", midnight(2024, 3, 15), midnight(2024, 3, 16))]
+    fn test_calendar_periods(
+        #[case] input: String,
+        #[case] start: i64,
+        #[case] next: i64,
+        test_span: Span,
+        now: DateTime<FixedOffset>,
+    ) {
+        assert_eq!(
+            validate_time_at(input, test_span, &now).unwrap(),
+            TimeRange::between(start, next - 1)
+        );
+    }
+
+    #[rstest]
+    #[case(">yesterday", TimeRange::at_least(midnight(2024, 3, 15)))]
+    #[case(">=yesterday", TimeRange::at_least(midnight(2024, 3, 14)))]
+    #[case("<this-month", TimeRange::at_most(midnight(2024, 3, 1) - 1))]
+    #[case("<=2024-02", TimeRange::at_most(midnight(2024, 3, 1) - 1))]
+    #[case("=today", TimeRange::between(midnight(2024, 3, 15), midnight(2024, 3, 16) - 1))]
+    #[case("2023..2024-02", TimeRange::between(midnight(2023, 1, 1), midnight(2024, 3, 1) - 1))]
+    #[case("last-month..today", TimeRange::between(midnight(2024, 2, 1), midnight(2024, 3, 16) - 1))]
+    #[case("1704067200", TimeRange::between(1704067200, 1704067200))]
+    #[case("-86400..0", TimeRange::between(-86400, 0))]
+    #[case("1969-07-20", TimeRange::between(midnight(1969, 7, 20), midnight(1969, 7, 21) - 1))]
+    #[case("<1960", TimeRange::at_most(midnight(1960, 1, 1) - 1))]
+    #[case(">=60y", TimeRange::at_least(local(1964, 3, 15, 10, 30)))]
+    fn test_period_operators(
+        #[case] input: String,
+        #[case] expected: TimeRange,
+        test_span: Span,
+        now: DateTime<FixedOffset>,
+    ) {
+        assert_eq!(validate_time_at(input, test_span, &now).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("2024-13")]
+    #[case("2024-03-")]
+    #[case("next-week")]
+    #[case("today..yesterday")]
+    fn test_invalid_periods(#[case] input: String, test_span: Span, now: DateTime<FixedOffset>) {
+        assert!(validate_time_at(input, test_span, &now).is_err());
+    }
+
     // ==================== validate_time ====================
 
     #[rstest]
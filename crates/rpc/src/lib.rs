@@ -21,6 +21,10 @@ pub trait World {
     ) -> (Uuid, SResult<FetchResults>);
 
     async fn cancel_search(session_id: Uuid) -> (Uuid, SResult<()>);
+
+    /// Names of the `type:` groups configured on the server, the built-in
+    /// groups are not included
+    async fn file_type_groups() -> Vec<String>;
 }
 
 #[derive(Debug)]
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
+use std::fmt;
+use std::ops::Range;
 use std::path::PathBuf;
 use uuid::Uuid;
-use query::ValidationError;
+use query::Diagnostics;
 
 pub type SResult<T> = Result<T, SearchErrorKind>;
 
@@ -34,10 +36,65 @@ pub struct FetchSearchResultsRequest {
 pub enum SearchErrorKind {
     SessionNotExists,
     SessionAlreadyCancelled,
-    InvalidQuery(ValidationError),
+    /// The query is not valid DSL syntax. `span`, `expected` and `found`
+    /// describe the first syntax error
+    ParseError {
+        /// Byte range of the offending input in the query string
+        span: Range<usize>,
+        /// What the parser would have accepted at `span`
+        expected: Vec<String>,
+        /// The offending input, `None` at the end of the query
+        found: Option<String>,
+        /// Every syntax and validation error in the query, with its warnings
+        diagnostics: Diagnostics,
+    },
+    /// Every validation error in a syntactically valid query, with its warnings
+    InvalidQuery(Diagnostics),
+    /// The search failed while running
+    ExecutionError {
+        message: String,
+    },
+    /// The search index could not be read
+    IndexUnavailable,
+    /// The search did not finish within the server's time limit
+    Timeout,
+    /// The query has to check more files than the server allows, and needs
+    /// keywords or filters to narrow it down
+    TooManyResults,
     OperateOnAlreadyFailedSearch,
 }
 
+impl fmt::Display for SearchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchErrorKind::SessionNotExists => write!(f, "Search session does not exist"),
+            SearchErrorKind::SessionAlreadyCancelled => write!(f, "Search was cancelled"),
+            SearchErrorKind::ParseError { span, expected, found, diagnostics } => {
+                write!(f, "Syntax error at {}: ", span.start)?;
+                match found {
+                    Some(found) => write!(f, "unexpected '{}'", found)?,
+                    None => write!(f, "unexpected end of query")?,
+                }
+                if !expected.is_empty() {
+                    write!(f, ", expected {}", expected.join(", "))?;
+                }
+                if diagnostics.errors.len() > 1 {
+                    write!(f, " (and {} more errors)", diagnostics.errors.len() - 1)?;
+                }
+                Ok(())
+            }
+            SearchErrorKind::InvalidQuery(diagnostics) => write!(f, "Invalid query: {}", diagnostics),
+            SearchErrorKind::ExecutionError { message } => write!(f, "Search failed: {}", message),
+            SearchErrorKind::IndexUnavailable => write!(f, "Search index is unavailable"),
+            SearchErrorKind::Timeout => write!(f, "Search timed out, try a more specific query"),
+            SearchErrorKind::TooManyResults => {
+                write!(f, "Too many files to check, add keywords or filters to narrow the query")
+            }
+            SearchErrorKind::OperateOnAlreadyFailedSearch => write!(f, "Search has already failed"),
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SearchStatus {
@@ -59,18 +116,33 @@ pub struct FetchResults {
     pub has_more: bool,
 }
 
+/// Location of a passage inside a file, 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreviewLocation {
+    /// Page number for paged documents (PDF)
+    Page(u32),
+    /// Starting line number for other documents
+    Line(u32),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchHit {
     pub file_path: PathBuf,
     /// Score is only available for natural language search 
     pub score: Option<f32>,
     pub preview: String,
+    /// Byte ranges in `preview` that matched the query, sorted by start
+    pub highlights: Vec<Range<usize>>,
+    /// Where the preview passage is located in the file, if the preview is a
+    /// matched passage
+    pub preview_location: Option<PreviewLocation>,
     pub file_size: u64,
-    /// FIXME: BUG here. Should be i64. User's computer can set local time to a
-    /// a time before Unix Epoch
-    /// Access time since Unix Epoch
-    pub access_time: u64,
-    pub modified_time: u64,
-    pub create_time: u64
+    /// Access time in seconds since Unix Epoch, negative before 1970. `None`
+    /// when the file system does not record it
+    pub access_time: Option<i64>,
+    pub modified_time: Option<i64>,
+    pub create_time: Option<i64>,
+    /// Other indexed files with identical content, collapsed into this hit
+    pub duplicates: Vec<PathBuf>,
 }
 
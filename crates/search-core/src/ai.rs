@@ -84,6 +84,36 @@ impl BertModel {
         Ok(vec)
     }
 
+    /// 获取查询的归一化向量
+    ///
+    /// bge 模型检索短查询时需要在查询前加检索指令，文档端不加。
+    pub fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let mut embedding = self.get_embedding(&format!("{}{}", QUERY_INSTRUCTION, query))?;
+        normalize(&mut embedding);
+        Ok(embedding)
+    }
+
+    /// 批量计算文档向量
+    ///
+    /// 输入为每个文档的段落，每个段落一个归一化向量，返回结果与输入一一对应。
+    /// 没有段落的文档返回空列表。
+    pub fn embed_documents(&self, passages: &[Vec<String>]) -> Result<Vec<Vec<Vec<f32>>>> {
+        let inputs: Vec<&str> = passages.iter().flatten().map(String::as_str).collect();
+
+        let mut embeddings = self.get_embeddings(&inputs)?.into_iter();
+        Ok(passages.iter()
+            .map(|doc_passages| {
+                doc_passages.iter()
+                    .filter_map(|_| embeddings.next())
+                    .map(|mut embedding| {
+                        normalize(&mut embedding);
+                        embedding
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
     /// 优化查询（意图识别）
     pub fn refine_query(&self, origin_query: &str) -> String {
         if origin_query.chars().count() < 4 {
@@ -112,6 +142,95 @@ impl BertModel {
 
     /// 提取关键词
     pub fn extract_keywords(&self, text: &str, top_k: usize) -> Result<Vec<String>> {
+        Ok(self.extract_keywords_batch(&[text], top_k)?.pop().unwrap_or_default())
+    }
+
+    /// 批量提取关键词
+    ///
+    /// 所有文档及其候选词一起编码，按长度分组做批量前向计算，
+    /// 返回结果与输入一一对应。
+    pub fn extract_keywords_batch(&self, texts: &[&str], top_k: usize) -> Result<Vec<Vec<String>>> {
+        let prepared: Vec<(String, Vec<String>)> = texts.iter()
+            .map(|text| self.keyword_candidates(text))
+            .collect();
+
+        // 每个文档依次放入文档文本和它的候选词
+        let mut inputs: Vec<&str> = Vec::new();
+        for (doc_text, candidates) in &prepared {
+            if !candidates.is_empty() {
+                inputs.push(doc_text);
+                inputs.extend(candidates.iter().map(String::as_str));
+            }
+        }
+        let embeddings = self.get_embeddings(&inputs)?;
+
+        let mut offset = 0;
+        let mut results = Vec::with_capacity(prepared.len());
+        for (_, candidates) in &prepared {
+            if candidates.is_empty() {
+                results.push(vec![]);
+                continue;
+            }
+            let doc_vec = &embeddings[offset];
+            let cand_vecs = &embeddings[offset + 1..offset + 1 + candidates.len()];
+            offset += 1 + candidates.len();
+            results.push(rank_keywords(doc_vec, candidates, cand_vecs, top_k));
+        }
+
+        Ok(results)
+    }
+
+    /// 批量获取文本的向量表示
+    ///
+    /// 先按文本长度排序再分批，减少补齐带来的无效计算，返回顺序与输入一致。
+    pub fn get_embeddings(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut order: Vec<usize> = (0..texts.len()).collect();
+        order.sort_by_key(|&i| texts[i].len());
+
+        let mut embeddings = vec![Vec::new(); texts.len()];
+        for chunk in order.chunks(EMBEDDING_BATCH_SIZE) {
+            let batch: Vec<&str> = chunk.iter().map(|&i| texts[i]).collect();
+            for (&i, embedding) in chunk.iter().zip(self.forward_batch(&batch)?) {
+                embeddings[i] = embedding;
+            }
+        }
+        Ok(embeddings)
+    }
+
+    /// 对一批文本做一次前向计算，补齐部分用 attention mask 屏蔽
+    fn forward_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true).map_err(anyhow::Error::msg)?;
+        let max_len = encodings.iter().map(|e| e.get_ids().len()).max().unwrap_or(0);
+
+        let mut ids = Vec::with_capacity(texts.len() * max_len);
+        let mut type_ids = Vec::with_capacity(texts.len() * max_len);
+        let mut mask = Vec::with_capacity(texts.len() * max_len);
+        for encoding in &encodings {
+            let padding = max_len - encoding.get_ids().len();
+            ids.extend_from_slice(encoding.get_ids());
+            ids.extend(std::iter::repeat_n(0, padding));
+            type_ids.extend_from_slice(encoding.get_type_ids());
+            type_ids.extend(std::iter::repeat_n(0, padding));
+            mask.extend_from_slice(encoding.get_attention_mask());
+            mask.extend(std::iter::repeat_n(0, padding));
+        }
+
+        let shape = (texts.len(), max_len);
+        let token_ids = Tensor::from_vec(ids, shape, &self.device)?;
+        let token_type_ids = Tensor::from_vec(type_ids, shape, &self.device)?;
+        let attention_mask = Tensor::from_vec(mask, shape, &self.device)?;
+
+        let output = self.model.forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+        let cls_embeddings = output.i((.., 0))?;
+        Ok(cls_embeddings.to_vec2()?)
+    }
+
+    /// 截断文本并用 jieba 分词得到候选关键词
+    fn keyword_candidates(&self, text: &str) -> (String, Vec<String>) {
         let truncated_text = if text.chars().count() > 512 {
             text.chars().take(512).collect::<String>()
         } else {
@@ -129,33 +248,40 @@ impl BertModel {
         tracing::debug!("[BERT 分词] 输入: '{}'", truncated_text);
         tracing::debug!("[BERT 分词] 候选词: {:?} (共 {} 个)", candidates, candidates.len());
 
-        if candidates.is_empty() {
-            return Ok(vec![]);
-        }
+        (truncated_text, candidates)
+    }
+}
 
-        let doc_vec = self.get_embedding(&truncated_text)?;
+/// 单次前向计算的最大文本数
+const EMBEDDING_BATCH_SIZE: usize = 32;
 
-        let mut scored_candidates: Vec<(f32, String)> = Vec::new();
+/// bge 中文模型的查询检索指令
+const QUERY_INSTRUCTION: &str = "为这个句子生成表示以用于检索相关文章：";
 
-        for candidate in &candidates {
-            if let Ok(cand_vec) = self.get_embedding(candidate) {
-                let score = cosine_similarity(&doc_vec, &cand_vec);
-                scored_candidates.push((score, candidate.clone()));
-            }
-        }
+/// 将向量归一化为单位长度，之后余弦相似度等于点积
+fn normalize(vec: &mut [f32]) {
+    let norm = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vec.iter_mut().for_each(|x| *x /= norm);
+    }
+}
 
-        scored_candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+/// 按与文档向量的相似度排序候选词，取前 `top_k` 个
+fn rank_keywords(doc_vec: &[f32], candidates: &[String], cand_vecs: &[Vec<f32>], top_k: usize) -> Vec<String> {
+    let mut scored_candidates: Vec<(f32, &String)> = candidates.iter()
+        .zip(cand_vecs)
+        .map(|(candidate, cand_vec)| (cosine_similarity(doc_vec, cand_vec), candidate))
+        .collect();
 
-        let keywords = scored_candidates.into_iter()
-            .take(top_k)
-            .map(|(score, word)| {
-                tracing::debug!("[BERT 关键词] {} (相似度: {:.4})", word, score);
-                word
-            })
-            .collect();
+    scored_candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
 
-        Ok(keywords)
-    }
+    scored_candidates.into_iter()
+        .take(top_k)
+        .map(|(score, word)| {
+            tracing::debug!("[BERT 关键词] {} (相似度: {:.4})", word, score);
+            word.clone()
+        })
+        .collect()
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
@@ -186,3 +312,19 @@ impl KeywordExtractor {
         self.model.refine_query(query)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let mut vec = vec![3.0, 4.0];
+        normalize(&mut vec);
+        assert_eq!(vec, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,630 @@
+// search-core/src/ann.rs
+//! 向量近似最近邻索引模块
+//!
+//! 基于 HNSW（分层可导航小世界图）的内存索引，每个文档分块向量是一个节点，
+//! 查询结果按文档聚合（取最相似的分块，并返回该分块的序号）。删除只打墓碑标记，墓碑过多时重建图。
+//!
+//! 索引定期序列化到缓存目录；向量本身以 `EmbeddingCache` 为准，
+//! 启动时会与缓存对账，补齐快照之后的增删。
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AnnConfig;
+
+/// 索引文件格式版本，结构变化时递增
+const FORMAT_VERSION: u32 = 2;
+
+/// 节点的最高层数
+const MAX_LEVEL: usize = 16;
+
+/// 墓碑节点超过一半且节点数不少于该值时重建图
+const COMPACT_MIN_NODES: usize = 1024;
+
+/// 两次自动保存之间的最短间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 按距离排序的候选节点
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// 查询命中的文档
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentMatch {
+    pub path: String,
+    /// 最相似分块的相似度
+    pub score: f32,
+    /// 最相似分块在文档中的序号
+    pub chunk: usize,
+}
+
+/// 图节点：一个文档分块的向量及其各层邻居
+#[derive(Serialize, Deserialize)]
+struct Node {
+    doc: u32,
+    /// 分块在文档中的序号
+    chunk: u32,
+    vector: Vec<f32>,
+    /// `neighbors[level]` 为该层的邻居，长度即节点层数 + 1
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+/// 文档条目
+#[derive(Serialize, Deserialize)]
+struct DocEntry {
+    path: String,
+    /// 生成向量时的内容哈希，用于与缓存对账
+    content_hash: u64,
+    nodes: Vec<u32>,
+}
+
+/// HNSW 图
+///
+/// 向量需预先归一化，距离定义为 `1 - 点积`。
+#[derive(Serialize, Deserialize)]
+pub struct HnswIndex {
+    m: usize,
+    ef_construction: usize,
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    docs: Vec<DocEntry>,
+    doc_ids: HashMap<String, u32>,
+    deleted_nodes: usize,
+    rng_state: u64,
+}
+
+impl HnswIndex {
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        Self {
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            nodes: Vec::new(),
+            entry_point: None,
+            docs: Vec::new(),
+            doc_ids: HashMap::new(),
+            deleted_nodes: 0,
+            rng_state: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    /// 已索引的文档数
+    pub fn len(&self) -> usize {
+        self.doc_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.doc_ids.is_empty()
+    }
+
+    /// 文档生成向量时的内容哈希，未索引时返回 `None`
+    pub fn content_hash(&self, path: &str) -> Option<u64> {
+        self.doc_ids.get(path).map(|&doc| self.docs[doc as usize].content_hash)
+    }
+
+    /// 所有已索引的文档路径
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.doc_ids.keys().map(String::as_str)
+    }
+
+    /// 插入或替换文档的分块向量
+    pub fn insert(&mut self, path: &str, content_hash: u64, chunks: &[Vec<f32>]) {
+        self.remove(path);
+
+        let doc = self.docs.len() as u32;
+        self.docs.push(DocEntry {
+            path: path.to_string(),
+            content_hash,
+            nodes: Vec::with_capacity(chunks.len()),
+        });
+        self.doc_ids.insert(path.to_string(), doc);
+
+        for (chunk, vector) in chunks.iter().enumerate() {
+            let node = self.insert_node(doc, chunk as u32, vector.clone());
+            self.docs[doc as usize].nodes.push(node);
+        }
+    }
+
+    /// 删除文档，其节点保留在图中用于导航，直到下次重建
+    pub fn remove(&mut self, path: &str) {
+        let Some(doc) = self.doc_ids.remove(path) else {
+            return;
+        };
+        for node in std::mem::take(&mut self.docs[doc as usize].nodes) {
+            self.nodes[node as usize].deleted = true;
+            self.deleted_nodes += 1;
+        }
+
+        if self.nodes.len() >= COMPACT_MIN_NODES && self.deleted_nodes * 2 > self.nodes.len() {
+            self.compact();
+        }
+    }
+
+    /// 查询与 `query` 最相似的 `k` 个文档，按相似度降序
+    ///
+    /// 只返回 `filter` 接受的文档。过滤掉的文档较多时会逐步扩大搜索范围，
+    /// 直到凑够 `k` 个文档或遍历完整个图。
+    pub fn search(&self, query: &[f32], k: usize, ef: usize, filter: &dyn Fn(&str) -> bool) -> Vec<DocumentMatch> {
+        let Some(entry) = self.entry_point else {
+            return vec![];
+        };
+        if k == 0 {
+            return vec![];
+        }
+
+        let top = self.nodes[entry as usize].neighbors.len() - 1;
+        let mut entry = entry;
+        for level in (1..=top).rev() {
+            entry = self.search_layer(query, &[entry], 1, level)[0].node;
+        }
+
+        let mut allowed: HashMap<u32, bool> = HashMap::new();
+        let mut ef = ef.max(k);
+        loop {
+            let mut best: HashMap<u32, (f32, u32)> = HashMap::new();
+            for candidate in self.search_layer(query, &[entry], ef, 0) {
+                let node = &self.nodes[candidate.node as usize];
+                if node.deleted {
+                    continue;
+                }
+                let accepted = *allowed
+                    .entry(node.doc)
+                    .or_insert_with(|| filter(&self.docs[node.doc as usize].path));
+                if !accepted {
+                    continue;
+                }
+                let similarity = 1.0 - candidate.distance;
+                // 候选按距离升序，文档第一次出现的分块即最相似的分块
+                best.entry(node.doc).or_insert((similarity, node.chunk));
+            }
+
+            if best.len() >= k || ef >= self.nodes.len() {
+                let mut results: Vec<DocumentMatch> = best.into_iter()
+                    .map(|(doc, (score, chunk))| DocumentMatch {
+                        path: self.docs[doc as usize].path.clone(),
+                        score,
+                        chunk: chunk as usize,
+                    })
+                    .collect();
+                results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+                results.truncate(k);
+                return results;
+            }
+            ef = (ef * 2).min(self.nodes.len());
+        }
+    }
+
+    fn insert_node(&mut self, doc: u32, chunk: u32, vector: Vec<f32>) -> u32 {
+        let level = self.random_level();
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            doc,
+            chunk,
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(entry) = self.entry_point else {
+            self.entry_point = Some(id);
+            return id;
+        };
+
+        let query = self.nodes[id as usize].vector.clone();
+        let top = self.nodes[entry as usize].neighbors.len() - 1;
+
+        // 从顶层贪心下降到节点所在的最高层
+        let mut entry_points = vec![entry];
+        for level in (level + 1..=top).rev() {
+            entry_points = vec![self.search_layer(&query, &entry_points, 1, level)[0].node];
+        }
+
+        for level in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &entry_points, self.ef_construction, level);
+            let selected: Vec<u32> = found.iter().map(|c| c.node).take(self.m).collect();
+
+            let max_connections = self.max_connections(level);
+            for &neighbor in &selected {
+                self.nodes[neighbor as usize].neighbors[level].push(id);
+                if self.nodes[neighbor as usize].neighbors[level].len() > max_connections {
+                    self.prune(neighbor, level, max_connections);
+                }
+            }
+            self.nodes[id as usize].neighbors[level] = selected;
+            entry_points = found.iter().map(|c| c.node).collect();
+        }
+
+        if level > top {
+            self.entry_point = Some(id);
+        }
+        id
+    }
+
+    /// 在指定层从入口节点出发搜索最近的 `ef` 个节点，按距离升序返回
+    fn search_layer(&self, query: &[f32], entry_points: &[u32], ef: usize, level: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut results: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        for &node in entry_points {
+            let candidate = Candidate { distance: self.distance(query, node), node };
+            candidates.push(Reverse(candidate));
+            results.push(candidate);
+            if results.len() > ef {
+                results.pop();
+            }
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let furthest = results.peek().map_or(f32::INFINITY, |c| c.distance);
+            if current.distance > furthest && results.len() >= ef {
+                break;
+            }
+
+            let neighbors = self.nodes[current.node as usize].neighbors.get(level);
+            for &neighbor in neighbors.into_iter().flatten() {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbor);
+                let furthest = results.peek().map_or(f32::INFINITY, |c| c.distance);
+                if results.len() < ef || distance < furthest {
+                    let candidate = Candidate { distance, node: neighbor };
+                    candidates.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+
+    /// 只保留距离最近的 `max_connections` 个邻居
+    fn prune(&mut self, node: u32, level: usize, max_connections: usize) {
+        let vector = &self.nodes[node as usize].vector;
+        let mut neighbors: Vec<Candidate> = self.nodes[node as usize].neighbors[level].iter()
+            .map(|&neighbor| Candidate { distance: self.distance(vector, neighbor), node: neighbor })
+            .collect();
+        neighbors.sort();
+        neighbors.truncate(max_connections);
+        self.nodes[node as usize].neighbors[level] = neighbors.into_iter().map(|c| c.node).collect();
+    }
+
+    /// 丢弃墓碑节点，用存活文档的向量重建图
+    fn compact(&mut self) {
+        tracing::info!("[向量索引] 重建图: {} 个节点中 {} 个已删除", self.nodes.len(), self.deleted_nodes);
+
+        let mut rebuilt = Self::new(self.m, self.ef_construction);
+        rebuilt.rng_state = self.rng_state;
+        for (path, &doc) in &self.doc_ids {
+            let entry = &self.docs[doc as usize];
+            let chunks: Vec<Vec<f32>> = entry.nodes.iter()
+                .map(|&node| self.nodes[node as usize].vector.clone())
+                .collect();
+            rebuilt.insert(path, entry.content_hash, &chunks);
+        }
+        *self = rebuilt;
+    }
+
+    fn max_connections(&self, level: usize) -> usize {
+        if level == 0 { self.m * 2 } else { self.m }
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        let vector = &self.nodes[node as usize].vector;
+        1.0 - query.iter().zip(vector).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    /// 按 `1 / ln(m)` 的指数分布随机选择节点层数
+    fn random_level(&mut self) -> usize {
+        // xorshift64*，固定种子使索引构建可复现
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        let uniform = 1.0 - bits as f64 / (1u64 << 53) as f64;
+
+        let level = -uniform.ln() / (self.m as f64).ln();
+        (level as usize).min(MAX_LEVEL)
+    }
+}
+
+/// 持久化的向量索引
+///
+/// 内部加读写锁，可在线程间共享。修改后按间隔自动保存，释放时也会保存。
+pub struct VectorIndex {
+    file: PathBuf,
+    ef_search: usize,
+    inner: RwLock<HnswIndex>,
+    dirty: AtomicBool,
+    last_save: Mutex<Instant>,
+}
+
+impl VectorIndex {
+    /// 打开索引文件，不存在、损坏或构建参数与配置不一致时新建空索引
+    pub fn open(file: &Path, config: &AnnConfig) -> Self {
+        let index = match Self::load(file) {
+            Ok(Some(index)) if index.m == config.m.max(2) && index.ef_construction == config.ef_construction.max(1) => {
+                tracing::info!("[向量索引] 已加载 {} 个文档: {:?}", index.len(), file);
+                index
+            }
+            Ok(Some(_)) => {
+                tracing::info!("[向量索引] 构建参数已变化，重新构建");
+                HnswIndex::new(config.m, config.ef_construction)
+            }
+            Ok(None) => HnswIndex::new(config.m, config.ef_construction),
+            Err(e) => {
+                tracing::warn!("[向量索引] 读取失败，重新构建: {}", e);
+                HnswIndex::new(config.m, config.ef_construction)
+            }
+        };
+
+        Self {
+            file: file.to_path_buf(),
+            ef_search: config.ef_search.max(1),
+            inner: RwLock::new(index),
+            dirty: AtomicBool::new(false),
+            last_save: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn load(file: &Path) -> Result<Option<HnswIndex>> {
+        if !file.exists() {
+            return Ok(None);
+        }
+        let mut reader = BufReader::new(File::open(file)?);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != FORMAT_VERSION {
+            bail!("索引格式版本 {} 与当前版本 {} 不一致", version, FORMAT_VERSION);
+        }
+        Ok(Some(bincode::deserialize_from(&mut reader)?))
+    }
+
+    /// 已索引的文档数
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 文档生成向量时的内容哈希，未索引时返回 `None`
+    pub fn content_hash(&self, path: &str) -> Option<u64> {
+        self.inner.read().unwrap().content_hash(path)
+    }
+
+    /// 所有已索引的文档路径
+    pub fn paths(&self) -> Vec<String> {
+        self.inner.read().unwrap().paths().map(String::from).collect()
+    }
+
+    /// 插入或替换文档的分块向量
+    pub fn insert(&self, path: &str, content_hash: u64, chunks: &[Vec<f32>]) {
+        self.inner.write().unwrap().insert(path, content_hash, chunks);
+        self.mark_dirty();
+    }
+
+    /// 删除文档
+    pub fn remove(&self, path: &str) {
+        self.inner.write().unwrap().remove(path);
+        self.mark_dirty();
+    }
+
+    /// 查询最相似的 `k` 个文档，只返回 `filter` 接受的文档
+    pub fn search(&self, query: &[f32], k: usize, filter: &dyn Fn(&str) -> bool) -> Vec<DocumentMatch> {
+        self.inner.read().unwrap().search(query, k, self.ef_search, filter)
+    }
+
+    /// 有未保存的修改时写入索引文件（先写临时文件再替换）
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty.swap(false, AtomicOrdering::SeqCst) {
+            return Ok(());
+        }
+
+        let result = (|| -> Result<()> {
+            if let Some(parent) = self.file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let tmp = self.file.with_extension("tmp");
+            let mut writer = BufWriter::new(File::create(&tmp)?);
+            let index = self.inner.read().unwrap();
+            bincode::serialize_into(&mut writer, &FORMAT_VERSION)?;
+            bincode::serialize_into(&mut writer, &*index)?;
+            drop(index);
+            writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+            fs::rename(&tmp, &self.file)?;
+            Ok(())
+        })();
+
+        *self.last_save.lock().unwrap() = Instant::now();
+        if result.is_err() {
+            self.dirty.store(true, AtomicOrdering::SeqCst);
+        }
+        result
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.store(true, AtomicOrdering::SeqCst);
+        let due = self.last_save.lock().unwrap().elapsed() >= SAVE_INTERVAL;
+        if due && let Err(e) = self.save() {
+            tracing::warn!("[向量索引] 保存失败: {}", e);
+        }
+    }
+}
+
+impl Drop for VectorIndex {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            tracing::warn!("[向量索引] 保存失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 生成确定性的归一化随机向量
+    fn random_vectors(count: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                let mut vector: Vec<f32> = (0..dim)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect();
+                let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+                vector.iter_mut().for_each(|x| *x /= norm);
+                vector
+            })
+            .collect()
+    }
+
+    fn exact_top_k(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let mut scored: Vec<(String, f32)> = vectors.iter().enumerate()
+            .map(|(i, v)| (format!("/doc{}", i), v.iter().zip(query).map(|(a, b)| a * b).sum()))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(path, _)| path).collect()
+    }
+
+    fn build(vectors: &[Vec<f32>]) -> HnswIndex {
+        let mut index = HnswIndex::new(16, 100);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&format!("/doc{}", i), i as u64, std::slice::from_ref(vector));
+        }
+        index
+    }
+
+    #[test]
+    fn test_recall_against_exact_search() {
+        let vectors = random_vectors(2000, 32, 7);
+        let index = build(&vectors);
+
+        let mut hits = 0;
+        let queries = random_vectors(20, 32, 42);
+        for query in &queries {
+            let expected = exact_top_k(&vectors, query, 10);
+            let found = index.search(query, 10, 64, &|_| true);
+            hits += found.iter().filter(|m| expected.contains(&m.path)).count();
+        }
+        let recall = hits as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.9, "recall too low: {}", recall);
+    }
+
+    #[test]
+    fn test_remove_and_replace() {
+        let vectors = random_vectors(200, 16, 3);
+        let mut index = build(&vectors);
+
+        let found = index.search(&vectors[5], 1, 32, &|_| true);
+        assert_eq!(found[0].path, "/doc5");
+
+        index.remove("/doc5");
+        assert_eq!(index.content_hash("/doc5"), None);
+        let found = index.search(&vectors[5], 5, 32, &|_| true);
+        assert!(found.iter().all(|m| m.path != "/doc5"));
+
+        // 替换为另一个向量后，按新向量可以查到
+        index.insert("/doc6", 99, std::slice::from_ref(&vectors[5]));
+        assert_eq!(index.content_hash("/doc6"), Some(99));
+        assert_eq!(index.search(&vectors[5], 1, 32, &|_| true)[0].path, "/doc6");
+        assert_eq!(index.len(), 199);
+    }
+
+    #[test]
+    fn test_search_reports_best_chunk() {
+        let vectors = random_vectors(3, 16, 9);
+        let mut index = HnswIndex::new(16, 100);
+        index.insert("/doc", 1, &vectors);
+
+        let found = index.search(&vectors[2], 1, 16, &|_| true);
+        assert_eq!(found[0].path, "/doc");
+        assert_eq!(found[0].chunk, 2);
+        assert!((found[0].score - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_filtered_search_returns_only_accepted_docs() {
+        let vectors = random_vectors(500, 16, 11);
+        let index = build(&vectors);
+
+        // 只接受极少数文档时也要找全
+        let accepted = ["/doc3", "/doc250", "/doc499"];
+        let found = index.search(&vectors[0], 10, 16, &|path| accepted.contains(&path));
+        let mut paths: Vec<&str> = found.iter().map(|m| m.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/doc250", "/doc3", "/doc499"]);
+    }
+
+    #[test]
+    fn test_compaction_keeps_live_docs() {
+        let vectors = random_vectors(COMPACT_MIN_NODES + 100, 8, 5);
+        let mut index = build(&vectors);
+        // 删除超过一半的文档时触发重建
+        for i in 0..=vectors.len() / 2 {
+            index.remove(&format!("/doc{}", i));
+        }
+
+        assert_eq!(index.deleted_nodes, 0);
+        assert_eq!(index.nodes.len(), index.len());
+        let last = vectors.len() - 1;
+        assert_eq!(index.search(&vectors[last], 1, 32, &|_| true)[0].path, format!("/doc{}", last));
+    }
+
+    #[test]
+    fn test_persistence_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("vectors.bin");
+        let config = AnnConfig::default();
+        let vectors = random_vectors(50, 8, 1);
+
+        {
+            let index = VectorIndex::open(&file, &config);
+            for (i, vector) in vectors.iter().enumerate() {
+                index.insert(&format!("/doc{}", i), i as u64, std::slice::from_ref(vector));
+            }
+        }
+
+        let index = VectorIndex::open(&file, &config);
+        assert_eq!(index.len(), 50);
+        assert_eq!(index.content_hash("/doc7"), Some(7));
+
+        // 构建参数变化后丢弃旧索引
+        let changed = AnnConfig { m: 8, ..AnnConfig::default() };
+        drop(index);
+        assert!(VectorIndex::open(&file, &changed).is_empty());
+    }
+}
@@ -1,18 +1,29 @@
 // search-core/src/cache.rs
 //! Embedding 缓存模块
-//! 使用 sled 存储关键词提取结果，避免重复 AI 计算
+//! 使用 sled 存储关键词提取结果和文档向量，避免重复 AI 计算，
+//! 同一数据库中还存放已索引文件的目录（见 `catalog` 模块）
 
 use anyhow::Result;
-use sled::Db;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use sled::{Db, Tree};
+use std::collections::HashSet;
 use std::path::Path;
 use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::ann::{DocumentMatch, VectorIndex};
+use crate::catalog::{hash_file, FileCatalog, FileStat};
+use crate::config::AnnConfig;
 
 /// Embedding 缓存管理器
 pub struct EmbeddingCache {
     db: Db,
+    /// 文档段落向量，独立于关键词和元数据存储
+    embeddings: Tree,
+    /// 文档向量的近似最近邻索引（未启用时为 `None`）
+    vectors: Option<VectorIndex>,
+    /// 已提交到索引的文件状态
+    catalog: FileCatalog,
 }
 
 /// 缓存条目：包含内容哈希和关键词
@@ -22,7 +33,14 @@ struct CacheEntry {
     keywords: Vec<String>,
 }
 
-/// 文件元数据缓存条目
+/// 文档向量缓存条目：每个段落一个归一化向量（按段落序号排列）
+#[derive(Serialize, Deserialize)]
+struct EmbeddingEntry {
+    content_hash: u64,
+    chunks: Vec<Vec<f32>>,
+}
+
+/// 旧版文件元数据缓存条目，仅用于导入文件目录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetaEntry {
     /// 文件大小（字节）
@@ -75,17 +93,33 @@ pub enum FileStatus {
 }
 
 impl EmbeddingCache {
-    /// 创建或打开缓存数据库
+    /// 创建或打开缓存数据库（使用默认的向量索引配置）
     pub fn new(cache_path: &Path) -> Result<Self> {
+        Self::open(cache_path, &AnnConfig::default())
+    }
+
+    /// 创建或打开缓存数据库，并加载缓存目录下的向量索引
+    pub fn open(cache_path: &Path, ann: &AnnConfig) -> Result<Self> {
         let db = sled::open(cache_path)?;
-        tracing::info!("Embedding 缓存已加载: {:?}", cache_path);
-        Ok(Self { db })
+        // 旧版本按固定长度分块计算的向量与段落不对应，直接丢弃
+        if db.drop_tree(Self::LEGACY_EMBEDDING_TREE)? {
+            tracing::info!("已清理旧版文档向量缓存");
+        }
+        let embeddings = db.open_tree(Self::EMBEDDING_TREE)?;
+        tracing::info!("Embedding 缓存已加载: {:?} ({} 个文档向量)", cache_path, embeddings.len());
+
+        let vectors = ann.enabled.then(|| VectorIndex::open(&cache_path.join(Self::VECTOR_INDEX_FILE), ann));
+        let catalog = FileCatalog::open(&db)?;
+        let cache = Self { db, embeddings, vectors, catalog };
+        cache.migrate_file_meta()?;
+        tracing::info!("文件目录已加载: {} 个文件", cache.catalog.len());
+        cache.sync_vector_index();
+        Ok(cache)
     }
 
+    /// 提取文本的内容哈希（xxh3-64，不随 Rust 版本变化）
     fn hash_content(content: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        hasher.finish()
+        xxh3_64(content.as_bytes())
     }
 
     /// 尝试从缓存获取关键词
@@ -115,9 +149,35 @@ impl EmbeddingCache {
         Ok(())
     }
 
-    /// 从缓存中删除指定文件的条目
+    /// 从缓存中删除指定文件的条目（关键词和文档向量）
     pub fn remove(&self, file_path: &str) -> Result<()> {
         self.db.remove(file_path.as_bytes())?;
+        self.embeddings.remove(file_path.as_bytes())?;
+        if let Some(vectors) = &self.vectors {
+            vectors.remove(file_path);
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// 把文件的关键词、文档向量和文件目录记录迁移到新路径
+    /// 
+    /// 文件移动后内容不变，按内容哈希缓存的结果可以直接复用，无需重新计算。
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        if let Some(data) = self.db.remove(from.as_bytes())? {
+            self.db.insert(to.as_bytes(), data)?;
+        }
+        self.catalog.rename(from, to)?;
+        if let Some(data) = self.embeddings.remove(from.as_bytes())? {
+            if let Some(vectors) = &self.vectors {
+                vectors.remove(from);
+                if let Ok(entry) = bincode::deserialize::<EmbeddingEntry>(&data) {
+                    vectors.insert(to, entry.content_hash, &entry.chunks);
+                }
+            }
+            self.embeddings.insert(to.as_bytes(), data)?;
+            self.embeddings.flush()?;
+        }
         self.db.flush()?;
         Ok(())
     }
@@ -129,84 +189,283 @@ impl EmbeddingCache {
         (count, size)
     }
     
-    // ============== 文件元数据缓存 ==============
+    // ============== 文档向量缓存 ==============
     
-    const META_PREFIX: &'static str = "meta:";
+    const EMBEDDING_TREE: &'static str = "passage_embeddings";
+    
+    /// 按固定长度分块的旧版向量（与段落不对应）
+    const LEGACY_EMBEDDING_TREE: &'static str = "embeddings";
+    
+    /// 向量索引文件名（位于缓存目录内）
+    const VECTOR_INDEX_FILE: &'static str = "vector_index.bin";
     
-    fn meta_key(file_path: &str) -> Vec<u8> {
-        format!("{}{}", Self::META_PREFIX, file_path).into_bytes()
+    /// 检查文档向量是否已缓存且与当前内容一致
+    pub fn has_embeddings(&self, file_path: &str, content: &str) -> bool {
+        self.get_embeddings(file_path, content).is_some()
     }
     
-    /// 检查文件状态（基于元数据，不读取文件内容）
-    pub fn check_file_status(&self, file_path: &str, path: &Path) -> FileStatus {
-        let current = match FileMetaEntry::from_path(path) {
-            Ok(meta) => meta,
-            Err(_) => return FileStatus::New,
+    /// 获取文档的分块向量（内容变化后返回 `None`）
+    pub fn get_embeddings(&self, file_path: &str, content: &str) -> Option<Vec<Vec<f32>>> {
+        let data = self.embeddings.get(file_path.as_bytes()).ok()??;
+        let entry = bincode::deserialize::<EmbeddingEntry>(&data).ok()?;
+        (entry.content_hash == Self::hash_content(content)).then_some(entry.chunks)
+    }
+    
+    /// 存储文档的分块向量
+    pub fn set_embeddings(&self, file_path: &str, content: &str, chunks: Vec<Vec<f32>>) -> Result<()> {
+        let entry = EmbeddingEntry {
+            content_hash: Self::hash_content(content),
+            chunks,
         };
         
-        let key = Self::meta_key(file_path);
-        match self.db.get(&key) {
-            Ok(Some(data)) => {
-                match bincode::deserialize::<FileMetaEntry>(&data) {
-                    Ok(cached) => {
-                        if cached.needs_reindex(&current) {
-                            FileStatus::Modified
-                        } else {
-                            FileStatus::Unchanged
-                        }
-                    }
-                    Err(_) => FileStatus::New,
-                }
-            }
-            Ok(None) => FileStatus::New,
-            Err(_) => FileStatus::New,
+        let data = bincode::serialize(&entry)?;
+        self.embeddings.insert(file_path.as_bytes(), data)?;
+        self.embeddings.flush()?;
+        if let Some(vectors) = &self.vectors {
+            vectors.insert(file_path, entry.content_hash, &entry.chunks);
         }
+        Ok(())
     }
     
-    /// 保存文件元数据到缓存
-    pub fn save_file_meta(&self, file_path: &str, path: &Path) -> Result<()> {
-        let mut meta = FileMetaEntry::from_path(path)?;
-        meta.indexed = true;
-        
-        let key = Self::meta_key(file_path);
-        let data = bincode::serialize(&meta)?;
-        self.db.insert(key, data)?;
-        self.db.flush()?;
-        Ok(())
+    /// 遍历所有文档向量，返回 `(文件路径, 分块向量)`
+    pub fn iter_embeddings(&self) -> impl Iterator<Item = (String, Vec<Vec<f32>>)> + '_ {
+        self.iter_embedding_entries().map(|(path, entry)| (path, entry.chunks))
     }
     
-    /// 获取缓存的文件元数据
-    pub fn get_file_meta(&self, file_path: &str) -> Option<FileMetaEntry> {
-        let key = Self::meta_key(file_path);
-        self.db.get(&key).ok()?.and_then(|data| {
-            bincode::deserialize::<FileMetaEntry>(&data).ok()
+    fn iter_embedding_entries(&self) -> impl Iterator<Item = (String, EmbeddingEntry)> + '_ {
+        self.embeddings.iter().filter_map(|result| {
+            let (key, data) = result.ok()?;
+            let path = String::from_utf8(key.to_vec()).ok()?;
+            let entry = bincode::deserialize::<EmbeddingEntry>(&data).ok()?;
+            Some((path, entry))
         })
     }
     
-    /// 删除文件元数据缓存
+    /// 查找与查询向量最相似的 `k` 个文档，按相似度降序
+    /// 
+    /// 文档得分取其所有段落中的最大相似度，并返回该段落的序号，只返回 `filter` 接受的文档。
+    /// 启用向量索引时走近似搜索，否则线性扫描所有文档向量。
+    pub fn nearest_documents(&self, query: &[f32], k: usize, filter: &dyn Fn(&str) -> bool) -> Vec<DocumentMatch> {
+        if let Some(vectors) = &self.vectors {
+            return vectors.search(query, k, filter);
+        }
+        
+        // 向量均已归一化，余弦相似度即点积
+        let mut scored: Vec<DocumentMatch> = self.iter_embeddings()
+            .filter(|(path, _)| filter(path))
+            .filter_map(|(path, chunks)| {
+                chunks.iter()
+                    .map(|chunk| chunk.iter().zip(query).map(|(a, b)| a * b).sum::<f32>())
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                    .map(|(chunk, score)| DocumentMatch { path, score, chunk })
+            })
+            .collect();
+        
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+        scored.truncate(k);
+        scored
+    }
+    
+    /// 立即保存向量索引
+    pub fn save_vector_index(&self) -> Result<()> {
+        match &self.vectors {
+            Some(vectors) => vectors.save(),
+            None => Ok(()),
+        }
+    }
+    
+    /// 使向量索引与缓存中的文档向量一致
+    /// 
+    /// 索引快照可能落后于缓存（例如上次退出前未保存），按内容哈希补齐差异。
+    fn sync_vector_index(&self) {
+        let Some(vectors) = &self.vectors else {
+            return;
+        };
+        
+        let mut stored = HashSet::new();
+        let mut updated = 0;
+        for (path, entry) in self.iter_embedding_entries() {
+            if vectors.content_hash(&path) != Some(entry.content_hash) {
+                vectors.insert(&path, entry.content_hash, &entry.chunks);
+                updated += 1;
+            }
+            stored.insert(path);
+        }
+        
+        let mut removed = 0;
+        for path in vectors.paths() {
+            if !stored.contains(&path) {
+                vectors.remove(&path);
+                removed += 1;
+            }
+        }
+        
+        if updated + removed > 0 {
+            tracing::info!("[向量索引] 已同步: 更新 {} 个文档, 删除 {} 个文档", updated, removed);
+            if let Err(e) = vectors.save() {
+                tracing::warn!("[向量索引] 保存失败: {}", e);
+            }
+        }
+    }
+    
+    // ============== 文件目录 ==============
+    
+    /// 旧版文件元数据的键前缀（已迁移到 `FileCatalog`）
+    const META_PREFIX: &'static str = "meta:";
+    
+    /// 已索引文件的持久化目录
+    pub fn catalog(&self) -> &FileCatalog {
+        &self.catalog
+    }
+    
+    /// 检查文件状态（基于文件目录，不读取文件内容）
+    pub fn check_file_status(&self, file_path: &str, path: &Path) -> FileStatus {
+        match FileStat::from_path(path) {
+            Ok(stat) => self.catalog.status(file_path, &stat),
+            Err(_) => FileStatus::New,
+        }
+    }
+    
+    /// 文件提交到索引后记录到文件目录
+    /// 
+    /// `stat` 为读取文件内容前的文件状态，`content_hash` 为提取文本时计算的原始字节哈希
+    /// （见 `catalog::hash_file`），未知时为 0。
+    pub fn save_file_meta(&self, file_path: &str, stat: &FileStat, content_hash: u64) -> Result<()> {
+        self.catalog.record(file_path, stat, content_hash)
+    }
+    
+    /// 从文件目录中删除文件
     pub fn remove_file_meta(&self, file_path: &str) -> Result<()> {
-        let key = Self::meta_key(file_path);
-        self.db.remove(key)?;
+        self.catalog.remove(file_path)
+    }
+    
+    /// 把旧版 `meta:` 元数据导入文件目录
+    /// 
+    /// 旧版只记录了大小和秒级修改时间，文件自记录后未变化时按当前状态导入，
+    /// 其余的留给下次扫描重新索引。
+    fn migrate_file_meta(&self) -> Result<()> {
+        let mut legacy_keys = Vec::new();
+        let mut imported = 0;
+        for item in self.db.scan_prefix(Self::META_PREFIX.as_bytes()) {
+            let (key, data) = item?;
+            legacy_keys.push(key.clone());
+            let Some(file_path) = std::str::from_utf8(&key).ok().and_then(|k| k.strip_prefix(Self::META_PREFIX)) else {
+                continue;
+            };
+            let path = Path::new(file_path);
+            let (Ok(legacy), Ok(current), Ok(stat)) = (
+                bincode::deserialize::<FileMetaEntry>(&data),
+                FileMetaEntry::from_path(path),
+                FileStat::from_path(path),
+            ) else {
+                continue;
+            };
+            if legacy.indexed && !legacy.needs_reindex(&current) {
+                self.catalog.record(file_path, &stat, hash_file(path).unwrap_or_default())?;
+                imported += 1;
+            }
+        }
+        if legacy_keys.is_empty() {
+            return Ok(());
+        }
+        
+        for key in &legacy_keys {
+            self.db.remove(key)?;
+        }
+        self.catalog.flush()?;
         self.db.flush()?;
+        tracing::info!("[文件目录] 已导入旧版文件元数据: {} / {} 个文件", imported, legacy_keys.len());
         Ok(())
     }
-    
-    /// 获取所有已缓存的文件路径
-    pub fn get_all_cached_paths(&self) -> Vec<String> {
-        let prefix = Self::META_PREFIX.as_bytes();
-        self.db.scan_prefix(prefix)
-            .filter_map(|result| {
-                result.ok().and_then(|(key, _)| {
-                    String::from_utf8(key.to_vec()).ok()
-                        .map(|s| s.strip_prefix(Self::META_PREFIX).unwrap_or(&s).to_string())
-                })
-            })
-            .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_follow_content_and_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EmbeddingCache::new(dir.path()).unwrap();
+        let chunks = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+
+        cache.set_embeddings("/a.txt", "hello", chunks.clone()).unwrap();
+        assert_eq!(cache.get_embeddings("/a.txt", "hello"), Some(chunks.clone()));
+        assert!(!cache.has_embeddings("/a.txt", "changed"));
+        assert_eq!(cache.iter_embeddings().collect::<Vec<_>>(), vec![("/a.txt".to_string(), chunks)]);
+
+        cache.remove("/a.txt").unwrap();
+        assert_eq!(cache.iter_embeddings().count(), 0);
     }
-    
-    /// 获取元数据缓存统计
-    pub fn meta_stats(&self) -> usize {
-        let prefix = Self::META_PREFIX.as_bytes();
-        self.db.scan_prefix(prefix).count()
+
+    #[test]
+    fn test_rename_moves_every_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = EmbeddingCache::new(&dir.path().join("cache")).unwrap();
+        let file = dir.path().join("b.txt");
+        std::fs::write(&file, "hello").unwrap();
+
+        cache.set_keywords("/a.txt", "hello", vec!["greeting".to_string()]).unwrap();
+        cache.set_embeddings("/a.txt", "hello", vec![vec![1.0, 0.0]]).unwrap();
+        cache.save_file_meta("/a.txt", &FileStat::from_path(&file).unwrap(), hash_file(&file).unwrap()).unwrap();
+        cache.rename("/a.txt", "/b.txt").unwrap();
+
+        assert_eq!(cache.get_keywords("/b.txt", "hello"), Some(vec!["greeting".to_string()]));
+        assert!(cache.has_embeddings("/b.txt", "hello"));
+        assert!(matches!(cache.check_file_status("/b.txt", &file), FileStatus::Unchanged));
+        assert!(cache.get_keywords("/a.txt", "hello").is_none());
+        assert!(!cache.has_embeddings("/a.txt", "hello"));
+        assert!(cache.catalog().get("/a.txt").is_none());
+        let found = cache.nearest_documents(&[1.0, 0.0], 2, &|_| true);
+        assert_eq!(found.iter().map(|m| m.path.as_str()).collect::<Vec<_>>(), vec!["/b.txt"]);
+    }
+
+    #[test]
+    fn test_legacy_file_meta_is_imported() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let file_path = file.to_string_lossy().to_string();
+        {
+            let db = sled::open(dir.path().join("cache")).unwrap();
+            let mut legacy = FileMetaEntry::from_path(&file).unwrap();
+            legacy.indexed = true;
+            db.insert(format!("meta:{}", file_path), bincode::serialize(&legacy).unwrap()).unwrap();
+            db.insert("meta:/missing.txt", bincode::serialize(&legacy).unwrap()).unwrap();
+            db.flush().unwrap();
+        }
+
+        // 未变化的文件导入目录，已不存在的文件丢弃，旧键全部清除
+        let cache = EmbeddingCache::new(&dir.path().join("cache")).unwrap();
+        assert!(matches!(cache.check_file_status(&file_path, &file), FileStatus::Unchanged));
+        assert_eq!(cache.catalog().paths(), vec![file_path]);
+        assert_eq!(cache.db.scan_prefix(EmbeddingCache::META_PREFIX.as_bytes()).count(), 0);
+    }
+
+    #[test]
+    fn test_vector_index_syncs_with_stored_embeddings() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let cache = EmbeddingCache::new(dir.path()).unwrap();
+            cache.set_embeddings("/a.txt", "a", vec![vec![1.0, 0.0]]).unwrap();
+            cache.set_embeddings("/b.txt", "b", vec![vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+        }
+
+        // 模拟索引快照丢失：重新打开时从缓存的向量重建
+        std::fs::remove_file(dir.path().join(EmbeddingCache::VECTOR_INDEX_FILE)).unwrap();
+        let cache = EmbeddingCache::new(dir.path()).unwrap();
+        let found = cache.nearest_documents(&[0.0, 1.0], 1, &|_| true);
+        assert_eq!(found, vec![DocumentMatch { path: "/b.txt".to_string(), score: 1.0, chunk: 1 }]);
+
+        let found = cache.nearest_documents(&[0.0, 1.0], 2, &|path| path != "/b.txt");
+        assert_eq!(found, vec![DocumentMatch { path: "/a.txt".to_string(), score: 0.0, chunk: 0 }]);
+
+        // 关闭向量索引时线性扫描，结果一致
+        let disabled = AnnConfig { enabled: false, ..AnnConfig::default() };
+        drop(cache);
+        let cache = EmbeddingCache::open(dir.path(), &disabled).unwrap();
+        let found = cache.nearest_documents(&[0.0, 1.0], 1, &|_| true);
+        assert_eq!((found[0].path.as_str(), found[0].chunk), ("/b.txt", 1));
     }
 }
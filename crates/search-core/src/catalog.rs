@@ -0,0 +1,516 @@
+// search-core/src/catalog.rs
+//! 文件目录模块 - 持久化记录已索引文件的状态
+//!
+//! 每个提交到索引的文件记录 inode、大小、修改时间（纳秒）、内容哈希和索引代数，
+//! 存放在缓存数据库的独立 tree 中，键为规范化后的文件路径。
+//! 启动时把目录与文件系统的遍历结果对比，直接得到新增、修改、删除和移动的文件，
+//! 不需要读取索引中的文档，也不需要为每个文件单独查询缓存。
+//!
+//! 内容哈希为原始字节的 xxh3-64，与 Rust 版本无关，可以跨版本持久化：
+//! 只有修改时间变化（touch）的文件按哈希确认未变化，内容相同的文件据此分组。
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use tantivy::Index;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::cache::FileStatus;
+
+/// 文件系统中读取的文件状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileStat {
+    /// inode 编号（不支持的平台上为 0）
+    pub inode: u64,
+    /// 文件大小（字节）
+    pub file_size: u64,
+    /// 修改时间（Unix 时间戳纳秒，1970 年以前为负数）
+    pub mtime_ns: i64,
+}
+
+impl FileStat {
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let mtime_ns = match metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH).duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => after.as_nanos() as i64,
+            Err(before) => -(before.duration().as_nanos() as i64),
+        };
+        Self {
+            inode: inode(metadata),
+            file_size: metadata.len(),
+            mtime_ns,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> std::io::Result<Self> {
+        fs::metadata(path).map(|metadata| Self::from_metadata(&metadata))
+    }
+}
+
+/// 计算文件原始字节的 xxh3-64 哈希（流式读取）
+pub fn hash_file(path: &Path) -> std::io::Result<u64> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.digest());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+/// 目录中一个已索引文件的记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// 提交到索引时的文件状态
+    pub stat: FileStat,
+    /// 索引时文件原始字节的 xxh3-64 哈希，未知时为 0
+    pub content_hash: u64,
+    /// 记录时的索引代数，与当前代数不同的记录视为已修改
+    pub generation: u64,
+}
+
+/// 目录与文件系统的差异
+#[derive(Debug, Default)]
+pub struct CatalogDiff {
+    /// 目录中没有记录的文件
+    pub added: Vec<PathBuf>,
+    /// 状态与记录不一致、且不能按内容哈希确认未变化的文件
+    pub modified: Vec<PathBuf>,
+    /// 已不存在的文件
+    pub deleted: Vec<PathBuf>,
+    /// inode、大小和修改时间都与某个已删除记录一致的新增文件，`(原路径, 新路径)`
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// 未变化的文件数
+    pub unchanged: usize,
+}
+
+impl CatalogDiff {
+    /// 需要处理的文件数（不含未变化的文件）
+    pub fn changed(&self) -> usize {
+        self.added.len() + self.modified.len() + self.deleted.len() + self.moved.len()
+    }
+}
+
+/// 内容相同的已索引文件分组
+///
+/// 按内容哈希和文件大小分组，只保留至少有两个文件的组，组内路径按字典序排列。
+#[derive(Debug, Default)]
+pub struct DuplicateGroups {
+    groups: Vec<Vec<String>>,
+    /// 文件路径到所在分组的下标
+    by_path: HashMap<String, usize>,
+}
+
+impl DuplicateGroups {
+    fn from_entries(entries: impl Iterator<Item = (String, CatalogEntry)>) -> Self {
+        let mut by_content: HashMap<(u64, u64), Vec<String>> = HashMap::new();
+        for (path, entry) in entries {
+            by_content.entry((entry.content_hash, entry.stat.file_size)).or_default().push(path);
+        }
+        let mut groups: Vec<Vec<String>> = by_content.into_values()
+            .filter(|paths| paths.len() > 1)
+            .map(|mut paths| {
+                paths.sort();
+                paths
+            })
+            .collect();
+        groups.sort();
+        let by_path = groups.iter()
+            .enumerate()
+            .flat_map(|(i, paths)| paths.iter().map(move |path| (path.clone(), i)))
+            .collect();
+        Self { groups, by_path }
+    }
+
+    /// 所有分组
+    pub fn groups(&self) -> &[Vec<String>] {
+        &self.groups
+    }
+
+    /// 文件所在分组的下标，内容唯一的文件返回 `None`
+    pub fn group_of(&self, path: &str) -> Option<usize> {
+        self.by_path.get(path).copied()
+    }
+
+    /// 指定下标的分组
+    pub fn group(&self, index: usize) -> &[String] {
+        &self.groups[index]
+    }
+
+    /// 文件是否有内容相同的副本
+    pub fn contains(&self, path: &str) -> bool {
+        self.by_path.contains_key(path)
+    }
+
+    /// 所有有副本的文件路径
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.groups.iter().flatten().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/// 持久化的文件目录
+pub struct FileCatalog {
+    tree: Tree,
+    generation: AtomicU64,
+    /// 按需计算的重复文件分组，记录变化时清空
+    duplicates: RwLock<Option<Arc<DuplicateGroups>>>,
+    /// 记录的变化次数，计算分组期间有变化时不缓存结果
+    changes: AtomicU64,
+}
+
+impl FileCatalog {
+    const TREE: &'static str = "file_catalog";
+
+    /// 当前索引代数，路径均为绝对路径，不会与该键冲突
+    const GENERATION_KEY: &'static [u8] = b"\0generation";
+
+    /// 打开缓存数据库中的文件目录
+    pub fn open(db: &Db) -> Result<Self> {
+        let tree = db.open_tree(Self::TREE)?;
+        let generation = match tree.get(Self::GENERATION_KEY)? {
+            Some(data) => bincode::deserialize(&data)?,
+            None => 0,
+        };
+        Ok(Self {
+            tree,
+            generation: AtomicU64::new(generation),
+            duplicates: RwLock::new(None),
+            changes: AtomicU64::new(0),
+        })
+    }
+
+    /// 已记录的文件数
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// 当前索引代数
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// 使所有记录失效，返回新的索引代数
+    ///
+    /// 记录仍然保留（例如用于索引重建时列出之前索引过的文件），
+    /// 但在重新提交之前都视为已修改。
+    pub fn invalidate(&self) -> Result<u64> {
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        self.tree.insert(Self::GENERATION_KEY, bincode::serialize(&generation)?)?;
+        self.tree.flush()?;
+        self.clear_duplicates();
+        Ok(generation)
+    }
+
+    /// 确认索引与目录属于同一代，不一致时使所有记录失效
+    ///
+    /// 写入线程每次提交都把当前代数写入索引的提交信息。索引目录被删除、重建或替换后
+    /// 两者不再对应，目录中的记录不能再用来跳过文件。
+    pub fn check_index(&self, index: &Index) -> Result<()> {
+        let metas = index.load_metas()?;
+        let consistent = match metas.payload.as_deref().map(str::parse::<u64>) {
+            Some(Ok(generation)) => generation == self.generation(),
+            // 旧版本提交时没有写入代数，索引非空时沿用已有的记录
+            _ => !metas.segments.is_empty(),
+        };
+        if !consistent && !self.is_empty() {
+            let generation = self.invalidate()?;
+            tracing::warn!("[文件目录] 索引与文件目录不一致，所有文件将重新索引 (代数 {})", generation);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, path: &str) -> Option<CatalogEntry> {
+        let data = self.tree.get(path.as_bytes()).ok()??;
+        bincode::deserialize(&data).ok()
+    }
+
+    /// 根据文件当前状态判断自上次索引后是否变化
+    ///
+    /// 状态与记录一致时不读取文件。大小相同但修改时间或 inode 不同（touch、
+    /// 原样覆盖保存）时重新计算内容哈希，与记录一致则更新记录并视为未变化。
+    pub fn status(&self, path: &str, stat: &FileStat) -> FileStatus {
+        let Some(entry) = self.get(path) else {
+            return FileStatus::New;
+        };
+        if entry.generation != self.generation() {
+            return FileStatus::Modified;
+        }
+        if entry.stat == *stat {
+            return FileStatus::Unchanged;
+        }
+        let same_content = entry.content_hash != 0
+            && entry.stat.file_size == stat.file_size
+            && hash_file(Path::new(path)).is_ok_and(|hash| hash == entry.content_hash);
+        if !same_content {
+            return FileStatus::Modified;
+        }
+        tracing::debug!("[文件目录] 内容未变化，只更新文件状态: {}", path);
+        if let Err(e) = self.record(path, stat, entry.content_hash) {
+            tracing::warn!("[文件目录] 更新记录失败 {}: {}", path, e);
+        }
+        FileStatus::Unchanged
+    }
+
+    /// 记录已提交到索引的文件（不立即刷盘，见 `flush`）
+    pub fn record(&self, path: &str, stat: &FileStat, content_hash: u64) -> Result<()> {
+        let entry = CatalogEntry {
+            stat: *stat,
+            content_hash,
+            generation: self.generation(),
+        };
+        self.tree.insert(path.as_bytes(), bincode::serialize(&entry)?)?;
+        self.clear_duplicates();
+        Ok(())
+    }
+
+    pub fn remove(&self, path: &str) -> Result<()> {
+        self.tree.remove(path.as_bytes())?;
+        self.clear_duplicates();
+        Ok(())
+    }
+
+    /// 把记录迁移到新路径（移动不改变 inode 和内容）
+    pub fn rename(&self, from: &str, to: &str) -> Result<()> {
+        if let Some(data) = self.tree.remove(from.as_bytes())? {
+            self.tree.insert(to.as_bytes(), data)?;
+            self.clear_duplicates();
+        }
+        Ok(())
+    }
+
+    /// 当前代数中内容相同的文件分组
+    ///
+    /// 首次调用时遍历整个目录计算，结果缓存到记录下一次变化为止。
+    pub fn duplicate_groups(&self) -> Arc<DuplicateGroups> {
+        if let Some(groups) = self.duplicates.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return groups.clone();
+        }
+        let changes = self.changes.load(Ordering::Acquire);
+        let generation = self.generation();
+        let groups = Arc::new(DuplicateGroups::from_entries(
+            self.iter().filter(|(_, entry)| entry.generation == generation && entry.content_hash != 0),
+        ));
+        let mut cached = self.duplicates.write().unwrap_or_else(|e| e.into_inner());
+        if self.changes.load(Ordering::Acquire) == changes {
+            *cached = Some(groups.clone());
+        }
+        groups
+    }
+
+    fn clear_duplicates(&self) {
+        let mut cached = self.duplicates.write().unwrap_or_else(|e| e.into_inner());
+        self.changes.fetch_add(1, Ordering::Release);
+        cached.take();
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    /// 遍历所有记录，返回 `(文件路径, 记录)`
+    pub fn iter(&self) -> impl Iterator<Item = (String, CatalogEntry)> + '_ {
+        self.tree
+            .iter()
+            .filter(|item| !matches!(item, Ok((key, _)) if key.as_ref() == Self::GENERATION_KEY))
+            .filter_map(Self::decode)
+    }
+
+    /// 所有已记录的文件路径
+    pub fn paths(&self) -> Vec<String> {
+        self.iter().map(|(path, _)| path).collect()
+    }
+
+    /// `root` 目录下（含子目录）的所有记录，按路径顺序
+    pub fn entries_under<'a>(&'a self, root: &'a Path) -> impl Iterator<Item = (String, CatalogEntry)> + 'a {
+        // 键按字节序排列，前缀扫描只读取该目录下的记录；再排除 `/a/bc` 这类同前缀的兄弟路径
+        self.tree
+            .scan_prefix(root.to_string_lossy().as_bytes())
+            .filter_map(Self::decode)
+            .filter(move |(path, _)| Path::new(path).starts_with(root))
+    }
+
+    fn decode(item: sled::Result<(sled::IVec, sled::IVec)>) -> Option<(String, CatalogEntry)> {
+        let (key, data) = item.ok()?;
+        let path = String::from_utf8(key.to_vec()).ok()?;
+        let entry = bincode::deserialize(&data).ok()?;
+        Some((path, entry))
+    }
+
+    /// 对比 `root` 下的记录与遍历得到的文件
+    ///
+    /// `files` 为 `root` 下所有支持的文件（规范化路径）及其当前状态。
+    /// 已删除的记录与新增文件的 inode、大小和修改时间一致时视为移动。
+    pub fn diff(&self, root: &Path, files: Vec<(PathBuf, FileStat)>) -> CatalogDiff {
+        let mut diff = CatalogDiff::default();
+        let mut seen = HashSet::with_capacity(files.len());
+        let mut added = Vec::new();
+        for (path, stat) in files {
+            let key = path.to_string_lossy().to_string();
+            match self.status(&key, &stat) {
+                FileStatus::Unchanged => diff.unchanged += 1,
+                FileStatus::Modified => diff.modified.push(path),
+                FileStatus::New => added.push((path, stat)),
+            }
+            seen.insert(key);
+        }
+
+        // inode 为 0 表示平台不支持，无法可靠地识别移动
+        let mut added_by_stat: HashMap<FileStat, usize> = added.iter()
+            .enumerate()
+            .filter(|(_, (_, stat))| stat.inode != 0)
+            .map(|(i, (_, stat))| (*stat, i))
+            .collect();
+        let mut moved_to = HashSet::new();
+        let generation = self.generation();
+        for (path, entry) in self.entries_under(root) {
+            if seen.contains(&path) {
+                continue;
+            }
+            let target = (entry.generation == generation)
+                .then(|| added_by_stat.remove(&entry.stat))
+                .flatten();
+            match target {
+                Some(i) => {
+                    moved_to.insert(i);
+                    diff.moved.push((PathBuf::from(path), added[i].0.clone()));
+                }
+                None => diff.deleted.push(PathBuf::from(path)),
+            }
+        }
+
+        diff.added = added.into_iter()
+            .enumerate()
+            .filter(|(i, _)| !moved_to.contains(i))
+            .map(|(_, (path, _))| path)
+            .collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_catalog(dir: &Path) -> FileCatalog {
+        FileCatalog::open(&sled::open(dir.join("db")).unwrap()).unwrap()
+    }
+
+    fn stat(inode: u64, file_size: u64) -> FileStat {
+        FileStat { inode, file_size, mtime_ns: 1_700_000_000_000_000_000 }
+    }
+
+    fn sorted(paths: &[PathBuf]) -> Vec<&str> {
+        let mut paths: Vec<&str> = paths.iter().map(|p| p.to_str().unwrap()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_diff_against_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = open_catalog(dir.path());
+        for (path, inode) in [("/w/same.txt", 1), ("/w/edited.txt", 2), ("/w/gone.txt", 3), ("/w/old.txt", 4), ("/wx/other.txt", 5)] {
+            catalog.record(path, &stat(inode, 10), 0).unwrap();
+        }
+
+        let diff = catalog.diff(Path::new("/w"), vec![
+            (PathBuf::from("/w/same.txt"), stat(1, 10)),
+            (PathBuf::from("/w/edited.txt"), stat(2, 20)),
+            (PathBuf::from("/w/sub/renamed.txt"), stat(4, 10)),
+            (PathBuf::from("/w/new.txt"), stat(6, 10)),
+        ]);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(sorted(&diff.modified), vec!["/w/edited.txt"]);
+        assert_eq!(sorted(&diff.added), vec!["/w/new.txt"]);
+        // 同前缀的兄弟目录 /wx 不在 /w 之下
+        assert_eq!(sorted(&diff.deleted), vec!["/w/gone.txt"]);
+        assert_eq!(diff.moved, vec![(PathBuf::from("/w/old.txt"), PathBuf::from("/w/sub/renamed.txt"))]);
+        assert_eq!(diff.changed(), 4);
+    }
+
+    #[test]
+    fn test_invalidate_marks_every_entry_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let catalog = open_catalog(dir.path());
+            catalog.record("/w/a.txt", &stat(1, 10), 42).unwrap();
+            assert!(matches!(catalog.status("/w/a.txt", &stat(1, 10)), FileStatus::Unchanged));
+            catalog.invalidate().unwrap();
+            catalog.flush().unwrap();
+        }
+
+        // 代数持久化，重新打开后记录仍然失效，重新记录后恢复
+        let catalog = open_catalog(dir.path());
+        assert_eq!(catalog.generation(), 1);
+        assert_eq!(catalog.paths(), vec!["/w/a.txt".to_string()]);
+        assert!(matches!(catalog.status("/w/a.txt", &stat(1, 10)), FileStatus::Modified));
+        let diff = catalog.diff(Path::new("/w"), vec![(PathBuf::from("/w/b.txt"), stat(1, 10))]);
+        assert_eq!((diff.deleted.len(), diff.moved.len()), (1, 0));
+
+        catalog.record("/w/a.txt", &stat(1, 10), 42).unwrap();
+        assert!(matches!(catalog.status("/w/a.txt", &stat(1, 10)), FileStatus::Unchanged));
+        assert_eq!(catalog.get("/w/a.txt").unwrap().content_hash, 42);
+    }
+
+    #[test]
+    fn test_touch_only_change_and_duplicate_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = open_catalog(dir.path());
+        let mut keys = Vec::new();
+        for (name, content) in [("a.txt", "same"), ("b.txt", "same"), ("c.txt", "other")] {
+            let file = dir.path().join(name);
+            fs::write(&file, content).unwrap();
+            let key = file.to_string_lossy().to_string();
+            catalog.record(&key, &FileStat::from_path(&file).unwrap(), hash_file(&file).unwrap()).unwrap();
+            keys.push(key);
+        }
+
+        let groups = catalog.duplicate_groups();
+        assert_eq!(groups.groups(), &[vec![keys[0].clone(), keys[1].clone()]]);
+        assert_eq!(groups.group_of(&keys[1]), Some(0));
+        assert!(!groups.contains(&keys[2]));
+
+        // 只有修改时间变化：按内容哈希确认未变化，并更新记录的状态
+        let stat = catalog.get(&keys[0]).unwrap().stat;
+        let touched = FileStat { mtime_ns: stat.mtime_ns + 1_000_000_000, ..stat };
+        assert!(matches!(catalog.status(&keys[0], &touched), FileStatus::Unchanged));
+        assert_eq!(catalog.get(&keys[0]).unwrap().stat, touched);
+
+        // 大小不变但内容变化
+        fs::write(dir.path().join("a.txt"), "diff").unwrap();
+        let current = FileStat::from_path(&dir.path().join("a.txt")).unwrap();
+        assert!(matches!(catalog.status(&keys[0], &current), FileStatus::Modified));
+
+        catalog.remove(&keys[1]).unwrap();
+        assert!(catalog.duplicate_groups().is_empty());
+    }
+}
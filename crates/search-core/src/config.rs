@@ -2,26 +2,87 @@
 //! 配置模块
 
 use once_cell::sync::Lazy;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// 搜索引擎配置
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SearchConfig {
-    /// 要监控和索引的目录列表
-    pub watch_paths: Vec<String>,
+    /// 要监控和索引的目录列表，每项为路径或带规则的表（见 `WatchPath`）
+    #[serde(deserialize_with = "deserialize_watch_paths")]
+    pub watch_paths: Vec<WatchPath>,
     pub index: IndexConfig,
     pub ai: AiConfig,
     pub walker: WalkerConfig,
     pub cache_path: String,
     pub display: DisplayConfig,
+    /// 全文检索时各字段的默认权重
+    #[serde(default)]
+    pub field_boosts: FieldBoostConfig,
+    /// 规则查询的执行限制
+    #[serde(default)]
+    pub query: QueryConfig,
+    /// 文件监控的事件合并
+    #[serde(default)]
+    pub watcher: WatcherConfig,
+    /// `type:` 的文件类型分组（组名 → 扩展名），同名时覆盖内置分组
+    #[serde(default, deserialize_with = "deserialize_file_types")]
+    pub file_types: HashMap<String, Vec<String>>,
 }
 
 /// 索引配置
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct IndexConfig {
     pub storage_path: String,
+    /// `IndexWriter` 的内存预算（字节），按每线程至少 15MB 分配写入线程
     pub writer_memory: usize,
+    /// 累计多少个新增/删除操作后提交一次
+    #[serde(default = "default_commit_batch_size")]
+    pub commit_batch_size: usize,
+    /// 距离上次提交超过该时间（毫秒）且有待提交操作时提交
+    #[serde(default = "default_commit_interval_ms")]
+    pub commit_interval_ms: u64,
+    /// 索引 Schema 与当前版本不一致时的处理方式
+    #[serde(default)]
+    pub schema_mismatch: SchemaMismatchPolicy,
+    /// 扫描时目录遍历和文本提取的线程数，0 表示按 CPU 核数自动选择
+    #[serde(default)]
+    pub index_threads: usize,
+    /// 扫描时每批送入 BERT 的文档数
+    #[serde(default = "default_embedding_batch_size")]
+    pub embedding_batch_size: usize,
+    /// 长文档的段落切分
+    #[serde(default)]
+    pub passage: PassageConfig,
+}
+
+/// 段落切分配置
+/// 
+/// 修改后只影响之后新索引或内容变化的文件，已有文件需重建索引才会按新配置切分。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PassageConfig {
+    /// 每个段落的最大字符数（需保证分词后不超过 BERT 的 512 token 上限）
+    pub chars: usize,
+    /// 相邻段落重叠的字符数，避免关键句被切断
+    pub overlap: usize,
+    /// 每个文件最多计算向量的段落数（从头开始），超出部分只参与全文检索
+    pub max_embedded: usize,
+}
+
+/// 索引 Schema 不匹配时的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaMismatchPolicy {
+    /// 删除旧索引，并在后台根据元数据缓存重新索引所有文件
+    #[default]
+    Rebuild,
+    /// 拒绝启动，由用户手动处理
+    Refuse,
 }
 
 /// AI 配置
@@ -29,6 +90,65 @@ pub struct IndexConfig {
 pub struct AiConfig {
     pub model_path: String,
     pub keyword_count: usize,
+    /// 语义召回使用的向量近似最近邻索引
+    #[serde(default)]
+    pub ann: AnnConfig,
+}
+
+/// 向量近似最近邻（HNSW）索引配置
+/// 
+/// `m` 和 `ef_construction` 变化后索引会在启动时按缓存的向量重建。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnConfig {
+    /// 是否启用 ANN 索引，关闭时语义召回线性扫描所有文档向量
+    pub enabled: bool,
+    /// 每个节点在上层保留的邻居数（第 0 层为两倍），越大召回率越高、内存越多
+    pub m: usize,
+    /// 构建时的候选队列大小，越大图质量越好、插入越慢
+    pub ef_construction: usize,
+    /// 查询时的候选队列大小，越大召回率越高、查询越慢
+    pub ef_search: usize,
+}
+
+/// 全文检索的字段权重
+/// 
+/// 命中得分乘以所在字段的权重，查询中的 `^N` 在此基础上再乘以 N。
+/// 段落文档使用正文的权重。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FieldBoostConfig {
+    /// 标题（文件名）
+    pub title: f32,
+    /// AI 标签
+    pub tags: f32,
+    /// 正文
+    pub body: f32,
+}
+
+/// 规则查询执行配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QueryConfig {
+    /// 单次查询的最长执行时间（毫秒），超时返回错误，0 表示不限制
+    pub timeout_ms: u64,
+    /// 含跨词正则等无法下推到索引的条件时，最多逐条校验的候选文档数，0 表示不限制
+    pub max_scanned: usize,
+    /// 结果中内容相同的文件只保留一个，其余路径列在该结果的 `duplicates` 中
+    /// （查询本身包含 `dupes:` 条件时不折叠）
+    pub collapse_duplicates: bool,
+}
+
+/// 文件监控配置
+/// 
+/// 同一文件的事件在静默期内持续到达时会合并，直到静默期结束或超过最大延迟才处理。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatcherConfig {
+    /// 文件最后一个事件之后等待的时间（毫秒）
+    pub quiet_ms: u64,
+    /// 文件第一个事件之后最长等待的时间（毫秒），避免持续写入的文件一直不被索引
+    pub max_latency_ms: u64,
 }
 
 /// Walker 配置
@@ -44,6 +164,114 @@ pub struct WalkerConfig {
     pub supported_extensions: Vec<String>,
 }
 
+/// 监控目录及其索引规则
+///
+/// 配置中可以直接写路径，也可以写成表：
+///
+/// ```toml
+/// watch-paths = [
+///     "/home/me/Documents",
+///     { path = "/home/me/Projects", include = ["*.md", "docs/"], exclude = ["vendor/"], max-file-size = "20MB" },
+/// ]
+/// ```
+///
+/// `include` 和 `exclude` 使用 gitignore 语法，相对于 `path` 匹配（见 `path_filter` 模块）。
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WatchPath {
+    pub path: PathBuf,
+    /// 只索引匹配的文件，为空时不限制
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// 在 `custom_ignore_patterns` 之外额外排除的文件和目录
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// 超过该大小的文件不索引，可以写字节数或带单位的大小（如 `"20MB"`）
+    #[serde(default, deserialize_with = "deserialize_file_size", skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+    /// 是否读取目录中的 `.unnamedignore`
+    #[serde(default = "default_ignore_file")]
+    pub ignore_file: bool,
+    /// 覆盖 `WalkerConfig.respect_gitignore`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub respect_gitignore: Option<bool>,
+}
+
+impl WatchPath {
+    /// 使用默认规则的监控目录
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_size: None,
+            ignore_file: default_ignore_file(),
+            respect_gitignore: None,
+        }
+    }
+}
+
+fn default_ignore_file() -> bool {
+    true
+}
+
+fn deserialize_file_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FileSize {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<FileSize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(FileSize::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(FileSize::Text(text)) => query::validator::parse_size_value(&text, query::empty_span())
+            .map(Some)
+            .map_err(|e| de::Error::custom(format!("无效的 max-file-size '{}': {}", text, e.kind))),
+    }
+}
+
+/// 反序列化 `file_types`，组名统一转为小写（查询中的 `type:` 值不区分大小写）
+pub fn deserialize_file_types<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, Vec<String>>, D::Error> {
+    let groups = HashMap::<String, Vec<String>>::deserialize(deserializer)?;
+    Ok(groups.into_iter().map(|(name, extensions)| (name.to_lowercase(), extensions)).collect())
+}
+
+/// 反序列化 `watch_paths`，每项为路径字符串或 `WatchPath` 表
+pub fn deserialize_watch_paths<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<WatchPath>, D::Error> {
+    struct Entry(WatchPath);
+
+    impl<'de> Deserialize<'de> for Entry {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct EntryVisitor;
+
+            impl<'de> Visitor<'de> for EntryVisitor {
+                type Value = Entry;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a directory path or a table with `path`")
+                }
+
+                fn visit_str<E: de::Error>(self, path: &str) -> Result<Entry, E> {
+                    Ok(Entry(WatchPath::new(path)))
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Entry, A::Error> {
+                    WatchPath::deserialize(de::value::MapAccessDeserializer::new(map)).map(Entry)
+                }
+            }
+
+            deserializer.deserialize_any(EntryVisitor)
+        }
+    }
+
+    let entries = Vec::<Entry>::deserialize(deserializer)?;
+    Ok(entries.into_iter().map(|Entry(watch_path)| watch_path).collect())
+}
+
 /// 显示配置
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DisplayConfig {
@@ -54,21 +282,92 @@ pub struct DisplayConfig {
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
-            watch_paths: vec!["./docs".to_string()],
+            watch_paths: vec![WatchPath::new("./docs")],
             index: IndexConfig::default(),
             ai: AiConfig::default(),
             walker: WalkerConfig::default(),
             cache_path: "./cache".to_string(),
             display: DisplayConfig::default(),
+            field_boosts: FieldBoostConfig::default(),
+            query: QueryConfig::default(),
+            watcher: WatcherConfig::default(),
+            file_types: HashMap::new(),
         }
     }
 }
 
+impl SearchConfig {
+    /// 配置的 `type:` 分组名（按名称排序），不含内置分组
+    pub fn file_type_groups(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.file_types.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// `type:` 的值对应的扩展名（见 `file_type_extensions`）
+    pub fn file_type_extensions(&self, name: &str) -> Vec<String> {
+        file_type_extensions(&self.file_types, name)
+    }
+}
+
+/// `type:` 的值对应的扩展名：先查 `file_types` 中配置的分组，再查内置分组，都不是时视为单个扩展名
+pub fn file_type_extensions(file_types: &HashMap<String, Vec<String>>, name: &str) -> Vec<String> {
+    if let Some(extensions) = file_types.get(name) {
+        return extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_lowercase())
+            .collect();
+    }
+    match query::FileTypeGroup::find(name) {
+        Some(group) => group.extensions.iter().map(|ext| ext.to_string()).collect(),
+        None => vec![name.to_string()],
+    }
+}
+
 impl Default for IndexConfig {
     fn default() -> Self {
         Self {
             storage_path: "./storage".to_string(),
             writer_memory: 50_000_000,
+            commit_batch_size: default_commit_batch_size(),
+            commit_interval_ms: default_commit_interval_ms(),
+            schema_mismatch: SchemaMismatchPolicy::default(),
+            index_threads: 0,
+            embedding_batch_size: default_embedding_batch_size(),
+            passage: PassageConfig::default(),
+        }
+    }
+}
+
+fn default_commit_batch_size() -> usize {
+    1000
+}
+
+fn default_commit_interval_ms() -> u64 {
+    2000
+}
+
+fn default_embedding_batch_size() -> usize {
+    16
+}
+
+impl IndexConfig {
+    /// 实际使用的扫描线程数
+    pub fn scan_threads(&self) -> usize {
+        if self.index_threads > 0 {
+            self.index_threads
+        } else {
+            std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+        }
+    }
+}
+
+impl Default for PassageConfig {
+    fn default() -> Self {
+        Self {
+            chars: 400,
+            overlap: 80,
+            max_embedded: 512,
         }
     }
 }
@@ -78,10 +377,61 @@ impl Default for AiConfig {
         Self {
             model_path: "./model".to_string(),
             keyword_count: 3,
+            ann: AnnConfig::default(),
         }
     }
 }
 
+impl Default for AnnConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+impl Default for FieldBoostConfig {
+    fn default() -> Self {
+        Self {
+            title: 2.0,
+            tags: 1.5,
+            body: 1.0,
+        }
+    }
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 10_000,
+            max_scanned: 200_000,
+            collapse_duplicates: true,
+        }
+    }
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            quiet_ms: 300,
+            max_latency_ms: 5_000,
+        }
+    }
+}
+
+impl WatcherConfig {
+    pub fn quiet(&self) -> Duration {
+        Duration::from_millis(self.quiet_ms)
+    }
+
+    pub fn max_latency(&self) -> Duration {
+        Duration::from_millis(self.max_latency_ms)
+    }
+}
+
 impl Default for WalkerConfig {
     fn default() -> Self {
         Self {
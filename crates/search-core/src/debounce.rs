@@ -0,0 +1,295 @@
+// search-core/src/debounce.rs
+//! 文件事件防抖队列
+//!
+//! 编辑器保存文件时通常会产生一连串事件（写临时文件、重命名、删除旧文件等）。
+//! 队列按路径合并事件，路径在静默期内没有新事件、或距第一个事件超过最大延迟后，
+//! 才输出一个最终动作：先创建后删除的文件不产生动作，多次修改只重新索引一次，
+//! 重命名输出为移动。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// 已过滤掉不支持文件的监控事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    /// 在监控目录内重命名或移动
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+/// 合并后需要执行的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchAction {
+    /// 重新提取并索引文件
+    Index(PathBuf),
+    /// 从索引中删除文件
+    Delete(PathBuf),
+    /// 文件从 `from` 移动到 `to`
+    Move { from: PathBuf, to: PathBuf },
+}
+
+/// 单个路径合并后的状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingState {
+    /// 需要索引，`created` 表示第一个事件是创建，之前不在索引中
+    Upsert { created: bool },
+    /// 需要删除
+    Remove,
+    /// 从 `from` 移动而来
+    MovedFrom(PathBuf),
+}
+
+#[derive(Debug)]
+struct PendingEntry {
+    state: PendingState,
+    first_seen: Instant,
+    last_seen: Instant,
+}
+
+/// 按路径合并文件事件的防抖队列
+#[derive(Debug)]
+pub struct EventQueue {
+    entries: HashMap<PathBuf, PendingEntry>,
+    quiet: Duration,
+    max_latency: Duration,
+}
+
+impl EventQueue {
+    /// `quiet` 为静默期，`max_latency` 为持续有事件的路径最长等待时间
+    pub fn new(quiet: Duration, max_latency: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            quiet,
+            max_latency,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 加入一个事件，与同一路径上尚未输出的事件合并
+    pub fn push(&mut self, event: FileEvent, now: Instant) {
+        match event {
+            FileEvent::Created(path) => self.merge(path, now, |state| match state {
+                None => Some(PendingState::Upsert { created: true }),
+                // 删除后又创建（写临时文件再替换），按修改处理
+                Some(PendingState::Remove) => Some(PendingState::Upsert { created: false }),
+                Some(state) => Some(state),
+            }),
+            FileEvent::Modified(path) => self.merge(path, now, |state| match state {
+                None | Some(PendingState::Remove) => Some(PendingState::Upsert { created: false }),
+                Some(state) => Some(state),
+            }),
+            FileEvent::Removed(path) => {
+                // 移动后又删除，索引中只有移动前的路径
+                let path = match self.entries.get(&path).map(|entry| &entry.state) {
+                    Some(PendingState::MovedFrom(origin)) => {
+                        let origin = origin.clone();
+                        self.entries.remove(&path);
+                        origin
+                    }
+                    _ => path,
+                };
+                self.merge(path, now, |state| match state {
+                    // 创建后又删除，索引中从未有过该文件
+                    Some(PendingState::Upsert { created: true }) => None,
+                    _ => Some(PendingState::Remove),
+                })
+            }
+            FileEvent::Renamed { from, to } => self.rename(from, to, now),
+        }
+    }
+
+    fn rename(&mut self, from: PathBuf, to: PathBuf, now: Instant) {
+        if from == to {
+            return;
+        }
+        let previous = self.entries.remove(&from);
+        let first_seen = previous.as_ref().map_or(now, |entry| entry.first_seen);
+        let state = match previous.map(|entry| entry.state) {
+            // 新建的文件随即改名，按新文件索引
+            Some(PendingState::Upsert { created: true }) => PendingState::Upsert { created: true },
+            // 连续移动只保留最初的路径，移回原处时按修改处理
+            Some(PendingState::MovedFrom(origin)) if origin == to => {
+                PendingState::Upsert { created: false }
+            }
+            Some(PendingState::MovedFrom(origin)) => PendingState::MovedFrom(origin),
+            // 重命名的源路径事件（Remove）与之后配对的重命名属于同一次移动
+            Some(PendingState::Upsert { created: false }) | Some(PendingState::Remove) | None => {
+                PendingState::MovedFrom(from)
+            }
+        };
+        self.entries.insert(
+            to,
+            PendingEntry {
+                state,
+                first_seen,
+                last_seen: now,
+            },
+        );
+    }
+
+    fn merge(
+        &mut self,
+        path: PathBuf,
+        now: Instant,
+        next: impl FnOnce(Option<PendingState>) -> Option<PendingState>,
+    ) {
+        let previous = self.entries.remove(&path);
+        let first_seen = previous.as_ref().map_or(now, |entry| entry.first_seen);
+        if let Some(state) = next(previous.map(|entry| entry.state)) {
+            self.entries.insert(
+                path,
+                PendingEntry {
+                    state,
+                    first_seen,
+                    last_seen: now,
+                },
+            );
+        }
+    }
+
+    /// 最早可以输出动作的时间，队列为空时返回 `None`
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.entries.values().map(|entry| self.deadline(entry)).min()
+    }
+
+    fn deadline(&self, entry: &PendingEntry) -> Instant {
+        (entry.last_seen + self.quiet).min(entry.first_seen + self.max_latency)
+    }
+
+    /// 取出到期的路径合并后的动作，按第一个事件的时间排序
+    pub fn drain_ready(&mut self, now: Instant) -> Vec<WatchAction> {
+        let ready: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| self.deadline(entry) <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let mut entries: Vec<(PathBuf, PendingEntry)> = ready
+            .into_iter()
+            .filter_map(|path| self.entries.remove_entry(&path))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.first_seen);
+        entries
+            .into_iter()
+            .map(|(path, entry)| match entry.state {
+                PendingState::Upsert { .. } => WatchAction::Index(path),
+                PendingState::Remove => WatchAction::Delete(path),
+                PendingState::MovedFrom(from) => WatchAction::Move { from, to: path },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUIET: Duration = Duration::from_millis(100);
+    const MAX_LATENCY: Duration = Duration::from_millis(1000);
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(format!("/watch/{name}"))
+    }
+
+    /// 依次加入事件（相隔 10ms），返回静默期结束后的动作
+    fn coalesce(events: Vec<FileEvent>) -> Vec<WatchAction> {
+        let mut queue = EventQueue::new(QUIET, MAX_LATENCY);
+        let start = Instant::now();
+        let mut now = start;
+        for event in events {
+            queue.push(event, now);
+            now += Duration::from_millis(10);
+        }
+        let actions = queue.drain_ready(now + QUIET);
+        assert!(queue.is_empty());
+        actions
+    }
+
+    #[test]
+    fn test_coalesce_sequences() {
+        use FileEvent::*;
+
+        assert!(coalesce(vec![Created(path("a.txt")), Modified(path("a.txt")), Removed(path("a.txt"))]).is_empty());
+        assert_eq!(
+            coalesce(vec![Modified(path("a.txt")), Modified(path("a.txt")), Modified(path("a.txt"))]),
+            vec![WatchAction::Index(path("a.txt"))]
+        );
+        assert_eq!(
+            coalesce(vec![Modified(path("a.txt")), Removed(path("a.txt"))]),
+            vec![WatchAction::Delete(path("a.txt"))]
+        );
+        // 先删除再创建（原子保存）只重新索引
+        assert_eq!(
+            coalesce(vec![Removed(path("a.txt")), Created(path("a.txt"))]),
+            vec![WatchAction::Index(path("a.txt"))]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_renames() {
+        use FileEvent::*;
+
+        // 重命名的源路径先以删除到达，随后与重命名配对
+        assert_eq!(
+            coalesce(vec![Removed(path("a.txt")), Renamed { from: path("a.txt"), to: path("b.txt") }]),
+            vec![WatchAction::Move { from: path("a.txt"), to: path("b.txt") }]
+        );
+        // 连续移动合并为一次，移回原处按修改处理
+        assert_eq!(
+            coalesce(vec![
+                Renamed { from: path("a.txt"), to: path("b.txt") },
+                Renamed { from: path("b.txt"), to: path("c.txt") },
+            ]),
+            vec![WatchAction::Move { from: path("a.txt"), to: path("c.txt") }]
+        );
+        assert_eq!(
+            coalesce(vec![
+                Renamed { from: path("a.txt"), to: path("b.txt") },
+                Renamed { from: path("b.txt"), to: path("a.txt") },
+            ]),
+            vec![WatchAction::Index(path("a.txt"))]
+        );
+        // 新建后改名按新文件索引，移动后删除则删除原路径
+        assert_eq!(
+            coalesce(vec![Created(path("a.txt")), Renamed { from: path("a.txt"), to: path("b.txt") }]),
+            vec![WatchAction::Index(path("b.txt"))]
+        );
+        assert_eq!(
+            coalesce(vec![Renamed { from: path("a.txt"), to: path("b.txt") }, Removed(path("b.txt"))]),
+            vec![WatchAction::Delete(path("a.txt"))]
+        );
+    }
+
+    #[test]
+    fn test_quiet_period_and_max_latency() {
+        let mut queue = EventQueue::new(QUIET, MAX_LATENCY);
+        let start = Instant::now();
+        assert_eq!(queue.next_deadline(), None);
+
+        // 静默期内持续修改的文件不输出，直到超过最大延迟
+        let mut now = start;
+        while now < start + MAX_LATENCY {
+            queue.push(FileEvent::Modified(path("busy.log")), now);
+            assert!(queue.drain_ready(now).is_empty());
+            now += QUIET / 2;
+        }
+        assert_eq!(queue.next_deadline(), Some(start + MAX_LATENCY));
+        assert_eq!(queue.drain_ready(now), vec![WatchAction::Index(path("busy.log"))]);
+
+        queue.push(FileEvent::Created(path("a.txt")), now);
+        queue.push(FileEvent::Modified(path("b.txt")), now + QUIET / 2);
+        assert_eq!(queue.drain_ready(now + QUIET), vec![WatchAction::Index(path("a.txt"))]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.next_deadline(), Some(now + QUIET / 2 + QUIET));
+    }
+}
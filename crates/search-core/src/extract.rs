@@ -3,46 +3,43 @@
 
 use std::fs;
 use std::path::Path;
-use std::time::Duration;
 use anyhow::{Result, Context};
 use chardetng::EncodingDetector;
+use xxhash_rust::xxh3::xxh3_64;
 
 use crate::models::FileDoc;
 use crate::config::CONFIG;
 
-/// 智能读取文本文件（自动检测编码）
-fn read_text_with_encoding_detection(path: &Path) -> Result<String> {
-    let bytes = fs::read(path)?;
-    
+/// 智能解码文本文件内容（自动检测编码）
+fn decode_text_with_encoding_detection(path: &Path, bytes: &[u8]) -> String {
     // 先尝试UTF-8
-    if let Ok(text) = std::str::from_utf8(&bytes) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
         tracing::debug!("文件使用 UTF-8 编码: {:?}", path);
-        return Ok(text.to_string());
+        return text.to_string();
     }
     
     // 使用 chardetng 检测编码
     let mut detector = EncodingDetector::new();
-    detector.feed(&bytes, true);
+    detector.feed(bytes, true);
     let detected_encoding = detector.guess(None, true);
     
     tracing::debug!("检测到文件编码 {:?}: {:?}", detected_encoding.name(), path);
     
     // 尝试使用检测到的编码
-    let (decoded, encoding_used, had_errors) = detected_encoding.decode(&bytes);
+    let (decoded, encoding_used, had_errors) = detected_encoding.decode(bytes);
     
     if had_errors {
         tracing::warn!("文件 {:?} 使用 {} 解码时有部分错误，可能影响搜索准确性", path, encoding_used.name());
     }
     
-    Ok(decoded.into_owned())
+    decoded.into_owned()
 }
 
-/// 从 DOCX 文件提取文本内容
-fn extract_docx_text(path: &Path) -> Result<String> {
+/// 从 DOCX 文件内容提取文本
+fn extract_docx_text(bytes: &[u8]) -> Result<String> {
     use docx_rs::*;
     
-    let bytes = fs::read(path)?;
-    let docx = read_docx(&bytes)
+    let docx = read_docx(bytes)
         .map_err(|e| anyhow::anyhow!("无法解析 DOCX 文件: {:?}", e))?;
     
     let mut text_content = String::new();
@@ -91,25 +88,30 @@ fn extract_docx_text(path: &Path) -> Result<String> {
 }
 
 /// 从文件提取文本内容
+///
+/// 文件只读取一次，同时计算原始字节的内容哈希。
 pub fn extract_text(path: &Path) -> Result<FileDoc> {
-    // 简单的防抖动：如果是刚创建的文件，可能还在写入中
-    std::thread::sleep(Duration::from_millis(100));
-
     let extension = path.extension()
         .and_then(|ext| ext.to_str())
         .unwrap_or("");
 
     tracing::debug!("正在解析文件: {:?}", path);
 
-    let content = match extension {
+    let read = || fs::read(path);
+    let (content, page_starts, bytes) = match extension {
         "txt" | "md" | "markdown" | "rs" | "toml" | "json" | "yaml" | "yml" => {
-            read_text_with_encoding_detection(path)?
+            let bytes = read()?;
+            (decode_text_with_encoding_detection(path, &bytes), Vec::new(), bytes)
         }
         "pdf" => {
-            pdf_extract::extract_text(path).with_context(|| "无法解析 PDF")?
+            let bytes = read()?;
+            let pages = pdf_extract::extract_text_from_mem_by_pages(&bytes).with_context(|| "无法解析 PDF")?;
+            let (content, page_starts) = join_pages(pages);
+            (content, page_starts, bytes)
         }
         "docx" => {
-            extract_docx_text(path).with_context(|| "无法解析 DOCX")?
+            let bytes = read()?;
+            (extract_docx_text(&bytes).with_context(|| "无法解析 DOCX")?, Vec::new(), bytes)
         }
         _ => return Err(anyhow::anyhow!("跳过不支持的文件格式: {}", extension)),
     };
@@ -124,9 +126,25 @@ pub fn extract_text(path: &Path) -> Result<FileDoc> {
         title: path.file_stem().unwrap().to_string_lossy().to_string(),
         content,
         path: canonical_path,
+        page_starts,
+        content_hash: xxh3_64(&bytes),
     })
 }
 
+/// 拼接各页文本，返回全文和每页的起始字节偏移
+fn join_pages(pages: Vec<String>) -> (String, Vec<usize>) {
+    let mut content = String::new();
+    let mut page_starts = Vec::with_capacity(pages.len());
+    for page in pages {
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        page_starts.push(content.len());
+        content.push_str(&page);
+    }
+    (content, page_starts)
+}
+
 /// 格式化内容预览
 pub fn format_content_preview(content: &str) -> String {
     let preview_max_length = CONFIG.display.preview_max_length;
@@ -1,26 +1,37 @@
 // search-core/src/indexer.rs
 //! 索引模块 - 文件索引和监控
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use std::thread;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 use anyhow::Result;
 use std::sync::Arc;
 
-use ignore::WalkBuilder;
+use notify::event::{ModifyKind, RenameMode};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher, EventKind};
 use tantivy::schema::*;
-use tantivy::{Index, doc, IndexWriter, Term, IndexReader, ReloadPolicy};
+use tantivy::{Index, IndexReader, ReloadPolicy, TantivyDocument};
 use tantivy_jieba::JiebaTokenizer;
 
 use crate::ai::BertModel;
 use crate::cache::{EmbeddingCache, FileStatus};
-use crate::config::CONFIG;
+use crate::catalog::FileStat;
+use crate::config::{CONFIG, IndexConfig, PassageConfig, SchemaMismatchPolicy, WatcherConfig};
+use crate::debounce::{EventQueue, FileEvent, WatchAction};
 use crate::extract::extract_text;
-use crate::registry::{FileRegistry, EventType};
-use crate::schema::{build_schema, FIELD_TITLE, FIELD_BODY, FIELD_PATH, FIELD_TAGS, FIELD_FILE_SIZE, FIELD_MODIFIED_TIME, FIELD_CREATED_TIME, FIELD_ACCESSED_TIME};
+use crate::models::FileDoc;
+use crate::passage::split_passages;
+use crate::path_filter::PathFilter;
+use crate::pipeline::{build_walker, walk_supported_files, ScanPipeline};
+use crate::registry::FileRegistry;
+use crate::relocate::{indexed_paths_under, relocate_indexed};
+use crate::writer::IndexWriterHandle;
+use crate::schema::{
+    build_schema, check_index_schema, passage_document, IndexDocument, SchemaCheck, SchemaFields, SchemaStamp, FIELD_PATH,
+};
 
 /// 初始化持久化索引
 pub fn init_persistent_index(index_path: &Path) -> Result<(Index, Schema, IndexReader)> {
@@ -47,11 +58,107 @@ pub fn init_persistent_index(index_path: &Path) -> Result<(Index, Schema, IndexR
     Ok((index, schema, reader))
 }
 
+/// 检查索引的 Schema 版本，不一致时按策略删除旧索引或拒绝启动
+/// 
+/// 返回 `true` 表示旧索引已删除，调用方需要重新索引文件目录中的文件
+/// （见 `spawn_index_rebuild`）。新建或兼容的索引会写入当前版本文件。
+pub fn migrate_index_schema(index_path: &Path, policy: SchemaMismatchPolicy) -> Result<bool> {
+    let current = SchemaStamp::current();
+    let found = match check_index_schema(index_path)? {
+        SchemaCheck::Missing => {
+            current.save(index_path)?;
+            return Ok(false);
+        }
+        SchemaCheck::Compatible => return Ok(false),
+        SchemaCheck::Outdated { found } => found,
+    };
+    
+    let found_desc = found
+        .map(|s| format!("v{} ({})", s.version, s.fingerprint))
+        .unwrap_or_else(|| "未知版本".to_string());
+    let current_desc = format!("v{} ({})", current.version, current.fingerprint);
+    
+    if policy == SchemaMismatchPolicy::Refuse {
+        anyhow::bail!(
+            "索引 Schema 不兼容: 索引为 {}，当前为 {}。\n\
+            请删除索引目录 {:?} 后重新索引，或将 index.schema_mismatch 设为 \"rebuild\" 自动重建",
+            found_desc, current_desc, index_path
+        );
+    }
+    
+    tracing::warn!("[索引迁移] 索引 Schema {} 与当前 {} 不兼容，重建索引: {:?}", found_desc, current_desc, index_path);
+    fs::remove_dir_all(index_path)?;
+    fs::create_dir_all(index_path)?;
+    current.save(index_path)?;
+    Ok(true)
+}
+
+/// 索引文件所需的句柄和配置
+/// 
+/// 各字段都是共享句柄，克隆后可以移入后台线程（索引重建、文件监控）。
+#[derive(Clone)]
+pub struct IndexContext {
+    pub index: Index,
+    pub writer: IndexWriterHandle,
+    pub schema: Schema,
+    pub bert: Arc<BertModel>,
+    pub cache: Arc<EmbeddingCache>,
+    pub registry: FileRegistry,
+    pub config: IndexConfig,
+}
+
+/// 在后台线程中重新索引给定文件，用于索引迁移后恢复内容
+/// 
+/// 关键词缓存按内容哈希保留，未变化的文件不会重复 AI 计算。
+/// 与初始扫描并发时通过 `FileRegistry` 避免重复处理。
+/// 与扫描相同，只恢复 `filters` 中某个监控目录接受的文件，其余文件的记录直接删除。
+pub fn spawn_index_rebuild(
+    paths: Vec<String>,
+    filters: Vec<Arc<PathFilter>>,
+    ctx: IndexContext,
+) -> thread::JoinHandle<usize> {
+    thread::spawn(move || {
+        let IndexContext { writer, schema, bert, cache, registry, config, .. } = ctx;
+        tracing::info!("[索引迁移] 后台重建开始，共 {} 个文件", paths.len());
+        let mut rebuilt = 0;
+        for path_str in paths {
+            let path = PathBuf::from(&path_str);
+            if !path.exists() || !is_supported_file(&path) {
+                continue;
+            }
+            // 已被排除或不在任何监控目录下的文件
+            let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+            let accepted = filters.iter().any(|filter| {
+                path.starts_with(filter.root()) && !filter.is_excluded(&path, false) && filter.accepts_size(size)
+            });
+            if !accepted {
+                let _ = cache.remove(&path_str);
+                let _ = cache.remove_file_meta(&path_str);
+                continue;
+            }
+            // 初始扫描可能已经处理过该文件
+            if matches!(cache.check_file_status(&path_str, &path), FileStatus::Unchanged) {
+                continue;
+            }
+            if let Some(modified_time) = get_modified_time(&path) {
+                if registry.try_start_processing(&path, modified_time) {
+                    match process_and_index(&path, &writer, &schema, &bert, &cache, &config.passage) {
+                        Ok(_) => rebuilt += 1,
+                        Err(e) => tracing::error!("[索引迁移] 处理文件失败 {:?}: {}", path, e),
+                    }
+                    registry.finish_processing(&path);
+                }
+            }
+        }
+        tracing::info!("[索引迁移] 后台重建完成，已索引 {} 个文件", rebuilt);
+        rebuilt
+    })
+}
+
 /// 从索引中删除文件
 pub fn delete_from_index(
     file_path: &Path, 
-    index: &Index, 
-    schema: &Schema, 
+    writer: &IndexWriterHandle, 
     cache: Option<&EmbeddingCache>
 ) -> Result<bool> {
     let path_str = file_path.canonicalize()
@@ -59,20 +166,13 @@ pub fn delete_from_index(
         .to_string_lossy()
         .to_string();
     
-    let path_field = schema.get_field(FIELD_PATH).unwrap();
-    let mut index_writer: IndexWriter = index.writer(50_000_000)?;
-    
-    let path_term = Term::from_field_text(path_field, &path_str);
-    index_writer.delete_term(path_term);
+    writer.delete(path_str.clone())?;
     
     let original_path_str = file_path.to_string_lossy();
     if original_path_str != path_str {
-        let original_term = Term::from_field_text(path_field, &original_path_str);
-        index_writer.delete_term(original_term);
+        writer.delete(original_path_str.to_string())?;
     }
     
-    index_writer.commit()?;
-    
     if let Some(c) = cache {
         let _ = c.remove(&path_str);
         let _ = c.remove(&original_path_str);
@@ -84,39 +184,19 @@ pub fn delete_from_index(
     Ok(true)
 }
 
-/// 处理并索引单个文件
+/// 处理并索引单个文件，按 `passage` 切分段落
 pub fn process_and_index(
     file_path: &Path, 
-    index: &Index, 
+    writer: &IndexWriterHandle, 
     schema: &Schema, 
     bert: &BertModel, 
-    cache: &EmbeddingCache
+    cache: &EmbeddingCache,
+    passage: &PassageConfig,
 ) -> Result<()> {
+    // 在读取内容之前获取文件状态，读取期间的修改在下次扫描时仍能发现
+    let stat = FileStat::from_path(file_path)?;
     let doc_data = extract_text(file_path)?;
 
-    let file_timestamp = fs::metadata(file_path)
-        .and_then(|m| m.modified())
-        .unwrap_or(SystemTime::now())
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    let file_created = fs::metadata(file_path)
-        .and_then(|m| m.created())
-        .unwrap_or(SystemTime::UNIX_EPOCH)
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    let file_accessed = fs::metadata(file_path)
-        .and_then(|m| m.accessed())
-        .unwrap_or(SystemTime::UNIX_EPOCH)
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-
-    let file_size = fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
-
     // AI 关键词提取（优先使用缓存）
     let keywords = if let Some(cached_keywords) = cache.get_keywords(&doc_data.path, &doc_data.content) {
         tracing::debug!("缓存命中: {:?}", cached_keywords);
@@ -128,51 +208,91 @@ pub fn process_and_index(
         tracing::debug!("生成标签: {:?}", new_keywords);
         new_keywords
     };
-    let tags_str = keywords.join(" ");
-
-    let title_field = schema.get_field(FIELD_TITLE).unwrap();
-    let body_field = schema.get_field(FIELD_BODY).unwrap();
-    let path_field = schema.get_field(FIELD_PATH).unwrap();
-    let tags_field = schema.get_field(FIELD_TAGS).unwrap();
-    let modified_time_field = schema.get_field(FIELD_MODIFIED_TIME).unwrap();
-    let created_time_field = schema.get_field(FIELD_CREATED_TIME).unwrap();
-    let accessed_time_field = schema.get_field(FIELD_ACCESSED_TIME).unwrap();
-    let size_field = schema.get_field(FIELD_FILE_SIZE).unwrap();
-    
-    let mut index_writer: IndexWriter = index.writer(50_000_000)?;
-
-    // 先删除旧文档
-    let path_term = Term::from_field_text(path_field, &doc_data.path);
-    index_writer.delete_term(path_term);
-
-    // 写入新文档
-    index_writer.add_document(doc!(
-        title_field => doc_data.title.as_str(),
-        body_field => doc_data.content.as_str(),
-        path_field => doc_data.path.as_str(),
-        tags_field => tags_str,
-        modified_time_field => file_timestamp,
-        created_time_field => file_created,
-        accessed_time_field => file_accessed,
-        size_field => file_size
-    ))?;
-
-    index_writer.commit()?;
-    
-    // 保存元数据
-    let _ = cache.save_file_meta(&doc_data.path, file_path);
+
+    // 语义搜索使用的文档向量，失败时不影响全文索引
+    if let Err(e) = store_embeddings(bert, cache, &[&doc_data], passage) {
+        tracing::warn!("文档向量计算失败 {:?}: {}", file_path, e);
+    }
+
+    submit_document(file_path, stat, doc_data, keywords, writer, &SchemaFields::from_schema(schema), passage)
+}
+
+/// 为向量缓存缺失或已过期的文档计算段落向量并保存
+pub(crate) fn store_embeddings(
+    bert: &BertModel,
+    cache: &EmbeddingCache,
+    docs: &[&FileDoc],
+    config: &PassageConfig,
+) -> Result<()> {
+    let missing: Vec<&FileDoc> = docs.iter()
+        .copied()
+        .filter(|doc| !cache.has_embeddings(&doc.path, &doc.content))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    // 分块与索引中的段落一一对应，超长文档只计算前 `max_embedded` 个段落
+    let passages: Vec<Vec<String>> = missing.iter()
+        .map(|doc| {
+            split_passages(doc, config).into_iter()
+                .take(config.max_embedded)
+                .map(|passage| passage.text)
+                .collect()
+        })
+        .collect();
+    for (doc, chunks) in missing.iter().zip(bert.embed_documents(&passages)?) {
+        let _ = cache.set_embeddings(&doc.path, &doc.content, chunks);
+    }
+    Ok(())
+}
+
+/// 将已提取文本和关键词的文件提交给写入线程
+pub(crate) fn submit_document(
+    file_path: &Path,
+    stat: FileStat,
+    doc_data: FileDoc,
+    keywords: Vec<String>,
+    writer: &IndexWriterHandle,
+    fields: &SchemaFields,
+    passage: &PassageConfig,
+) -> Result<()> {
+    let passages: Vec<TantivyDocument> = split_passages(&doc_data, passage)
+        .iter()
+        .map(|passage| passage_document(&doc_data.path, passage, fields))
+        .collect();
+    let document = IndexDocument::from_path(file_path, doc_data.title.clone(), doc_data.content)?
+        .with_tags(keywords);
+    
+    // 写入线程会先按 path 删除旧的文件文档和段落文档，提交成功后保存元数据
+    writer.upsert_with_passages(
+        doc_data.path,
+        document.to_tantivy(fields),
+        passages,
+        stat,
+        doc_data.content_hash,
+    )?;
 
     tracing::info!("已索引: {}", doc_data.title);
     Ok(())
 }
 
 /// 清理孤儿索引
-pub fn cleanup_orphan_indexes(index: &Index, schema: &Schema, cache: &EmbeddingCache) -> Result<usize> {
+/// 
+/// 需要读取索引中的所有文档，启动扫描已改为按文件目录对比，
+/// 仅用于清理不在文件目录中的残留文档。
+pub fn cleanup_orphan_indexes(
+    index: &Index,
+    writer: &IndexWriterHandle,
+    schema: &Schema,
+    cache: &EmbeddingCache,
+) -> Result<usize> {
     let reader = index.reader()?;
     let searcher = reader.searcher();
     let path_field = schema.get_field("path").unwrap();
     
-    let mut orphan_paths: Vec<String> = Vec::new();
+    // 段落文档与所属文件共用 path，用集合去重
+    let mut orphan_paths: HashSet<String> = HashSet::new();
     
     for segment_reader in searcher.segment_readers() {
         let store_reader = segment_reader.get_store_reader(1)?;
@@ -181,9 +301,8 @@ pub fn cleanup_orphan_indexes(index: &Index, schema: &Schema, cache: &EmbeddingC
                 if let Some(path_value) = doc.get_first(path_field) {
                     if let Some(path_str) = path_value.as_str() {
                         let path = Path::new(path_str);
-                        if !path.exists() {
+                        if !path.exists() && orphan_paths.insert(path_str.to_string()) {
                             tracing::info!("发现孤儿索引: {}", path_str);
-                            orphan_paths.push(path_str.to_string());
                         }
                     }
                 }
@@ -194,19 +313,16 @@ pub fn cleanup_orphan_indexes(index: &Index, schema: &Schema, cache: &EmbeddingC
     let orphan_count = orphan_paths.len();
     
     if orphan_count > 0 {
-        let mut index_writer: IndexWriter = index.writer(50_000_000)?;
         for path_str in &orphan_paths {
-            let path_term = Term::from_field_text(path_field, path_str);
-            index_writer.delete_term(path_term);
+            writer.delete(path_str.clone())?;
             let _ = cache.remove(path_str);
             let _ = cache.remove_file_meta(path_str);
         }
-        index_writer.commit()?;
         tracing::info!("已清理 {} 个孤儿索引", orphan_count);
     }
     
-    // 清理元数据缓存中的孤儿
-    let cached_paths = cache.get_all_cached_paths();
+    // 清理文件目录中的孤儿
+    let cached_paths = cache.catalog().paths();
     let mut meta_orphan_count = 0;
     for path_str in cached_paths {
         let path = Path::new(&path_str);
@@ -217,326 +333,87 @@ pub fn cleanup_orphan_indexes(index: &Index, schema: &Schema, cache: &EmbeddingC
         }
     }
     if meta_orphan_count > 0 {
-        tracing::info!("已清理 {} 个孤儿元数据缓存", meta_orphan_count);
+        tracing::info!("已清理 {} 个孤儿文件目录记录", meta_orphan_count);
     }
     
     Ok(orphan_count + meta_orphan_count)
 }
 
-/// 扫描现有文件
-pub fn scan_existing_files(
-    watch_path: &Path, 
-    index: &Index, 
-    schema: &Schema, 
-    bert: &BertModel, 
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-) -> Result<()> {
-    scan_existing_files_with_progress(watch_path, index, schema, bert, cache, registry, |_, _| {})
+/// 扫描现有文件，跳过 `filter` 排除的文件和目录
+pub fn scan_existing_files(watch_path: &Path, filter: &Arc<PathFilter>, ctx: &IndexContext) -> Result<()> {
+    scan_existing_files_with_progress(watch_path, filter, ctx, |_, _| {})
 }
 
 /// 扫描现有文件（带进度回调）
+/// 
+/// 遍历结果与持久化的文件目录对比（见 `catalog` 模块），离线期间删除的文件从索引中删除，
+/// 移动的文件改写路径，新增和修改的文件交给扫描流水线（见 `pipeline` 模块）并行提取文本和
+/// 关键词，线程数和批量大小由 `IndexConfig` 控制。`progress_callback(已处理数, 总数)`
+/// 中未变化和已移动的文件一次性计入，其余文件每处理完一个调用一次。
+/// 
+/// 被 `filter` 排除的文件不在遍历结果中，此前已索引的会按删除处理。
 pub fn scan_existing_files_with_progress<F>(
     watch_path: &Path, 
-    index: &Index, 
-    schema: &Schema, 
-    bert: &BertModel, 
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
+    filter: &Arc<PathFilter>,
+    ctx: &IndexContext,
     progress_callback: F,
 ) -> Result<()> 
 where
     F: Fn(usize, usize) + Send + Sync,
 {
-    let _ = cleanup_orphan_indexes(index, schema, cache);
-    
-    // 先统计文件总数
-    let total_files = count_supported_files(watch_path);
-    tracing::info!("正在扫描现有文件... (共 {} 个支持的文件)", total_files);
-    
-    let mut file_count = 0;
-
-    if CONFIG.walker.use_ripgrep_walker {
-        scan_with_ripgrep_walker_progress(watch_path, index, schema, bert, cache, registry, &mut file_count, total_files, &progress_callback)?;
-    } else {
-        scan_with_std_walker_progress(watch_path, index, schema, bert, cache, registry, &mut file_count, total_files, &progress_callback)?;
-    }
-    
-    tracing::info!("初始索引完成，共处理 {} 个文件", file_count);
-    Ok(())
-}
-
-/// 统计目录下支持的文件数量
-fn count_supported_files(dir: &Path) -> usize {
-    if !dir.exists() {
-        return 0;
-    }
-    
-    let mut count = 0;
-    
-    if CONFIG.walker.use_ripgrep_walker {
-        let walker_config = &CONFIG.walker;
-        let mut builder = ignore::WalkBuilder::new(dir);
-        builder
-            .hidden(!walker_config.skip_hidden)
-            .git_ignore(false)
-            .git_global(false)
-            .git_exclude(false)
-            .ignore(walker_config.respect_ignore)
-            .follow_links(walker_config.follow_symlinks);
-        
-        if walker_config.max_depth > 0 {
-            builder.max_depth(Some(walker_config.max_depth));
-        }
-        
-        for result in builder.build() {
-            if let Ok(entry) = result {
-                let path = entry.path();
-                if !path.is_dir() && is_supported_file(path) {
-                    count += 1;
-                }
-            }
-        }
-    } else {
-        fn count_recursive(dir: &Path, count: &mut usize) {
-            if let Ok(entries) = fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() && is_supported_file(&path) {
-                        *count += 1;
-                    } else if path.is_dir() {
-                        count_recursive(&path, count);
-                    }
-                }
-            }
-        }
-        count_recursive(dir, &mut count);
-    }
-    
-    count
-}
-
-fn scan_with_ripgrep_walker_progress<F>(
-    watch_path: &Path,
-    index: &Index,
-    schema: &Schema,
-    bert: &BertModel,
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-    file_count: &mut usize,
-    total_files: usize,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(usize, usize),
-{
-    let walker_config = &CONFIG.walker;
-    
-    let mut builder = ignore::WalkBuilder::new(watch_path);
-    builder
-        .hidden(!walker_config.skip_hidden)
-        .git_ignore(false)
-        .git_global(false)
-        .git_exclude(false)
-        .ignore(walker_config.respect_ignore)
-        .follow_links(walker_config.follow_symlinks);
-    
-    if walker_config.max_depth > 0 {
-        builder.max_depth(Some(walker_config.max_depth));
+    let IndexContext { index, writer, schema, bert, cache, registry, config } = ctx;
+    let root = watch_path.canonicalize().unwrap_or_else(|_| watch_path.to_path_buf());
+    let pipeline = ScanPipeline::new(writer, SchemaFields::from_schema(schema), bert, cache, registry, config);
+    
+    // 与文件目录对比，只处理离线期间新增、修改、删除和移动的文件
+    let started = Instant::now();
+    let files = walk_supported_files(&root, filter, pipeline.threads);
+    let total_files = files.len();
+    let diff = cache.catalog().diff(&root, files);
+    tracing::info!(
+        "正在扫描现有文件... (共 {} 个支持的文件: 新增 {}, 修改 {}, 删除 {}, 移动 {}, 未变化 {}, 对比耗时 {:?})",
+        total_files, diff.added.len(), diff.modified.len(), diff.deleted.len(), diff.moved.len(), diff.unchanged,
+        started.elapsed()
+    );
+    
+    for path in &diff.deleted {
+        let path_str = path.to_string_lossy();
+        writer.delete(path_str.to_string())?;
+        let _ = cache.remove(&path_str);
+        let _ = cache.remove_file_meta(&path_str);
     }
     
-    tracing::debug!("开始遍历目录: {:?}", watch_path);
-    
-    for result in builder.build() {
-        match result {
-            Ok(entry) => {
-                let path = entry.path();
-                tracing::debug!("发现条目: {:?}, is_dir={}, is_supported={}", 
-                    path, path.is_dir(), is_supported_file(path));
-                if path.is_dir() || !is_supported_file(path) {
-                    continue;
+    // 移动的文件改写索引中的路径，改写失败的按新文件索引
+    let mut skipped = diff.unchanged;
+    let mut pending = diff.added;
+    pending.extend(diff.modified);
+    if !diff.moved.is_empty() {
+        let reader = index.reader()?;
+        for (from, to) in diff.moved {
+            match relocate_indexed(&from, &to, &reader, writer, schema, cache) {
+                Ok(relocated) if !relocated.is_empty() => skipped += 1,
+                Ok(_) => pending.push(to),
+                Err(e) => {
+                    tracing::error!("[路径迁移] 改写索引失败 {:?} -> {:?}: {}", from, to, e);
+                    pending.push(to);
                 }
-                process_file_entry(path, index, schema, bert, cache, registry, file_count);
-                progress_callback(*file_count, total_files);
-            }
-            Err(e) => {
-                tracing::warn!("遍历错误: {}", e);
             }
         }
     }
     
-    Ok(())
-}
-
-fn scan_with_std_walker_progress<F>(
-    watch_path: &Path,
-    index: &Index,
-    schema: &Schema,
-    bert: &BertModel,
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-    file_count: &mut usize,
-    total_files: usize,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(usize, usize),
-{
-    fn visit_dirs<F2>(
-        dir: &Path, 
-        index: &Index, 
-        schema: &Schema, 
-        file_count: &mut usize, 
-        bert: &BertModel, 
-        cache: &EmbeddingCache,
-        registry: &FileRegistry,
-        total_files: usize,
-        progress_callback: &F2,
-    ) -> Result<()>
-    where
-        F2: Fn(usize, usize),
-    {
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    visit_dirs(&path, index, schema, file_count, bert, cache, registry, total_files, progress_callback)?;
-                } else if path.is_file() && is_supported_file(&path) {
-                    process_file_entry(&path, index, schema, bert, cache, registry, file_count);
-                    progress_callback(*file_count, total_files);
-                }
-            }
-        }
-        Ok(())
-    }
-
-    visit_dirs(watch_path, index, schema, file_count, bert, cache, registry, total_files, progress_callback)
-}
-
-fn scan_with_ripgrep_walker(
-    watch_path: &Path,
-    index: &Index,
-    schema: &Schema,
-    bert: &BertModel,
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-    file_count: &mut usize,
-) -> Result<()> {
-    let walker_config = &CONFIG.walker;
-    
-    let mut builder = WalkBuilder::new(watch_path);
-    builder
-        .hidden(!walker_config.skip_hidden)
-        // 注意：用户明确指定要索引的目录，不应该被 .gitignore 排除
-        // 所以禁用 gitignore，但保留其他 ignore 规则
-        .git_ignore(false)
-        .git_global(false)
-        .git_exclude(false)
-        .ignore(walker_config.respect_ignore)
-        .follow_links(walker_config.follow_symlinks);
-    
-    if walker_config.max_depth > 0 {
-        builder.max_depth(Some(walker_config.max_depth));
-    }
-    
-    tracing::debug!("开始遍历目录: {:?}", watch_path);
+    tracing::info!("[扫描] {} 个工作线程, 每批 {} 个文档", pipeline.threads, pipeline.batch_size);
+    let stats = pipeline.run(pending, skipped, total_files, &progress_callback);
     
-    for result in builder.build() {
-        match result {
-            Ok(entry) => {
-                let path = entry.path();
-                tracing::debug!("发现条目: {:?}, is_dir={}, is_supported={}", 
-                    path, path.is_dir(), is_supported_file(path));
-                if path.is_dir() || !is_supported_file(path) {
-                    continue;
-                }
-                process_file_entry(path, index, schema, bert, cache, registry, file_count);
-            }
-            Err(e) => {
-                tracing::warn!("遍历错误: {}", e);
-            }
-        }
+    // 扫描结束时立即提交，保证结果可见
+    writer.commit()?;
+    if let Err(e) = cache.save_vector_index() {
+        tracing::warn!("[向量索引] 保存失败: {}", e);
     }
-    
+    tracing::info!("初始索引完成，共处理 {} 个文件，索引 {} 个", stats.handled(), stats.indexed());
     Ok(())
 }
 
-fn scan_with_std_walker(
-    watch_path: &Path,
-    index: &Index,
-    schema: &Schema,
-    bert: &BertModel,
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-    file_count: &mut usize,
-) -> Result<()> {
-    fn visit_dirs(
-        dir: &Path, 
-        index: &Index, 
-        schema: &Schema, 
-        file_count: &mut usize, 
-        bert: &BertModel, 
-        cache: &EmbeddingCache,
-        registry: &FileRegistry,
-    ) -> Result<()> {
-        if dir.is_dir() {
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    visit_dirs(&path, index, schema, file_count, bert, cache, registry)?;
-                } else if path.is_file() && is_supported_file(&path) {
-                    process_file_entry(&path, index, schema, bert, cache, registry, file_count);
-                }
-            }
-        }
-        Ok(())
-    }
-
-    visit_dirs(watch_path, index, schema, file_count, bert, cache, registry)
-}
-
-fn process_file_entry(
-    path: &Path,
-    index: &Index,
-    schema: &Schema,
-    bert: &BertModel,
-    cache: &EmbeddingCache,
-    registry: &FileRegistry,
-    file_count: &mut usize,
-) {
-    let path_buf = path.to_path_buf();
-    let path_str = path.canonicalize()
-        .unwrap_or_else(|_| path.to_path_buf())
-        .to_string_lossy()
-        .to_string();
-    
-    let status = cache.check_file_status(&path_str, path);
-    tracing::debug!("文件状态检查: {:?} -> {:?}", path.file_name().unwrap_or_default(), status);
-    
-    match status {
-        FileStatus::Unchanged => return,
-        FileStatus::New => {
-            tracing::debug!("[新增] {}", path.file_name().unwrap_or_default().to_string_lossy());
-        }
-        FileStatus::Modified => {
-            tracing::debug!("[变更] {}", path.file_name().unwrap_or_default().to_string_lossy());
-        }
-    }
-    
-    if let Some(modified_time) = get_modified_time(path) {
-        if registry.try_start_processing(&path_buf, modified_time) {
-            match process_and_index(path, index, schema, bert, cache) {
-                Ok(_) => *file_count += 1,
-                Err(e) => tracing::error!("处理文件失败 {:?}: {}", path, e),
-            }
-            registry.finish_processing(&path_buf);
-        }
-    }
-}
-
-fn is_supported_file(path: &Path) -> bool {
+pub(crate) fn is_supported_file(path: &Path) -> bool {
     if path.to_string_lossy().contains(".DS_Store") {
         return false;
     }
@@ -556,22 +433,31 @@ pub fn is_file_supported(path: &Path) -> bool {
     is_supported_file(path)
 }
 
-fn get_modified_time(path: &Path) -> Option<SystemTime> {
+pub(crate) fn get_modified_time(path: &Path) -> Option<SystemTime> {
     fs::metadata(path).ok()?.modified().ok()
 }
 
+/// 初始扫描期间检查扫描是否完成的间隔
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// 启动文件监控
+/// 
+/// 事件经 `EventQueue` 按路径防抖合并（见 `WatcherConfig`），初始扫描完成前只收集不处理。
+/// 文件和目录的重命名/移动通过 `relocate_indexed` 改写索引中的路径，不重新运行 BERT。
+/// 与初始扫描使用同一个 `filter`，被排除的路径不产生事件。
 pub fn start_file_watcher(
     watch_path: PathBuf, 
-    index: Index, 
-    schema: Schema, 
-    bert: Arc<BertModel>, 
-    cache: Arc<EmbeddingCache>,
-    registry: FileRegistry,
+    filter: Arc<PathFilter>,
+    reader: IndexReader,
+    ctx: IndexContext,
+    watcher_config: &WatcherConfig,
 ) -> Sender<()> {
     let (scan_complete_tx, scan_complete_rx): (Sender<()>, Receiver<()>) = channel();
+    let (quiet, max_latency) = (watcher_config.quiet(), watcher_config.max_latency());
     
     thread::spawn(move || {
+        // 事件路径与过滤器、索引中的路径一致，都基于规范化的监控目录
+        let watch_path = watch_path.canonicalize().unwrap_or(watch_path);
         let (tx, rx) = channel();
         let mut watcher = match RecommendedWatcher::new(tx, Config::default()) {
             Ok(w) => w,
@@ -588,120 +474,236 @@ pub fn start_file_watcher(
 
         tracing::info!("文件监控已启动: {:?}", watch_path);
 
-        // 等待扫描完成，期间收集事件到 pending_events
+        let IndexContext { writer, schema, bert, cache, registry, config, .. } = ctx;
+        let ctx = WatchContext { reader, writer, schema, bert, cache, registry, filter, passage: config.passage };
+        let mut queue = EventQueue::new(quiet, max_latency);
+        let mut renames = RenameTracker::default();
+        let mut scanning = true;
         loop {
-            // 非阻塞检查扫描是否完成
-            match scan_complete_rx.try_recv() {
-                Ok(()) => {
-                    tracing::info!("扫描完成，开始处理实时事件");
-                    break;
-                }
-                Err(std::sync::mpsc::TryRecvError::Empty) => {
-                    // 扫描未完成，收集事件到待处理队列
-                    match rx.recv_timeout(std::time::Duration::from_millis(100)) {
-                        Ok(res) => {
-                            if let Ok(event) = res {
-                                let event_type = match event.kind {
-                                    EventKind::Create(_) => Some(EventType::Create),
-                                    EventKind::Modify(notify::event::ModifyKind::Data(_)) => Some(EventType::Modify),
-                                    EventKind::Remove(_) => Some(EventType::Delete),
-                                    _ => None,
-                                };
-                                if let Some(et) = event_type {
-                                    for path in event.paths {
-                                        if is_supported_file(&path) {
-                                            registry.add_pending_event(path, et.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
-                        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            if scanning {
+                match scan_complete_rx.try_recv() {
+                    Ok(()) => {
+                        tracing::info!("扫描完成，开始处理实时事件（{} 个待处理文件）", queue.len());
+                        ctx.registry.complete_scan();
+                        scanning = false;
                     }
+                    Err(TryRecvError::Empty) => {}
+                    Err(TryRecvError::Disconnected) => return,
                 }
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => return,
             }
-        }
-
-        // 处理扫描期间的待处理事件（去重：只处理扫描后修改的文件）
-        let pending_events = registry.complete_scan();
-        for event in pending_events {
-            if is_supported_file(&event.path) {
-                // 检查文件是否在扫描时已经处理过且未再修改
-                if let Some(file_mod_time) = get_modified_time(&event.path) {
-                    if registry.is_file_processed(&event.path, file_mod_time) {
-                        tracing::debug!("跳过已处理的文件: {:?}", event.path);
-                        continue;
-                    }
+            
+            // 扫描期间定期检查扫描状态，之后等到队列中最早的路径到期
+            let received = match (scanning, queue.next_deadline()) {
+                (true, _) => rx.recv_timeout(SCAN_POLL_INTERVAL),
+                (false, Some(deadline)) => {
+                    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
                 }
-                
-                match event.event_type {
-                    EventType::Create | EventType::Modify => {
-                        let _ = process_and_index(&event.path, &index, &schema, &bert, &cache);
+                (false, None) => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(Ok(event)) => {
+                    tracing::debug!("收到文件事件: {:?}", event);
+                    for path in &event.paths {
+                        ctx.filter.invalidate_ignore_file(path);
                     }
-                    EventType::Delete => {
-                        let _ = delete_from_index(&event.path, &index, &schema, Some(&cache));
-                        registry.mark_deleted(&event.path);
+                    let now = Instant::now();
+                    for file_event in renames.file_events(event, &ctx.filter) {
+                        queue.push(file_event, now);
                     }
                 }
+                Ok(Err(e)) => tracing::error!("Watch error: {:?}", e),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            
+            if !scanning {
+                for action in queue.drain_ready(Instant::now()) {
+                    ctx.apply(action);
+                }
             }
         }
+    });
 
-        // 处理实时事件
-        for res in rx {
-            match res {
-                Ok(event) => {
-                    tracing::debug!("收到文件事件: {:?}", event);
-                    
-                    let event_type = match event.kind {
-                        EventKind::Create(_) => Some(EventType::Create),
-                        EventKind::Modify(notify::event::ModifyKind::Data(_)) => Some(EventType::Modify),
-                        EventKind::Remove(_) => Some(EventType::Delete),
-                        _ => None,
-                    };
-
-                    let event_type = match event_type {
-                        Some(t) => t,
-                        None => continue,
-                    };
-
-                    for path in event.paths {
-                        if !is_supported_file(&path) {
-                            continue;
-                        }
-                        
-                        // 使用 registry 防止重复处理
-                        let path_buf = path.to_path_buf();
-                        if let Some(modified_time) = get_modified_time(&path) {
-                            if !registry.try_start_processing(&path_buf, modified_time) {
-                                tracing::debug!("跳过正在处理或已处理的文件: {:?}", path);
-                                continue;
-                            }
-                        }
+    scan_complete_tx
+}
 
-                        match event_type {
-                            EventType::Create | EventType::Modify => {
-                                if !path.exists() {
-                                    let _ = delete_from_index(&path, &index, &schema, Some(&cache));
-                                    registry.mark_deleted(&path_buf);
-                                } else {
-                                    let _ = process_and_index(&path, &index, &schema, &bert, &cache);
-                                }
-                            }
-                            EventType::Delete => {
-                                let _ = delete_from_index(&path, &index, &schema, Some(&cache));
-                                registry.mark_deleted(&path_buf);
-                            }
-                        }
-                        
-                        registry.finish_processing(&path_buf);
+/// 将 notify 事件转换为支持且未被排除的文件的 `FileEvent`，并配对重命名的源路径和目标路径
+/// 
+/// inotify 依次发出 `From`、`To` 和成对的 `Both` 事件，Windows 只发出 `From` 和 `To`，
+/// macOS 只发出不区分方向的 `Any`，按路径是否存在判断。
+#[derive(Default)]
+struct RenameTracker {
+    /// 最近一个尚未配对的 `From` 路径
+    pending_from: Option<PathBuf>,
+    /// 最近一次由 `To` 配对的重命名，用于忽略随后相同的 `Both` 事件
+    last_rename: Option<(PathBuf, PathBuf)>,
+}
+
+impl RenameTracker {
+    fn file_events(&mut self, event: notify::Event, filter: &PathFilter) -> Vec<FileEvent> {
+        let mut paths = event.paths.into_iter();
+        let events = match event.kind {
+            EventKind::Create(_) => paths.map(FileEvent::Created).collect(),
+            EventKind::Modify(ModifyKind::Data(_)) => paths.map(FileEvent::Modified).collect(),
+            EventKind::Remove(_) => paths.map(FileEvent::Removed).collect(),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                // 源路径先按删除处理，移出监控目录时不会再有配对的事件
+                self.pending_from = paths.next_back();
+                self.pending_from.clone().map(FileEvent::Removed).into_iter().collect()
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let Some(to) = paths.next_back() else { return Vec::new() };
+                match self.pending_from.take() {
+                    Some(from) => {
+                        self.last_rename = Some((from.clone(), to.clone()));
+                        vec![FileEvent::Renamed { from, to }]
                     }
+                    None => vec![FileEvent::Created(to)],
                 }
-                Err(e) => tracing::error!("Watch error: {:?}", e),
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                let (Some(from), Some(to)) = (paths.next(), paths.next()) else { return Vec::new() };
+                self.pending_from = None;
+                if self.last_rename.take().is_some_and(|(f, t)| f == from && t == to) {
+                    return Vec::new();
+                }
+                vec![FileEvent::Renamed { from, to }]
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => paths
+                .map(|path| if path.exists() { FileEvent::Created(path) } else { FileEvent::Removed(path) })
+                .collect(),
+            _ => Vec::new(),
+        };
+        events.into_iter().filter_map(|event| supported_event(event, filter)).collect()
+    }
+}
+
+/// 只保留涉及支持且未被排除的文件或目录的事件，重命名只有一端符合时按创建或删除处理
+fn supported_event(event: FileEvent, filter: &PathFilter) -> Option<FileEvent> {
+    let watched_file = |path: &Path| is_supported_file(path) && !filter.is_excluded(path, false);
+    match event {
+        FileEvent::Created(ref path) if path.is_dir() => (!filter.is_excluded(path, true)).then_some(event),
+        FileEvent::Created(ref path) | FileEvent::Modified(ref path) => watched_file(path).then_some(event),
+        // 已删除的路径无法判断是否为目录，执行时再按目录清理子树
+        FileEvent::Removed(_) => Some(event),
+        // 目录移入被排除的位置时清理原路径下的索引
+        FileEvent::Renamed { from, to } if to.is_dir() => {
+            if filter.is_excluded(&to, true) {
+                Some(FileEvent::Removed(from))
+            } else {
+                Some(FileEvent::Renamed { from, to })
             }
         }
-    });
+        FileEvent::Renamed { from, to } => match (watched_file(&from), watched_file(&to)) {
+            (true, true) => Some(FileEvent::Renamed { from, to }),
+            (false, true) => Some(FileEvent::Created(to)),
+            (true, false) => Some(FileEvent::Removed(from)),
+            (false, false) => None,
+        },
+    }
+}
 
-    scan_complete_tx
+/// 监控线程处理事件所需的索引和缓存
+struct WatchContext {
+    reader: IndexReader,
+    writer: IndexWriterHandle,
+    schema: Schema,
+    bert: Arc<BertModel>,
+    cache: Arc<EmbeddingCache>,
+    registry: FileRegistry,
+    filter: Arc<PathFilter>,
+    passage: PassageConfig,
+}
+
+impl WatchContext {
+    /// 执行防抖队列输出的动作
+    fn apply(&self, action: WatchAction) {
+        match action {
+            WatchAction::Index(path) if path.is_dir() => self.index_tree(&path, &HashSet::new()),
+            WatchAction::Index(path) => self.index_file(&path),
+            WatchAction::Delete(path) if is_supported_file(&path) => self.delete_file(&path),
+            WatchAction::Delete(path) => self.delete_tree(&path),
+            WatchAction::Move { from, to } => self.move_path(&from, &to),
+        }
+    }
+
+    /// 移动文件或目录：改写索引中已有的文件，其余文件按新文件索引
+    fn move_path(&self, from: &Path, to: &Path) {
+        tracing::info!("路径已移动: {:?} -> {:?}", from, to);
+        let relocated = match relocate_indexed(from, to, &self.reader, &self.writer, &self.schema, &self.cache) {
+            Ok(relocated) => relocated,
+            Err(e) => {
+                tracing::error!("[路径迁移] 改写索引失败 {:?} -> {:?}: {}", from, to, e);
+                Vec::new()
+            }
+        };
+        let relocated: HashSet<PathBuf> = relocated.into_iter().collect();
+        self.registry.mark_deleted(&from.to_path_buf());
+        if to.is_dir() {
+            self.index_tree(to, &relocated);
+        } else if relocated.is_empty() {
+            self.index_file(to);
+        }
+    }
+
+    /// 索引目录下除 `skip` 以外的所有支持且未被排除的文件
+    fn index_tree(&self, dir: &Path, skip: &HashSet<PathBuf>) {
+        for entry in build_walker(dir, self.filter.clone()).build().flatten() {
+            let path = entry.path();
+            if path.is_file() && is_supported_file(path) && !skip.contains(path) {
+                self.index_file(path);
+            }
+        }
+    }
+
+    /// 索引监控到变化的文件，跳过扫描或其他线程已处理过的版本
+    fn index_file(&self, path: &Path) {
+        let path_buf = path.to_path_buf();
+        // 动作到期前文件可能已被删除
+        let Ok(metadata) = fs::metadata(path) else {
+            self.delete_file(path);
+            return;
+        };
+        // 超过大小限制的文件不索引，此前已索引的（文件变大）一并删除
+        if !self.filter.accepts_size(metadata.len()) {
+            tracing::debug!("文件超过大小限制，跳过: {:?}", path);
+            self.delete_file(path);
+            return;
+        }
+        let Some(modified_time) = metadata.modified().ok() else {
+            self.delete_file(path);
+            return;
+        };
+        // touch 或原样保存等内容未变的修改只更新文件目录中的状态
+        let key = path.canonicalize().unwrap_or_else(|_| path_buf.clone()).to_string_lossy().to_string();
+        if matches!(self.cache.check_file_status(&key, path), FileStatus::Unchanged) {
+            tracing::debug!("文件内容未变化，跳过: {:?}", path);
+            return;
+        }
+        if !self.registry.try_start_processing(&path_buf, modified_time) {
+            tracing::debug!("跳过正在处理或已处理的文件: {:?}", path);
+            return;
+        }
+        if let Err(e) = process_and_index(path, &self.writer, &self.schema, &self.bert, &self.cache, &self.passage) {
+            tracing::error!("处理文件失败 {:?}: {}", path, e);
+        }
+        self.registry.finish_processing(&path_buf);
+    }
+
+    fn delete_file(&self, path: &Path) {
+        let _ = delete_from_index(path, &self.writer, Some(&self.cache));
+        self.registry.mark_deleted(&path.to_path_buf());
+    }
+
+    /// 删除目录（或不支持的文件）时清理索引中该路径下的所有文件
+    fn delete_tree(&self, dir: &Path) {
+        match indexed_paths_under(dir, &self.reader, &self.schema) {
+            Ok(paths) => {
+                for path in paths {
+                    self.delete_file(Path::new(&path));
+                }
+            }
+            Err(e) => tracing::error!("读取目录下的索引失败 {:?}: {}", dir, e),
+        }
+    }
 }
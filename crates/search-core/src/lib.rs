@@ -10,15 +10,24 @@
 use std::sync::Arc;
 
 pub mod ai;
+pub mod ann;
 pub mod cache;
+pub mod catalog;
 pub mod config;
+pub mod debounce;
 pub mod extract;
 pub mod indexer;
 pub mod models;
+pub mod passage;
+pub mod path_filter;
+pub mod pipeline;
 pub mod registry;
+pub mod relocate;
 pub mod schema;
 pub mod search;
+pub mod snippet;
 pub mod query_executor;
+pub mod writer;
 
 // RPC 适配层（可选功能）
 #[cfg(feature = "rpc-compat")]
@@ -27,10 +36,15 @@ pub mod rpc_compat;
 // 重导出核心类型
 pub use ai::{BertModel, KeywordExtractor};
 pub use cache::{EmbeddingCache, FileMetaEntry, FileStatus};
-pub use config::{SearchConfig, IndexConfig, AiConfig, WalkerConfig};
+pub use catalog::{hash_file, CatalogDiff, CatalogEntry, DuplicateGroups, FileCatalog, FileStat};
+pub use config::{SearchConfig, IndexConfig, PassageConfig, AiConfig, AnnConfig, FieldBoostConfig, QueryConfig, WalkerConfig, WatchPath, WatcherConfig, SchemaMismatchPolicy};
+pub use debounce::{EventQueue, FileEvent, WatchAction};
 pub use extract::{extract_text, TextExtractor};
 pub use indexer::{
     init_persistent_index, 
+    migrate_index_schema,
+    spawn_index_rebuild,
+    IndexContext,
     scan_existing_files,
     scan_existing_files_with_progress,
     delete_from_index,
@@ -38,16 +52,23 @@ pub use indexer::{
     is_file_supported,
 };
 pub use models::FileDoc;
+pub use passage::{Passage, PassageLocation};
+pub use path_filter::{PathFilter, PROJECT_IGNORE_FILE};
 pub use registry::{FileRegistry, FileState, EventType, PendingEvent};
+pub use relocate::{indexed_paths_under, relocate_indexed};
 pub use schema::{build_schema, IndexDocument, SchemaFields, FIELD_TITLE, FIELD_BODY, FIELD_PATH, FIELD_TAGS, FIELD_FILE_SIZE, FIELD_MODIFIED_TIME};
 pub use search::search_index;
-pub use query_executor::{execute_query, parse_and_execute, QueryContext, QueryExecuteError};
+pub use snippet::Snippet;
+pub use writer::IndexWriterHandle;
+pub use query_executor::{compile_query, execute_query, parse_and_execute, parse_dsl, CompiledQuery, QueryContext, QueryExecuteError};
 
 /// 搜索引擎统一入口
 pub struct SearchEngine {
     pub index: tantivy::Index,
     pub schema: tantivy::schema::Schema,
     pub reader: tantivy::IndexReader,
+    /// 唯一的索引写入句柄，所有新增/删除都经由它批量提交
+    pub writer: IndexWriterHandle,
     pub bert: Arc<BertModel>,
     pub cache: Arc<EmbeddingCache>,
     pub registry: FileRegistry,
@@ -59,8 +80,9 @@ impl SearchEngine {
     pub fn new(config: SearchConfig) -> anyhow::Result<Self> {
         use std::path::Path;
         
-        // 初始化索引
+        // 初始化索引（Schema 版本不兼容时按配置重建或拒绝启动）
         let storage_path = Path::new(&config.index.storage_path);
+        let migrated = indexer::migrate_index_schema(storage_path, config.index.schema_mismatch)?;
         let (index, schema, reader) = init_persistent_index(storage_path)?;
         
         // 加载 AI 模型
@@ -70,17 +92,47 @@ impl SearchEngine {
         
         // 初始化缓存
         let cache_path = Path::new(&config.cache_path);
-        let cache = Arc::new(EmbeddingCache::new(cache_path)?);
+        let cache = Arc::new(EmbeddingCache::open(cache_path, &config.ai.ann)?);
         let (count, size) = cache.stats();
         println!(" [Cache] 缓存统计: {} 条记录, {} 字节", count, size);
         
+        // 索引被重建或替换后，文件目录中的记录全部失效
+        cache.catalog().check_index(&index)?;
+        
+        // 启动索引写入线程
+        let writer = IndexWriterHandle::spawn(&index, cache.clone(), &config.index)?;
+        
         // 创建注册表
         let registry = FileRegistry::new();
         
+        // 索引已重建：根据文件目录在后台恢复之前索引过的文件，按各监控目录的规则过滤
+        if migrated {
+            let paths = cache.catalog().paths();
+            let filters = config.watch_paths
+                .iter()
+                .map(|watch| PathFilter::new(watch, &config::CONFIG.walker).map(Arc::new))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            println!(" [Index] 索引结构已升级，后台重新索引 {} 个文件", paths.len());
+            indexer::spawn_index_rebuild(
+                paths,
+                filters,
+                IndexContext {
+                    index: index.clone(),
+                    writer: writer.clone(),
+                    schema: schema.clone(),
+                    bert: bert.clone(),
+                    cache: cache.clone(),
+                    registry: registry.clone(),
+                    config: config.index.clone(),
+                },
+            );
+        }
+        
         Ok(Self {
             index,
             schema,
             reader,
+            writer,
             bert,
             cache,
             registry,
@@ -90,13 +142,18 @@ impl SearchEngine {
     
     /// 执行搜索（传统全文搜索）
     pub fn search(&self, query: &str) -> anyhow::Result<Vec<SearchHit>> {
-        search::search_with_results(&self.reader, &self.index, query)
+        search::search_with_results(&self.reader, &self.index, &self.config.field_boosts, query)
     }
     
     /// 混合搜索：结合传统全文搜索和语义向量搜索
     /// 
+    /// 全文搜索使用 AI 提取的关键词，向量搜索使用原始查询，
+    /// 以便找到措辞与查询不同但语义相关的文件。
+    /// 按 `config.query.collapse_duplicates` 折叠内容相同的结果（`filter` 包含 `dupes:` 时除外）。
+    /// 
     /// # 参数
     /// - `query`: 搜索查询字符串
+    /// - `filter`: 过滤条件（如 `root:`、`size:`、`mtime:`），两路结果都只保留满足条件的文件
     /// - `use_semantic`: 是否使用语义搜索
     /// - `text_weight`: 传统搜索权重（0.0-1.0）
     /// - `semantic_weight`: 语义搜索权重（0.0-1.0）
@@ -104,30 +161,53 @@ impl SearchEngine {
     pub fn hybrid_search(
         &self,
         query: &str,
+        filter: Option<&query::Query>,
         use_semantic: bool,
         text_weight: f32,
         semantic_weight: f32,
         limit: usize,
     ) -> anyhow::Result<Vec<SearchHit>> {
-        if !use_semantic {
-            // 只使用传统搜索
-            let mut results = self.search(query)?;
-            results.truncate(limit);
-            return Ok(results);
-        }
+        // 正则等无法精确编译的条件按超集处理（不过滤）
+        let fields = SchemaFields::from_schema(&self.schema);
+        let duplicates = self.cache.catalog().duplicate_groups();
+        let filter_query = filter
+            .map(|filter| compile_query(&self.index, filter, Some(duplicates.clone()), &self.config.field_boosts, &self.config.file_types).into_query(&fields));
         
-        // 获取查询的向量表示
-        let query_embedding = self.bert.get_embedding(query).ok();
+        let (text_query, query_embedding) = if use_semantic {
+            // 获取查询的向量表示
+            let query_embedding = match self.bert.embed_query(query) {
+                Ok(embedding) => Some(embedding),
+                Err(e) => {
+                    tracing::warn!("[语义搜索] 查询向量计算失败，仅使用全文搜索: {}", e);
+                    None
+                }
+            };
+            (self.refine_query(query), query_embedding)
+        } else {
+            // 只使用传统搜索
+            (query.to_string(), None)
+        };
         
-        search::hybrid_search(
+        let mut results = search::hybrid_search(
             &self.reader,
             &self.index,
-            query,
+            &self.cache,
+            &self.config.field_boosts,
+            &text_query,
             query_embedding.as_deref(),
-            text_weight,
-            semantic_weight,
-            limit,
-        )
+            &search::HybridOptions {
+                filter: filter_query.as_deref(),
+                text_weight,
+                semantic_weight,
+                limit,
+            },
+        )?;
+        
+        if self.config.query.collapse_duplicates && !filter.is_some_and(query_executor::has_duplicates_term) {
+            let mut collapser = query_executor::DuplicateCollapser::new(&duplicates);
+            results.retain_mut(|hit| collapser.admit(hit));
+        }
+        Ok(results)
     }
     
     /// 使用 AI 优化查询
@@ -140,24 +220,44 @@ impl SearchEngine {
     
     /// 索引单个文件
     pub fn index_file(&self, path: &std::path::Path) -> anyhow::Result<()> {
-        indexer::process_and_index(path, &self.index, &self.schema, &self.bert, &self.cache)
+        indexer::process_and_index(path, &self.writer, &self.schema, &self.bert, &self.cache, &self.config.index.passage)
     }
     
     /// 删除文件索引
     pub fn delete_file(&self, path: &std::path::Path) -> anyhow::Result<bool> {
-        delete_from_index(path, &self.index, &self.schema, Some(&self.cache))
+        delete_from_index(path, &self.writer, Some(&self.cache))
+    }
+    
+    /// 监控目录的路径过滤器
+    ///
+    /// 使用 `config.watch_paths` 中同一目录的规则，未配置该目录时使用默认规则。
+    pub fn path_filter(&self, watch_path: &std::path::Path) -> anyhow::Result<Arc<PathFilter>> {
+        let canonical = |path: &std::path::Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let root = canonical(watch_path);
+        let watch = self.config.watch_paths
+            .iter()
+            .find(|watch| canonical(&watch.path) == root)
+            .cloned()
+            .unwrap_or_else(|| WatchPath::new(watch_path));
+        Ok(Arc::new(PathFilter::new(&watch, &config::CONFIG.walker)?))
+    }
+    
+    /// 扫描、监控和后台重建共用的索引句柄
+    pub fn index_context(&self) -> IndexContext {
+        IndexContext {
+            index: self.index.clone(),
+            writer: self.writer.clone(),
+            schema: self.schema.clone(),
+            bert: self.bert.clone(),
+            cache: self.cache.clone(),
+            registry: self.registry.clone(),
+            config: self.config.index.clone(),
+        }
     }
     
     /// 扫描并索引目录
     pub fn scan_directory(&self, watch_path: &std::path::Path) -> anyhow::Result<()> {
-        scan_existing_files(
-            watch_path,
-            &self.index,
-            &self.schema,
-            &self.bert,
-            &self.cache,
-            &self.registry,
-        )
+        scan_existing_files(watch_path, &self.path_filter(watch_path)?, &self.index_context())
     }
     
     /// 扫描并索引目录（带进度回调）
@@ -165,15 +265,7 @@ impl SearchEngine {
     where
         F: Fn(usize, usize) + Send + Sync,
     {
-        scan_existing_files_with_progress(
-            watch_path,
-            &self.index,
-            &self.schema,
-            &self.bert,
-            &self.cache,
-            &self.registry,
-            progress_callback,
-        )
+        scan_existing_files_with_progress(watch_path, &self.path_filter(watch_path)?, &self.index_context(), progress_callback)
     }
 }
 
@@ -186,10 +278,18 @@ pub struct SearchHit {
     pub tags: Option<String>,
     /// 文件大小（字节），可选
     pub file_size: Option<u64>,
-    /// 修改时间（Unix 时间戳秒），可选
-    pub modified_time: Option<u64>,
+    /// 修改时间（Unix 时间戳秒，1970 年以前为负数），可选
+    pub modified_time: Option<i64>,
     /// 创建时间（Unix 时间戳秒），可选
-    pub created_time: Option<u64>,
+    pub created_time: Option<i64>,
     /// 访问时间（Unix 时间戳秒），可选
-    pub accessed_time: Option<u64>,
+    pub accessed_time: Option<i64>,
+    /// 正则在 body 中的匹配位置（字节偏移），仅 `regex:` 查询会填充
+    pub match_ranges: Vec<std::ops::Range<usize>>,
+    /// 文件中最相关的段落，全文或语义搜索命中段落时填充
+    pub passage: Option<Passage>,
+    /// 预览片段及查询词的高亮位置，没有可展示的正文时为 `None`
+    pub snippet: Option<Snippet>,
+    /// 内容相同、折叠到该结果下的其他文件路径
+    pub duplicates: Vec<String>,
 }
@@ -9,6 +9,12 @@ pub struct FileDoc {
     pub title: String,
     pub content: String,
     pub path: String,
+    /// 分页文档（PDF）每页在 `content` 中的起始字节偏移，其他文档为空
+    #[serde(default)]
+    pub page_starts: Vec<usize>,
+    /// 文件原始字节的 xxh3-64 哈希，未知时为 0
+    #[serde(default)]
+    pub content_hash: u64,
 }
 
 impl FileDoc {
@@ -17,6 +23,8 @@ impl FileDoc {
             title: title.into(),
             content: content.into(),
             path: path.into(),
+            page_starts: Vec::new(),
+            content_hash: 0,
         }
     }
 }
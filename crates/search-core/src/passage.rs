@@ -0,0 +1,162 @@
+// search-core/src/passage.rs
+//! 段落切分模块
+//!
+//! 长文档按字符数切分为相互重叠的段落，每个段落单独写入索引并计算向量，
+//! 搜索时按文件中最相关的段落排序，并把该段落作为预览返回。
+//! 分页文档（PDF）的段落不跨页，记录页码；其他文档记录段落起始行号。
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PassageConfig;
+use crate::models::FileDoc;
+
+/// 段落在原文件中的位置（从 1 开始计数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PassageLocation {
+    /// 所在页码
+    Page(u32),
+    /// 起始行号
+    Line(u32),
+}
+
+/// 文档中的一个段落
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Passage {
+    /// 段落在文档中的序号，与文档向量的分块一一对应
+    pub ordinal: usize,
+    pub text: String,
+    pub location: PassageLocation,
+}
+
+/// 将文档切分为段落
+///
+/// 空白内容不产生段落。相邻段落重叠 `config.overlap` 个字符（最多半个段落）。
+pub fn split_passages(doc: &FileDoc, config: &PassageConfig) -> Vec<Passage> {
+    let content = doc.content.as_str();
+    let mut passages = Vec::new();
+
+    if doc.page_starts.is_empty() {
+        let mut lines = LineCounter::default();
+        for (offset, text) in split_segment(content, config) {
+            let line = lines.line_at(content, offset);
+            passages.push(Passage { ordinal: passages.len(), text, location: PassageLocation::Line(line) });
+        }
+        return passages;
+    }
+
+    for (page, start) in doc.page_starts.iter().enumerate() {
+        let end = doc.page_starts.get(page + 1).copied().unwrap_or(content.len());
+        let Some(page_text) = content.get(*start..end) else {
+            continue;
+        };
+        for (_, text) in split_segment(page_text, config) {
+            let location = PassageLocation::Page(page as u32 + 1);
+            passages.push(Passage { ordinal: passages.len(), text, location });
+        }
+    }
+    passages
+}
+
+/// 按字符数切分一段连续文本，返回 `(段落起始字节偏移, 段落文本)`
+///
+/// 优先在段落后半段的空白或标点处断开，找不到时按字符数硬切。
+fn split_segment(text: &str, config: &PassageConfig) -> Vec<(usize, String)> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let size = config.chars.max(1);
+    let overlap = config.overlap.min(size / 2);
+    let byte_at = |index: usize| chars.get(index).map_or(text.len(), |&(offset, _)| offset);
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+        if end < chars.len() {
+            let search_from = start + size / 2;
+            if let Some(pos) = chars[search_from..end].iter().rposition(|&(_, c)| is_break(c)) {
+                end = search_from + pos + 1;
+            }
+        }
+
+        let raw = &text[byte_at(start)..byte_at(end)];
+        let trimmed = raw.trim_start();
+        let offset = byte_at(start) + (raw.len() - trimmed.len());
+        let trimmed = trimmed.trim_end();
+        if !trimmed.is_empty() {
+            segments.push((offset, trimmed.to_string()));
+        }
+
+        if end >= chars.len() {
+            break;
+        }
+        start = (end - overlap).max(start + 1);
+    }
+    segments
+}
+
+/// 适合作为段落边界的字符
+fn is_break(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '。' | '！' | '？' | '；' | '，' | '.' | '!' | '?' | ';' | ',')
+}
+
+/// 递增地计算字节偏移所在的行号，偏移需单调不减
+#[derive(Default)]
+struct LineCounter {
+    offset: usize,
+    newlines: usize,
+}
+
+impl LineCounter {
+    fn line_at(&mut self, text: &str, offset: usize) -> u32 {
+        self.newlines += text[self.offset..offset].matches('\n').count();
+        self.offset = offset;
+        self.newlines as u32 + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(chars: usize, overlap: usize) -> PassageConfig {
+        PassageConfig { chars, overlap, ..PassageConfig::default() }
+    }
+
+    fn texts(passages: &[Passage]) -> Vec<&str> {
+        passages.iter().map(|p| p.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_split_overlapping_passages() {
+        let doc = FileDoc::new("a", "   ", "/a.txt");
+        assert!(split_passages(&doc, &config(10, 2)).is_empty());
+
+        // 在空白处断开，下一段从上一段末尾往回重叠
+        let doc = FileDoc::new("a", "aaaa bbbb cccc", "/a.txt");
+        assert_eq!(texts(&split_passages(&doc, &config(8, 2))), vec!["aaaa", "a bbbb", "b cccc"]);
+
+        // 没有断点的中文按字符数硬切
+        let doc = FileDoc::new("a", "一二三四五六七八九十", "/a.txt");
+        let passages = split_passages(&doc, &config(4, 1));
+        assert_eq!(texts(&passages), vec!["一二三四", "四五六七", "七八九十"]);
+        assert_eq!(passages.iter().map(|p| p.ordinal).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_passage_locations() {
+        let doc = FileDoc::new("a", "first line\n\nthird line\nfourth", "/a.txt");
+        let passages = split_passages(&doc, &config(12, 0));
+        let locations: Vec<PassageLocation> = passages.iter().map(|p| p.location).collect();
+        assert_eq!(texts(&passages), vec!["first line", "third line", "fourth"]);
+        assert_eq!(
+            locations,
+            vec![PassageLocation::Line(1), PassageLocation::Line(3), PassageLocation::Line(4)]
+        );
+
+        // 分页文档的段落不跨页
+        let mut doc = FileDoc::new("a", "page one\npage two", "/a.pdf");
+        doc.page_starts = vec![0, 9];
+        let passages = split_passages(&doc, &config(100, 10));
+        assert_eq!(texts(&passages), vec!["page one", "page two"]);
+        assert_eq!(passages[1].location, PassageLocation::Page(2));
+    }
+}
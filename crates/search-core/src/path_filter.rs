@@ -0,0 +1,285 @@
+// search-core/src/path_filter.rs
+//! 路径过滤模块 - 初始扫描和文件监控共用的排除规则
+//!
+//! 每个监控目录对应一个 `PathFilter`，依次按以下规则判断路径是否排除：
+//! 1. 监控目录以下的隐藏文件和目录（`WalkerConfig.skip_hidden`）
+//! 2. `WalkerConfig.custom_ignore_patterns` 和该目录的 `exclude`
+//! 3. 监控目录及其子目录中的忽略文件：`.gitignore`、`.ignore` 和 `.unnamedignore`，
+//!    同一目录中后者优先，深层目录优先于浅层目录，可以用 `!pattern` 取消上级目录的排除
+//! 4. 配置了 `include` 时，文件必须匹配其中之一
+//!
+//! 所有规则都使用 gitignore 语法，相对于监控目录匹配。监控目录以外的忽略文件（例如上级仓库的
+//! `.gitignore`）不生效，用户明确指定的目录不会被整体排除。超过 `max-file-size` 的文件由调用方
+//! 读取文件大小后通过 `accepts_size` 排除。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::config::{WalkerConfig, WatchPath};
+
+/// 项目内的忽略文件名，语法与 `.gitignore` 相同
+pub const PROJECT_IGNORE_FILE: &str = ".unnamedignore";
+
+/// 单个监控目录的路径过滤规则
+#[derive(Debug)]
+pub struct PathFilter {
+    /// 规范化的监控目录
+    root: PathBuf,
+    skip_hidden: bool,
+    /// `custom_ignore_patterns` 和 `exclude`
+    excludes: Gitignore,
+    /// `include`，未配置时不限制
+    includes: Option<Gitignore>,
+    max_file_size: Option<u64>,
+    /// 各目录中读取的忽略文件名，按优先级从低到高排列
+    ignore_files: Vec<&'static str>,
+    /// 目录 → 该目录中的忽略文件合并后的规则，没有忽略文件时为 `None`
+    dir_rules: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl PathFilter {
+    /// 按全局 Walker 配置和监控目录的规则构建过滤器，规则语法错误时返回错误
+    pub fn new(watch: &WatchPath, walker: &WalkerConfig) -> Result<Self> {
+        let root = watch.path.canonicalize().unwrap_or_else(|_| watch.path.clone());
+
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in walker.custom_ignore_patterns.iter().chain(&watch.exclude) {
+            builder.add_line(None, pattern).with_context(|| format!("无效的排除规则 '{}'", pattern))?;
+        }
+        let excludes = builder.build()?;
+
+        let includes = if watch.include.is_empty() {
+            None
+        } else {
+            let mut builder = GitignoreBuilder::new(&root);
+            for pattern in &watch.include {
+                builder.add_line(None, pattern).with_context(|| format!("无效的包含规则 '{}'", pattern))?;
+            }
+            Some(builder.build()?)
+        };
+
+        let mut ignore_files = Vec::new();
+        if watch.respect_gitignore.unwrap_or(walker.respect_gitignore) {
+            ignore_files.push(".gitignore");
+        }
+        if walker.respect_ignore {
+            ignore_files.push(".ignore");
+        }
+        if watch.ignore_file {
+            ignore_files.push(PROJECT_IGNORE_FILE);
+        }
+
+        Ok(Self {
+            root,
+            skip_hidden: walker.skip_hidden,
+            excludes,
+            includes,
+            max_file_size: watch.max_file_size,
+            ignore_files,
+            dir_rules: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 路径是否被排除，逐级检查监控目录以下的上级目录，用于监控事件等零散路径
+    ///
+    /// 监控目录本身和监控目录以外的路径不排除。
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let mut current = self.root.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            current.push(component);
+            let entry_is_dir = components.peek().is_some() || is_dir;
+            if self.is_excluded_entry(&current, entry_is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 只判断路径本身，调用方保证上级目录未被排除，用于目录遍历
+    pub fn is_excluded_entry(&self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let Some(name) = relative.file_name() else {
+            return false;
+        };
+        if self.skip_hidden && name.to_string_lossy().starts_with('.') {
+            return true;
+        }
+        if self.excludes.matched(relative, is_dir).is_ignore() {
+            return true;
+        }
+        if self.ignored_by_files(path, is_dir) {
+            return true;
+        }
+        match &self.includes {
+            // 目录总是进入，由其中的文件决定是否索引
+            Some(includes) if !is_dir => !includes.matched_path_or_any_parents(relative, false).is_ignore(),
+            _ => false,
+        }
+    }
+
+    /// 文件大小是否在 `max-file-size` 以内
+    pub fn accepts_size(&self, file_size: u64) -> bool {
+        self.max_file_size.is_none_or(|max| file_size <= max)
+    }
+
+    /// 忽略文件变化后丢弃其所在目录的规则，下次访问时重新读取
+    ///
+    /// 返回 `path` 是否为忽略文件。已索引的文件不会因规则变化立即删除，
+    /// 下次启动扫描时与文件目录对比后清理。
+    pub fn invalidate_ignore_file(&self, path: &Path) -> bool {
+        let is_ignore_file = path
+            .file_name()
+            .is_some_and(|name| self.ignore_files.iter().any(|file| name == *file));
+        if is_ignore_file && let Some(dir) = path.parent() {
+            tracing::info!("忽略文件已变化，重新读取规则: {:?}", path);
+            self.dir_rules.write().unwrap().remove(dir);
+        }
+        is_ignore_file
+    }
+
+    /// 从路径所在目录向上到监控目录，按第一个有匹配的忽略文件判断
+    fn ignored_by_files(&self, path: &Path, is_dir: bool) -> bool {
+        if self.ignore_files.is_empty() {
+            return false;
+        }
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.root) {
+                break;
+            }
+            let Some(rules) = self.dir_rules(dir) else {
+                continue;
+            };
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            match rules.matched(relative, is_dir) {
+                Match::None => continue,
+                matched => return matched.is_ignore(),
+            }
+        }
+        false
+    }
+
+    fn dir_rules(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(rules) = self.dir_rules.read().unwrap().get(dir) {
+            return rules.clone();
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in &self.ignore_files {
+            let file = dir.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            found = true;
+            // 单行语法错误时其余规则仍然生效
+            if let Some(e) = builder.add(&file) {
+                tracing::warn!("忽略文件解析失败 {:?}: {}", file, e);
+            }
+        }
+        let rules = if found {
+            match builder.build() {
+                Ok(rules) => Some(Arc::new(rules)),
+                Err(e) => {
+                    tracing::warn!("忽略文件解析失败 {:?}: {}", dir, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        self.dir_rules.write().unwrap().insert(dir.to_path_buf(), rules.clone());
+        rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn filter(root: &Path, watch: impl FnOnce(&mut WatchPath)) -> PathFilter {
+        let mut watch_path = WatchPath::new(root);
+        watch(&mut watch_path);
+        PathFilter::new(&watch_path, &WalkerConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_ignore_files_and_custom_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/generated")).unwrap();
+        fs::write(root.join(".gitignore"), "build/\n*.bak\n").unwrap();
+        fs::write(root.join("src/.unnamedignore"), "generated/\n!keep.bak\n").unwrap();
+        let filter = filter(&root, |_| {});
+
+        assert!(!filter.is_excluded(&root.join("notes.md"), false));
+        assert!(!filter.is_excluded(&root, true));
+        // custom_ignore_patterns 和隐藏文件
+        assert!(filter.is_excluded(&root.join("app/node_modules/pkg/readme.md"), false));
+        assert!(filter.is_excluded(&root.join(".obsidian/workspace.md"), false));
+        // .gitignore 对子目录生效，目录规则只匹配目录
+        assert!(filter.is_excluded(&root.join("src/build/out.txt"), false));
+        assert!(filter.is_excluded(&root.join("old.bak"), false));
+        // 深层目录的 .unnamedignore 优先
+        assert!(filter.is_excluded(&root.join("src/generated/api.md"), false));
+        assert!(!filter.is_excluded(&root.join("src/keep.bak"), false));
+        assert!(filter.is_excluded_entry(&root.join("src/generated"), true));
+
+        // 关闭 .gitignore 和 .unnamedignore
+        let plain = self::filter(&root, |watch| {
+            watch.respect_gitignore = Some(false);
+            watch.ignore_file = false;
+        });
+        assert!(!plain.is_excluded(&root.join("old.bak"), false));
+        assert!(!plain.is_excluded(&root.join("src/generated/api.md"), false));
+
+        // 忽略文件修改后重新读取
+        fs::write(root.join(".gitignore"), "*.txt\n").unwrap();
+        assert!(filter.is_excluded(&root.join("old.bak"), false));
+        assert!(filter.invalidate_ignore_file(&root.join(".gitignore")));
+        assert!(!filter.invalidate_ignore_file(&root.join("notes.md")));
+        assert!(!filter.is_excluded(&root.join("old.bak"), false));
+        assert!(filter.is_excluded(&root.join("a.txt"), false));
+    }
+
+    #[test]
+    fn test_include_exclude_and_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let filter = filter(&root, |watch| {
+            watch.include = vec!["*.md".into(), "reports/".into()];
+            watch.exclude = vec!["drafts/".into(), "/README.md".into()];
+            watch.max_file_size = Some(1024);
+        });
+
+        assert!(filter.is_excluded(&root.join("notes.txt"), false));
+        assert!(!filter.is_excluded(&root.join("docs/notes.md"), false));
+        // 目录形式的 include 包含其中所有文件
+        assert!(!filter.is_excluded(&root.join("reports/2024/q1.pdf"), false));
+        assert!(!filter.is_excluded(&root.join("docs"), true));
+        assert!(filter.is_excluded(&root.join("drafts/notes.md"), false));
+        // 以 / 开头的规则只匹配监控目录下一级
+        assert!(filter.is_excluded(&root.join("README.md"), false));
+        assert!(!filter.is_excluded(&root.join("docs/README.md"), false));
+
+        assert!(filter.accepts_size(1024));
+        assert!(!filter.accepts_size(1025));
+    }
+}
@@ -0,0 +1,335 @@
+// search-core/src/pipeline.rs
+//! 扫描流水线模块
+//!
+//! 初始扫描先用 `walk_supported_files` 多线程遍历目录并读取文件状态，与文件目录
+//! （见 `catalog` 模块）对比得到需要索引的文件，再交给三个阶段处理，阶段之间用有界 channel 连接：
+//! 1. 提取：固定数量的工作线程并行提取文本
+//! 2. 向量化：单线程攒批调用 BERT 生成关键词和文档向量
+//! 3. 写入：交给唯一的索引写入线程
+//!
+//! 有界 channel 提供背压，慢阶段会让上游阻塞，内存占用不随目录大小增长。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use ignore::{DirEntry, WalkBuilder, WalkState};
+
+use crate::ai::BertModel;
+use crate::cache::EmbeddingCache;
+use crate::catalog::FileStat;
+use crate::config::{IndexConfig, PassageConfig, CONFIG};
+use crate::extract::extract_text;
+use crate::indexer::{get_modified_time, is_supported_file, store_embeddings, submit_document};
+use crate::models::FileDoc;
+use crate::path_filter::PathFilter;
+use crate::registry::FileRegistry;
+use crate::schema::SchemaFields;
+use crate::writer::IndexWriterHandle;
+
+/// 每个工作线程对应的 channel 容量
+const QUEUE_DEPTH_PER_THREAD: usize = 4;
+
+/// 流水线依赖的共享资源
+pub struct ScanPipeline<'a> {
+    pub writer: &'a IndexWriterHandle,
+    pub fields: SchemaFields,
+    pub bert: &'a BertModel,
+    pub cache: &'a EmbeddingCache,
+    pub registry: &'a FileRegistry,
+    /// 段落切分规则，与向量分块一致
+    pub passage: &'a PassageConfig,
+    /// 提取阶段的线程数
+    pub threads: usize,
+    /// 每批送入 BERT 的文档数
+    pub batch_size: usize,
+}
+
+/// 已提取文本、等待向量化的文件
+struct Extracted {
+    source: PathBuf,
+    /// 读取内容前的文件状态
+    stat: FileStat,
+    doc: FileDoc,
+}
+
+/// 扫描统计
+#[derive(Debug, Default)]
+pub struct ScanStats {
+    /// 已处理的支持文件数（含未变化和失败的文件）
+    handled: AtomicUsize,
+    /// 新增或更新到索引的文件数
+    indexed: AtomicUsize,
+}
+
+impl ScanStats {
+    pub fn handled(&self) -> usize {
+        self.handled.load(Ordering::Relaxed)
+    }
+
+    pub fn indexed(&self) -> usize {
+        self.indexed.load(Ordering::Relaxed)
+    }
+}
+
+impl<'a> ScanPipeline<'a> {
+    pub fn new(
+        writer: &'a IndexWriterHandle,
+        fields: SchemaFields,
+        bert: &'a BertModel,
+        cache: &'a EmbeddingCache,
+        registry: &'a FileRegistry,
+        config: &'a IndexConfig,
+    ) -> Self {
+        Self {
+            writer,
+            fields,
+            bert,
+            cache,
+            registry,
+            passage: &config.passage,
+            threads: config.scan_threads().max(1),
+            batch_size: config.embedding_batch_size.max(1),
+        }
+    }
+
+    /// 索引给定的新增或变更文件
+    ///
+    /// `skipped` 为无需处理的文件数，计入进度。每处理完一个文件调用一次
+    /// `progress_callback(已处理数, total)`，回调可能来自任意阶段的线程。
+    pub fn run<F>(&self, paths: Vec<PathBuf>, skipped: usize, total: usize, progress_callback: &F) -> ScanStats
+    where
+        F: Fn(usize, usize) + Send + Sync,
+    {
+        let stats = ScanStats::default();
+        stats.handled.store(skipped, Ordering::Relaxed);
+        if skipped > 0 {
+            progress_callback(skipped, total);
+        }
+        let report = || {
+            let handled = stats.handled.fetch_add(1, Ordering::Relaxed) + 1;
+            progress_callback(handled, total);
+        };
+
+        let depth = self.threads * QUEUE_DEPTH_PER_THREAD;
+        let (path_tx, path_rx) = sync_channel::<PathBuf>(depth);
+        let (doc_tx, doc_rx) = sync_channel::<Extracted>(depth);
+        let path_rx = Arc::new(Mutex::new(path_rx));
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let path_rx = Arc::clone(&path_rx);
+                let doc_tx = doc_tx.clone();
+                scope.spawn(move || self.extract_worker(&path_rx, &doc_tx, &report));
+            }
+            drop(doc_tx);
+
+            scope.spawn(|| self.embedding_stage(doc_rx, &stats, &report));
+
+            // `path_tx` 在发送完后释放，提取线程随之退出
+            for path in paths {
+                if path_tx.send(path).is_err() {
+                    break;
+                }
+            }
+            drop(path_tx);
+        });
+
+        stats
+    }
+
+    /// 提取阶段：从共享队列取文件并提取文本
+    fn extract_worker(
+        &self,
+        path_rx: &Mutex<Receiver<PathBuf>>,
+        doc_tx: &SyncSender<Extracted>,
+        report: &(dyn Fn() + Sync),
+    ) {
+        loop {
+            // 只在取任务时持有锁，提取文本时其他线程可以继续取
+            let received = path_rx.lock().unwrap().recv();
+            let Ok(path) = received else {
+                return;
+            };
+
+            // 文件监控线程可能正在处理同一个文件
+            let claimed = get_modified_time(&path)
+                .is_some_and(|modified_time| self.registry.try_start_processing(&path, modified_time));
+            if !claimed {
+                report();
+                continue;
+            }
+
+            // 在读取内容之前获取文件状态，读取期间的修改在下次扫描时仍能发现
+            let extracted = FileStat::from_path(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|stat| extract_text(&path).map(|doc| (stat, doc)));
+            match extracted {
+                Ok((stat, doc)) => {
+                    if doc_tx.send(Extracted { source: path.clone(), stat, doc }).is_err() {
+                        self.registry.finish_processing(&path);
+                        return;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("处理文件失败 {:?}: {}", path, e);
+                    self.registry.finish_processing(&path);
+                    report();
+                }
+            }
+        }
+    }
+
+    /// 向量化阶段：攒批生成关键词和文档向量后提交给写入线程
+    fn embedding_stage(&self, doc_rx: Receiver<Extracted>, stats: &ScanStats, report: &(dyn Fn() + Sync)) {
+        while let Ok(first) = doc_rx.recv() {
+            let mut batch = vec![first];
+            while batch.len() < self.batch_size {
+                match doc_rx.try_recv() {
+                    Ok(item) => batch.push(item),
+                    Err(_) => break,
+                }
+            }
+
+            let keywords = self.batch_keywords(&batch);
+            let docs: Vec<&FileDoc> = batch.iter().map(|item| &item.doc).collect();
+            if let Err(e) = store_embeddings(self.bert, self.cache, &docs, self.passage) {
+                tracing::warn!("文档向量计算失败 ({} 个文档): {}", docs.len(), e);
+            }
+            for (item, keywords) in batch.into_iter().zip(keywords) {
+                let result = keywords.and_then(|keywords| {
+                    submit_document(&item.source, item.stat, item.doc, keywords, self.writer, &self.fields, self.passage)
+                });
+                match result {
+                    Ok(()) => {
+                        stats.indexed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => tracing::error!("处理文件失败 {:?}: {}", item.source, e),
+                }
+                self.registry.finish_processing(&item.source);
+                report();
+            }
+        }
+    }
+
+    /// 为一批文档生成关键词（优先使用缓存，其余一次性批量计算）
+    fn batch_keywords(&self, batch: &[Extracted]) -> Vec<anyhow::Result<Vec<String>>> {
+        let mut results: Vec<Option<anyhow::Result<Vec<String>>>> = batch.iter()
+            .map(|item| {
+                let cached = self.cache.get_keywords(&item.doc.path, &item.doc.content)?;
+                tracing::debug!("缓存命中: {:?}", cached);
+                Some(Ok(cached))
+            })
+            .collect();
+
+        let misses: Vec<usize> = (0..batch.len()).filter(|&i| results[i].is_none()).collect();
+        if !misses.is_empty() {
+            tracing::debug!("正在分析 {} 个文档的语义...", misses.len());
+            let texts: Vec<&str> = misses.iter().map(|&i| batch[i].doc.content.as_str()).collect();
+            match self.bert.extract_keywords_batch(&texts, 3) {
+                Ok(keywords) => {
+                    for (&i, keywords) in misses.iter().zip(keywords) {
+                        let doc = &batch[i].doc;
+                        let _ = self.cache.set_keywords(&doc.path, &doc.content, keywords.clone());
+                        tracing::debug!("生成标签: {:?}", keywords);
+                        results[i] = Some(Ok(keywords));
+                    }
+                }
+                Err(e) => {
+                    for &i in &misses {
+                        results[i] = Some(Err(anyhow::anyhow!("关键词提取失败: {}", e)));
+                    }
+                }
+            }
+        }
+
+        results.into_iter().map(|result| result.expect("every document has a result")).collect()
+    }
+}
+
+/// 多线程遍历目录，返回所有支持且未被 `filter` 排除的文件及其当前状态
+///
+/// `watch_path` 应为规范化路径。不跟随符号链接时遍历得到的路径已是规范化路径，
+/// 否则逐个规范化，保证与索引和文件目录中的路径一致。
+pub fn walk_supported_files(watch_path: &Path, filter: &Arc<PathFilter>, threads: usize) -> Vec<(PathBuf, FileStat)> {
+    tracing::debug!("开始遍历目录: {:?}", watch_path);
+    let files = Mutex::new(Vec::new());
+    let visit = |path: &Path| {
+        if !is_supported_file(path) {
+            return;
+        }
+        let path = if CONFIG.walker.follow_symlinks {
+            path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+        } else {
+            path.to_path_buf()
+        };
+        match FileStat::from_path(&path) {
+            Ok(stat) if filter.accepts_size(stat.file_size) => files.lock().unwrap().push((path, stat)),
+            Ok(_) => tracing::debug!("文件超过大小限制，跳过: {:?}", path),
+            Err(e) => tracing::warn!("读取文件状态失败 {:?}: {}", path, e),
+        }
+    };
+
+    if CONFIG.walker.use_ripgrep_walker {
+        build_walker(watch_path, filter.clone())
+            .threads(threads)
+            .build_parallel()
+            .run(|| {
+                let visit = &visit;
+                Box::new(move |result: Result<DirEntry, ignore::Error>| {
+                    match result {
+                        Ok(entry) => {
+                            if entry.file_type().is_some_and(|t| !t.is_dir()) {
+                                visit(entry.path());
+                            }
+                        }
+                        Err(e) => tracing::warn!("遍历错误: {}", e),
+                    }
+                    WalkState::Continue
+                })
+            });
+    } else {
+        fn visit_dirs(dir: &Path, filter: &PathFilter, visit: &dyn Fn(&Path)) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if !filter.is_excluded_entry(&path, true) {
+                        visit_dirs(&path, filter, visit);
+                    }
+                } else if path.is_file() && !filter.is_excluded_entry(&path, false) {
+                    visit(&path);
+                }
+            }
+        }
+        visit_dirs(watch_path, filter, &visit);
+    }
+
+    files.into_inner().unwrap()
+}
+
+/// 按全局 Walker 配置构建目录遍历器
+///
+/// 不使用遍历器自带的隐藏文件和忽略文件处理，统一由 `filter` 判断，与文件监控保持一致。
+pub(crate) fn build_walker(dir: &Path, filter: Arc<PathFilter>) -> WalkBuilder {
+    let walker_config = &CONFIG.walker;
+
+    let mut builder = WalkBuilder::new(dir);
+    builder
+        .standard_filters(false)
+        .follow_links(walker_config.follow_symlinks)
+        .filter_entry(move |entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            !filter.is_excluded_entry(entry.path(), is_dir)
+        });
+
+    if walker_config.max_depth > 0 {
+        builder.max_depth(Some(walker_config.max_depth));
+    }
+    builder
+}
@@ -4,16 +4,31 @@
 //! 将解析后的 Query AST 转换为实际的搜索操作。
 //! 支持布尔逻辑（AND/OR/NOT）和各种过滤条件。
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Bound, Range};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use query::{Query, Term, ValidationError};
+use query::{Diagnostics, Query, Term, TextField, ValidationErrorKind};
+use regex::Regex;
 use tantivy::collector::TopDocs;
-use tantivy::query::{AllQuery, QueryParser};
-use tantivy::schema::Value;
-use tantivy::{Index, IndexReader, TantivyDocument};
+use tantivy::query::Query as _;
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, EnableScoring, FuzzyTermQuery, Occur,
+    PhraseQuery, QueryParser, RangeQuery, RegexQuery, TermQuery, TermSetQuery, Weight,
+};
+use tantivy::schema::{Field, IndexRecordOption, Value};
+use tantivy::tokenizer::{TextAnalyzer, TokenStream};
+use tantivy::{DocAddress, DocSet, Index, IndexReader, Searcher, TantivyDocument, TantivyError};
 
-use crate::schema::{FIELD_BODY, FIELD_FILE_SIZE, FIELD_MODIFIED_TIME, FIELD_CREATED_TIME, FIELD_ACCESSED_TIME, FIELD_PATH, FIELD_TITLE};
+use crate::catalog::{DuplicateGroups, FileCatalog};
+use crate::config::{file_type_extensions, FieldBoostConfig, QueryConfig, SearchConfig, CONFIG};
+use crate::schema::{SchemaFields, DOC_KIND_FILE};
+use crate::search::{highlighted_snippet, snippet_generator};
+use crate::snippet::Snippet;
 use crate::SearchHit;
 
 /// 查询执行上下文
@@ -21,233 +36,958 @@ pub struct QueryContext<'a> {
     pub reader: &'a IndexReader,
     pub index: &'a Index,
     pub limit: usize,
+    /// 超过该时间点仍未完成时返回 `QueryExecuteError::Timeout`
+    pub deadline: Option<Instant>,
+    /// 最多逐条校验的候选文档数，超出时返回 `QueryExecuteError::TooManyResults`
+    pub max_scanned: Option<usize>,
+    /// 内容相同的已索引文件分组，用于 `dupes:` 条件和折叠结果；`None` 时视为没有重复文件
+    pub duplicates: Option<Arc<DuplicateGroups>>,
+    /// 是否把内容相同的结果折叠为一个（查询包含 `dupes:` 条件时不折叠）
+    pub collapse_duplicates: bool,
+    /// 全文字段的权重
+    pub field_boosts: FieldBoostConfig,
+    /// 配置的 `type:` 分组（组名 → 扩展名），为空时只有内置分组
+    pub file_types: HashMap<String, Vec<String>>,
+}
+
+impl<'a> QueryContext<'a> {
+    /// 按 `config` 设置超时、候选文档上限和是否折叠重复文件
+    pub fn new(reader: &'a IndexReader, index: &'a Index, limit: usize, config: &QueryConfig) -> Self {
+        Self {
+            reader,
+            index,
+            limit,
+            deadline: (config.timeout_ms > 0)
+                .then(|| Instant::now() + Duration::from_millis(config.timeout_ms)),
+            max_scanned: (config.max_scanned > 0).then_some(config.max_scanned),
+            duplicates: None,
+            collapse_duplicates: config.collapse_duplicates,
+            field_boosts: FieldBoostConfig::default(),
+            file_types: HashMap::new(),
+        }
+    }
+    
+    /// 设置重复文件分组（见 `FileCatalog::duplicate_groups`）
+    pub fn with_duplicates(mut self, duplicates: Arc<DuplicateGroups>) -> Self {
+        self.duplicates = Some(duplicates);
+        self
+    }
+    
+    /// 设置全文字段的权重
+    pub fn with_field_boosts(mut self, field_boosts: &FieldBoostConfig) -> Self {
+        self.field_boosts = field_boosts.clone();
+        self
+    }
+    
+    /// 设置配置的 `type:` 分组
+    pub fn with_file_types(mut self, file_types: &HashMap<String, Vec<String>>) -> Self {
+        self.file_types = file_types.clone();
+        self
+    }
+    
+    /// 检查是否超时，以及已校验的候选文档数是否超出上限
+    fn check_limits(&self, scanned: usize) -> Result<(), QueryExecuteError> {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(QueryExecuteError::Timeout);
+        }
+        if self.max_scanned.is_some_and(|max| scanned > max) {
+            return Err(QueryExecuteError::TooManyResults);
+        }
+        Ok(())
+    }
 }
 
 /// 执行 Query AST 搜索
 /// 
 /// 搜索策略：
-/// 1. 先用 Tantivy 执行全文关键词搜索，得到候选集
-/// 2. 在候选集上应用各种过滤条件（glob、时间、大小等）
-/// 3. 对 AND/OR/NOT 逻辑进行集合运算
-pub fn execute_query(ctx: &QueryContext, query: &Query) -> Result<Vec<SearchHit>> {
-    let schema = ctx.index.schema();
+/// 1. 将 Query AST 编译为 Tantivy 查询（关键词、路径、时间/大小范围、AND/OR/NOT）
+/// 2. 编译结果与原语义完全一致时，直接取 Top-N
+/// 3. 含有无法精确下推的条件（跨词正则、glob）时，一次取出编译结果的所有命中文档，
+///    按得分逐条校验直到凑满 `limit` 条，不再受候选窗口大小限制
+///
+/// 含正则时以正文中第一个匹配附近的文本作为预览片段，否则截取正文中包含关键词的片段。
+/// 每处理一个候选文档都会检查 `ctx` 中的超时和候选文档上限。
+/// 启用折叠时内容相同的文件只返回得分最高的一个，其余路径列在它的 `duplicates` 中，
+/// 被折叠的文件不计入 `limit`。
+pub fn execute_query(ctx: &QueryContext, query: &Query) -> Result<Vec<SearchHit>, QueryExecuteError> {
+    if ctx.limit == 0 || query.matches_nothing() {
+        return Ok(Vec::new());
+    }
     
-    // 收集所有关键词用于 Tantivy 搜索
     let keywords = collect_keywords(query);
-    tracing::info!("[Query执行器] 收集到关键词: {:?}", keywords);
+    let regexes = collect_regexes(query, false);
+    tracing::info!("[Query执行器] 收集到关键词: {:?}, 正则: {:?}", keywords, regexes);
+    
+    let fields = SchemaFields::from_schema(&ctx.index.schema());
+    let compiler = QueryCompiler::new(ctx.index, ctx.duplicates.clone(), &ctx.field_boosts, &ctx.file_types);
+    let compiled = compiler.compile(query);
+    let exact = compiled.exact;
+    let tantivy_query = compiled.into_query(&fields);
+    tracing::debug!("[Query执行器] 编译结果: {:?}, 精确: {}", tantivy_query, exact);
     
-    // 如果没有关键词，获取全部文档作为候选
-    let candidates = if keywords.is_empty() {
-        tracing::info!("[Query执行器] 无关键词，获取全部文档作为候选");
-        get_all_docs(ctx, &schema)?
+    let searcher = ctx.reader.searcher();
+    let keyword_matcher = if exact {
+        None
     } else {
-        // 构建关键词查询
-        let query_str = keywords.join(" ");
-        tracing::info!("[Query执行器] 使用关键词搜索: '{}'", query_str);
-        search_by_keywords(ctx, &query_str)?
+        Some(KeywordMatcher::new(&compiler, &searcher, query)?)
     };
+    let keyword_snippets = if keywords.is_empty() {
+        None
+    } else {
+        Some(snippet_generator(&searcher, tantivy_query.as_ref(), fields.body)?)
+    };
+    // NOT 中的正则同样需要正文来校验，否则会回退为逐个重新读取文件
+    let with_body = !collect_regexes(query, true).is_empty() || keyword_snippets.is_some();
+    let duplicates = ctx.duplicates.as_deref();
+    let mut collapser = duplicates
+        .filter(|_| ctx.collapse_duplicates && !has_duplicates_term(query))
+        .map(DuplicateCollapser::new);
     
-    tracing::info!("[Query执行器] 候选文档数: {}", candidates.len());
+    // 只有精确且不折叠时取 Top-N 即可，否则一次取出所有命中文档，按得分依次处理
+    ctx.check_limits(0)?;
+    let ranked = if exact && collapser.is_none() {
+        searcher.search(tantivy_query.as_ref(), &TopDocs::with_limit(ctx.limit))?
+    } else {
+        ranked_matches(ctx, &searcher, tantivy_query.as_ref())?
+    };
     
-    // 在候选集上应用过滤器
-    let filtered = filter_by_query(candidates, query)?;
+    let mut results = Vec::new();
+    let mut scanned = 0;
+    for (score, address) in ranked {
+        if !exact {
+            scanned += 1;
+            ctx.check_limits(scanned)?;
+        }
+        let mut candidate = load_candidate(&searcher, &fields, score, address, with_body)?;
+        if let Some(matcher) = &keyword_matcher {
+            candidate.matched_keywords = matcher.matched(&searcher, address)?;
+            candidate.duplicate = duplicates.is_some_and(|groups| groups.contains(&candidate.hit.path));
+            if !matches_query(&candidate, query, &ctx.file_types) {
+                continue;
+            }
+        }
+        // 折叠掉的结果不计入数量
+        if collapser.as_mut().is_some_and(|collapser| !collapser.admit(&mut candidate.hit)) {
+            continue;
+        }
+        if !regexes.is_empty() {
+            let text = candidate.text();
+            let ranges = find_regex_matches(&text, &regexes);
+            let snippet = Snippet::around_matches(&text, &ranges, CONFIG.display.preview_max_length);
+            candidate.hit.match_ranges = ranges;
+            candidate.hit.snippet = snippet;
+        } else if let (Some(generator), Some(body)) = (&keyword_snippets, &candidate.body) {
+            candidate.hit.snippet = highlighted_snippet(generator, body);
+        }
+        results.push(candidate.hit);
+        if results.len() >= ctx.limit {
+            break;
+        }
+    }
     
-    tracing::info!("[Query执行器] 过滤后结果数: {}", filtered.len());
+    tracing::info!("[Query执行器] 结果数: {}", results.len());
     
-    Ok(filtered)
+    Ok(results)
 }
 
-/// 从 Query AST 中收集所有关键词
-fn collect_keywords(query: &Query) -> Vec<String> {
-    let mut keywords = Vec::new();
-    collect_keywords_recursive(query, &mut keywords);
-    keywords
+/// 查询命中的所有文档，按得分从高到低排列（得分相同时按文档地址）
+///
+/// 逐个 segment 打分遍历，整个查询只执行一次；处理每个 segment 前检查超时。
+fn ranked_matches(
+    ctx: &QueryContext,
+    searcher: &Searcher,
+    query: &dyn tantivy::query::Query,
+) -> Result<Vec<(f32, DocAddress)>, QueryExecuteError> {
+    let weight = query.weight(EnableScoring::enabled_from_searcher(searcher))?;
+    let mut matches = Vec::new();
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        ctx.check_limits(0)?;
+        weight.for_each(segment_reader, &mut |doc, score| {
+            if !segment_reader.is_deleted(doc) {
+                matches.push((score, DocAddress::new(segment_ord as u32, doc)));
+            }
+        })?;
+    }
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(matches)
+}
+
+/// 候选文档：搜索结果加上用于校验的上下文
+struct Candidate {
+    hit: SearchHit,
+    /// 索引中存储的 body，仅在查询包含正则或关键词时读取
+    body: Option<String>,
+    /// 该文档命中的关键词，仅在需要逐条校验时填充
+    matched_keywords: Vec<String>,
+    /// 文件内容是否与其他已索引文件相同，仅在需要逐条校验时填充
+    duplicate: bool,
+}
+
+impl Candidate {
+    /// 获取用于正则匹配的文本：优先使用索引中存储的 body，否则重新读取文件
+    fn text(&self) -> Cow<'_, str> {
+        match &self.body {
+            Some(body) if !body.is_empty() => Cow::Borrowed(body.as_str()),
+            _ => match crate::extract::extract_text(Path::new(&self.hit.path)) {
+                Ok(doc) => Cow::Owned(doc.content),
+                Err(e) => {
+                    tracing::warn!("[Regex过滤] 无法读取文件 '{}': {}", self.hit.path, e);
+                    Cow::Borrowed("")
+                }
+            },
+        }
+    }
+}
+
+/// 折叠内容相同的结果：每组只保留第一个（得分最高的）结果，其余路径列在它的 `duplicates` 中
+pub(crate) struct DuplicateCollapser<'a> {
+    groups: &'a DuplicateGroups,
+    /// 已有结果的分组
+    seen: HashSet<usize>,
 }
 
-fn collect_keywords_recursive(query: &Query, keywords: &mut Vec<String>) {
+impl<'a> DuplicateCollapser<'a> {
+    pub(crate) fn new(groups: &'a DuplicateGroups) -> Self {
+        Self { groups, seen: HashSet::new() }
+    }
+    
+    /// 结果所在分组已有结果时返回 `false`，否则填充 `duplicates` 并返回 `true`
+    pub(crate) fn admit(&mut self, hit: &mut SearchHit) -> bool {
+        let Some(group) = self.groups.group_of(&hit.path) else {
+            return true;
+        };
+        if !self.seen.insert(group) {
+            return false;
+        }
+        hit.duplicates = self.groups.group(group)
+            .iter()
+            .filter(|path| **path != hit.path)
+            .cloned()
+            .collect();
+        true
+    }
+}
+
+/// 查询是否包含 `dupes:` 条件
+pub(crate) fn has_duplicates_term(query: &Query) -> bool {
     match query {
-        Query::Term(term) => {
-            match term {
-                Term::KeyWord(kw) => {
-                    keywords.push(kw.clone());
+        Query::Term(term) => matches!(term, Term::Duplicates(_)),
+        Query::And(items) | Query::Or(items) => items.iter().any(has_duplicates_term),
+        Query::Not(inner) => has_duplicates_term(inner),
+    }
+}
+
+/// Query AST 编译结果
+pub struct CompiledQuery {
+    /// 编译后的 Tantivy 查询，`None` 表示不限制文档（等价于全部文档）
+    pub query: Option<Box<dyn tantivy::query::Query>>,
+    /// 编译结果是否与原查询语义完全一致；否则命中的是超集，需要逐条校验
+    pub exact: bool,
+}
+
+impl CompiledQuery {
+    fn exact(query: Box<dyn tantivy::query::Query>) -> Self {
+        Self { query: Some(query), exact: true }
+    }
+    
+    fn superset(query: Option<Box<dyn tantivy::query::Query>>) -> Self {
+        Self { query, exact: false }
+    }
+    
+    /// 转换为可执行的查询，只匹配文件文档（段落文档仅用于全文检索）
+    pub fn into_query(self, fields: &SchemaFields) -> Box<dyn tantivy::query::Query> {
+        let files = text_term_query(fields.doc_kind, DOC_KIND_FILE);
+        match self.query {
+            Some(query) => Box::new(BooleanQuery::new(vec![(Occur::Must, query), (Occur::Must, files)])),
+            None => files,
+        }
+    }
+}
+
+/// 将 Query AST 编译为 Tantivy 查询
+///
+/// - 关键词：title/body/tags 上的 `QueryParser` 查询
+/// - `title:`/`body:`/`tag:`：只在对应字段上的 `QueryParser` 查询
+/// - 短语：title/body/tags（或限定字段）上按分词位置构造的 `PhraseQuery`，`~N` 为允许的间隔
+/// - 模糊词：title/body/tags（或限定字段）上每个分词的 `FuzzyTermQuery`
+/// - 全文词项按 `boosts` 对各字段加权，`^N` 再包装为 `BoostQuery`
+/// - `root:`：parent_path 上的 `TermQuery`
+/// - `glob:`/`name:`：filename/path 词典上的 `RegexQuery`
+/// - `ext:`：file_type 上的 `TermQuery`
+/// - `type:`：按 `file_types` 和内置分组展开为 file_type 上 `TermQuery` 的 Should 组合
+/// - 时间/大小：FAST 字段上的 `RangeQuery`
+/// - `dupes:`：`duplicates` 中所有文件路径上的 `TermSetQuery`，未提供分组时视为没有重复文件
+/// - AND/OR/NOT：`BooleanQuery` 的 Must/Should/MustNot 子句
+/// - 正则：词级别模式下推为词典上的 `RegexQuery`（超集），其余不限制
+pub fn compile_query(
+    index: &Index,
+    query: &Query,
+    duplicates: Option<Arc<DuplicateGroups>>,
+    boosts: &FieldBoostConfig,
+    file_types: &HashMap<String, Vec<String>>,
+) -> CompiledQuery {
+    QueryCompiler::new(index, duplicates, boosts, file_types).compile(query)
+}
+
+struct QueryCompiler {
+    index: Index,
+    fields: SchemaFields,
+    parser: QueryParser,
+    /// body 字段的分词器，短语和模糊词按索引时的分词结果构造；未注册时按空白切分
+    tokenizer: Option<TextAnalyzer>,
+    /// 内容相同的文件分组，用于编译 `dupes:`
+    duplicates: Option<Arc<DuplicateGroups>>,
+    /// 全文字段的权重
+    boosts: FieldBoostConfig,
+    /// 配置的 `type:` 分组
+    file_types: HashMap<String, Vec<String>>,
+}
+
+impl QueryCompiler {
+    fn new(
+        index: &Index,
+        duplicates: Option<Arc<DuplicateGroups>>,
+        boosts: &FieldBoostConfig,
+        file_types: &HashMap<String, Vec<String>>,
+    ) -> Self {
+        let fields = SchemaFields::from_schema(&index.schema());
+        let parser = text_parser(index, &fields, boosts, vec![fields.title, fields.body, fields.tags]);
+        let tokenizer = match index.tokenizer_for_field(fields.body) {
+            Ok(tokenizer) => Some(tokenizer),
+            Err(e) => {
+                tracing::warn!("[Query执行器] 无法获取分词器，短语按空白切分: {}", e);
+                None
+            }
+        };
+        Self {
+            index: index.clone(),
+            fields,
+            parser,
+            tokenizer,
+            duplicates,
+            boosts: boosts.clone(),
+            file_types: file_types.clone(),
+        }
+    }
+    
+    fn compile(&self, query: &Query) -> CompiledQuery {
+        match query {
+            Query::Term(term) => self.compile_term(term),
+            Query::And(items) => self.compile_and(items),
+            Query::Or(items) => self.compile_or(items),
+            Query::Not(inner) => {
+                let inner = self.compile(inner);
+                if !inner.exact {
+                    // 超集取反不是子集，只能全部交给逐条校验
+                    return CompiledQuery::superset(None);
+                }
+                match inner.query {
+                    Some(q) => CompiledQuery::exact(Box::new(BooleanQuery::new(vec![
+                        (Occur::Must, Box::new(AllQuery)),
+                        (Occur::MustNot, q),
+                    ]))),
+                    None => CompiledQuery::exact(Box::new(EmptyQuery)),
                 }
-                Term::Regex(re) => {
-                    // 将正则表达式的模式作为关键词用于 Tantivy 搜索
-                    // Tantivy 会对其进行分词并在 body 中搜索
-                    let pattern = re.as_str();
-                    if !pattern.is_empty() {
-                        keywords.push(pattern.to_string());
+            }
+        }
+    }
+    
+    fn compile_and(&self, items: &[Query]) -> CompiledQuery {
+        let mut clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+        let mut exact = true;
+        for item in items {
+            // 精确的 NOT 子项直接作为 MustNot 子句
+            let (occur, compiled) = match item {
+                Query::Not(inner) => {
+                    let negated = self.compile(inner);
+                    if negated.exact && negated.query.is_some() {
+                        (Occur::MustNot, negated)
+                    } else {
+                        (Occur::Must, self.compile(item))
                     }
                 }
-                _ => {}
+                _ => (Occur::Must, self.compile(item)),
+            };
+            exact &= compiled.exact;
+            if let Some(q) = compiled.query {
+                clauses.push((occur, q));
             }
         }
-        Query::And(items) | Query::Or(items) => {
-            for item in items {
-                collect_keywords_recursive(item, keywords);
+        
+        if clauses.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+            if clauses.is_empty() {
+                return CompiledQuery { query: None, exact };
             }
+            clauses.push((Occur::Must, Box::new(AllQuery)));
         }
-        Query::Not(_inner) => {
-            // NOT 中的关键词不加入搜索，但需要在后处理中排除
+        CompiledQuery { query: Some(Box::new(BooleanQuery::new(clauses))), exact }
+    }
+    
+    fn compile_or(&self, items: &[Query]) -> CompiledQuery {
+        let mut clauses: Vec<(Occur, Box<dyn tantivy::query::Query>)> = Vec::new();
+        let mut exact = true;
+        for item in items {
+            let compiled = self.compile(item);
+            exact &= compiled.exact;
+            match compiled.query {
+                Some(q) => clauses.push((Occur::Should, q)),
+                // 任一分支不限制文档，整个 OR 也不限制
+                None => return CompiledQuery { query: None, exact },
+            }
+        }
+        CompiledQuery { query: Some(Box::new(BooleanQuery::new(clauses))), exact }
+    }
+    
+    fn compile_term(&self, term: &Term) -> CompiledQuery {
+        let fields = &self.fields;
+        match term {
+            Term::KeyWord(kw) => CompiledQuery::exact(keyword_query(&self.parser, kw)),
+            Term::FieldText { field, text } => CompiledQuery::exact(self.field_text_query(*field, text)),
+            Term::Phrase { field, text, slop } => CompiledQuery::exact(self.phrase_query(*field, text, *slop)),
+            Term::Fuzzy { field, word, distance } => CompiledQuery::exact(self.fuzzy_query(*field, word, *distance)),
+            Term::Boosted { term, boost } => {
+                let compiled = self.compile_term(term);
+                CompiledQuery {
+                    query: compiled.query.map(|q| Box::new(BoostQuery::new(q, *boost)) as Box<dyn tantivy::query::Query>),
+                    exact: compiled.exact,
+                }
+            }
+            Term::Root(root) => CompiledQuery::exact(root_query(fields, root)),
+            Term::Regex(re) => CompiledQuery::superset(regex_term_query(re, &[fields.title, fields.body])),
+            Term::Glob(pattern) => match glob_query(fields, pattern) {
+                Some(q) => CompiledQuery::exact(q),
+                None => CompiledQuery::superset(None),
+            },
+            Term::Extension(ext) => CompiledQuery::exact(text_term_query(fields.file_type, ext)),
+            Term::FileType(name) => {
+                let clauses = file_type_extensions(&self.file_types, name)
+                    .iter()
+                    .map(|ext| (Occur::Should, text_term_query(fields.file_type, ext)))
+                    .collect();
+                CompiledQuery::exact(Box::new(BooleanQuery::new(clauses)))
+            }
+            Term::AccessTime(range) => CompiledQuery::exact(i64_range_query(fields.accessed_time, range.min, range.max)),
+            Term::ModifiedTime(range) => CompiledQuery::exact(i64_range_query(fields.modified_time, range.min, range.max)),
+            Term::CreatedTime(range) => CompiledQuery::exact(i64_range_query(fields.created_time, range.min, range.max)),
+            Term::Size(range) => CompiledQuery::exact(u64_range_query(fields.file_size, range.min, range.max)),
+            Term::Duplicates(wanted) => {
+                let duplicates: Box<dyn tantivy::query::Query> = match self.duplicates.as_deref() {
+                    Some(groups) if !groups.is_empty() => Box::new(TermSetQuery::new(
+                        groups.paths().map(|path| tantivy::Term::from_field_text(fields.path, path)),
+                    )),
+                    _ => Box::new(EmptyQuery),
+                };
+                if *wanted {
+                    CompiledQuery::exact(duplicates)
+                } else {
+                    CompiledQuery::exact(Box::new(BooleanQuery::new(vec![
+                        (Occur::Must, Box::new(AllQuery)),
+                        (Occur::MustNot, duplicates),
+                    ])))
+                }
+            }
         }
     }
-}
-
-/// 使用关键词进行 Tantivy 搜索
-fn search_by_keywords(ctx: &QueryContext, query_str: &str) -> Result<Vec<SearchHit>> {
-    let searcher = ctx.reader.searcher();
-    let schema = ctx.index.schema();
     
-    let title_field = schema.get_field(FIELD_TITLE)?;
-    let body_field = schema.get_field(FIELD_BODY)?;
-    let path_field = schema.get_field(FIELD_PATH)?;
+    /// 全文词项搜索的字段：限定字段时只搜该字段，否则搜 title、body 和 tags
+    fn text_fields(&self, scope: Option<TextField>) -> Vec<Field> {
+        match scope {
+            None => vec![self.fields.title, self.fields.body, self.fields.tags],
+            Some(TextField::Title) => vec![self.fields.title],
+            Some(TextField::Body) => vec![self.fields.body],
+            Some(TextField::Tags) => vec![self.fields.tags],
+        }
+    }
     
-    let query_parser = QueryParser::for_index(ctx.index, vec![title_field, body_field]);
+    /// 限定字段的关键词查询
+    fn field_text_query(&self, scope: TextField, text: &str) -> Box<dyn tantivy::query::Query> {
+        let parser = text_parser(&self.index, &self.fields, &self.boosts, self.text_fields(Some(scope)));
+        keyword_query(&parser, text)
+    }
     
-    let tantivy_query = match query_parser.parse_query(query_str) {
-        Ok(q) => q,
-        Err(e) => {
-            tracing::warn!("[Query执行器] 查询语法错误: '{}' - {}", query_str, e);
-            return Ok(vec![]);
+    /// 分词并返回 `(位置, 词)`，忽略空白词
+    fn tokenize(&self, text: &str) -> Vec<(usize, String)> {
+        let Some(tokenizer) = &self.tokenizer else {
+            return text.split_whitespace().map(String::from).enumerate().collect();
+        };
+        let mut tokenizer = tokenizer.clone();
+        let mut tokens = Vec::new();
+        tokenizer.token_stream(text).process(&mut |token| {
+            if !token.text.trim().is_empty() {
+                tokens.push((token.position, token.text.clone()));
+            }
+        });
+        tokens
+    }
+    
+    /// 短语查询：词按分词位置排列，相邻词之间最多间隔 `slop` 个位置
+    fn phrase_query(&self, scope: Option<TextField>, text: &str, slop: u32) -> Box<dyn tantivy::query::Query> {
+        let tokens = self.tokenize(text);
+        let field_query = |field: Field| -> Box<dyn tantivy::query::Query> {
+            let terms: Vec<(usize, tantivy::Term)> = tokens.iter()
+                .map(|(position, token)| (*position, tantivy::Term::from_field_text(field, token)))
+                .collect();
+            let query: Box<dyn tantivy::query::Query> = match terms.len() {
+                0 => return Box::new(EmptyQuery),
+                // PhraseQuery 至少需要两个词
+                1 => Box::new(TermQuery::new(terms[0].1.clone(), IndexRecordOption::Basic)),
+                _ => {
+                    let mut query = PhraseQuery::new_with_offset(terms);
+                    query.set_slop(slop);
+                    Box::new(query)
+                }
+            };
+            Box::new(BoostQuery::new(query, field_boost(&self.boosts, &self.fields, field)))
+        };
+        Box::new(BooleanQuery::union(self.text_fields(scope).into_iter().map(field_query).collect()))
+    }
+    
+    /// 模糊查询：每个分词都要在 `distance` 次编辑内命中 title 或 body（或限定字段）
+    fn fuzzy_query(&self, scope: Option<TextField>, word: &str, distance: u8) -> Box<dyn tantivy::query::Query> {
+        let tokens = self.tokenize(word);
+        if tokens.is_empty() {
+            return Box::new(EmptyQuery);
         }
+        let clauses = tokens.iter()
+            .map(|(_, token)| {
+                let fields: Vec<Box<dyn tantivy::query::Query>> = self.text_fields(scope)
+                    .into_iter()
+                    .map(|field| -> Box<dyn tantivy::query::Query> {
+                        let term = tantivy::Term::from_field_text(field, token);
+                        let query = Box::new(FuzzyTermQuery::new(term, distance, true));
+                        Box::new(BoostQuery::new(query, field_boost(&self.boosts, &self.fields, field)))
+                    })
+                    .collect();
+                (Occur::Must, Box::new(BooleanQuery::union(fields)) as Box<dyn tantivy::query::Query>)
+            })
+            .collect();
+        Box::new(BooleanQuery::new(clauses))
+    }
+}
+
+/// 在 `targets` 上检索的 `QueryParser`，各字段按 `boosts` 加权
+fn text_parser(index: &Index, fields: &SchemaFields, boosts: &FieldBoostConfig, targets: Vec<Field>) -> QueryParser {
+    let mut parser = QueryParser::for_index(index, targets.clone());
+    for field in targets {
+        parser.set_field_boost(field, field_boost(boosts, fields, field));
+    }
+    parser
+}
+
+/// 全文字段的默认权重
+fn field_boost(boosts: &FieldBoostConfig, fields: &SchemaFields, field: Field) -> f32 {
+    if field == fields.title {
+        boosts.title
+    } else if field == fields.tags {
+        boosts.tags
+    } else {
+        boosts.body
+    }
+}
+
+/// 关键词查询，语法错误时尽量保留可解析的部分
+fn keyword_query(parser: &QueryParser, keyword: &str) -> Box<dyn tantivy::query::Query> {
+    let (query, errors) = parser.parse_query_lenient(keyword);
+    if !errors.is_empty() {
+        tracing::warn!("[Query执行器] 关键词 '{}' 存在语法错误: {:?}", keyword, errors);
+    }
+    query
+}
+
+/// `root:` 目录查询：匹配目录本身及其下所有文件
+/// 
+/// `parent_path` 索引了每个文件的所有祖先目录，因此整棵子树只需一个词项。
+fn root_query(fields: &SchemaFields, root: &str) -> Box<dyn tantivy::query::Query> {
+    let trimmed = root.trim_end_matches('/');
+    let root = if trimmed.is_empty() { "/" } else { trimmed };
+    Box::new(BooleanQuery::union(vec![
+        text_term_query(fields.parent_path, root),
+        text_term_query(fields.path, root),
+    ]))
+}
+
+/// `glob:`/`name:` 查询：在 filename 和 path 的词典上匹配，`!` 前缀表示取反
+fn glob_query(fields: &SchemaFields, pattern: &str) -> Option<Box<dyn tantivy::query::Query>> {
+    let (negated, pattern) = split_glob_negation(pattern);
+    let regex = glob_to_term_regex(pattern)?;
+    let mut subqueries: Vec<Box<dyn tantivy::query::Query>> = Vec::new();
+    for field in [fields.filename, fields.path] {
+        match RegexQuery::from_pattern(&regex, field) {
+            Ok(q) => subqueries.push(Box::new(q)),
+            Err(e) => {
+                tracing::warn!("[Query执行器] 无法编译 glob '{}': {}", pattern, e);
+                return None;
+            }
+        }
+    }
+    let query: Box<dyn tantivy::query::Query> = Box::new(BooleanQuery::union(subqueries));
+    if negated {
+        Some(Box::new(BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery)),
+            (Occur::MustNot, query),
+        ])))
+    } else {
+        Some(query)
+    }
+}
+
+/// 拆分 glob 的 `!` 取反前缀
+fn split_glob_negation(pattern: &str) -> (bool, &str) {
+    match pattern.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    }
+}
+
+/// 将 glob 模式转换为词典正则（匹配整个词项）
+/// 
+/// 与 `glob::Pattern` 的默认选项一致：`*` 可以跨越 `/`，`?` 匹配任意单个字符，
+/// `[!...]` 为取反字符类。字符类未闭合时返回 `None`。
+fn glob_to_term_regex(pattern: &str) -> Option<String> {
+    let mut regex = String::with_capacity(pattern.len() * 2);
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                while chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                regex.push_str(".*");
+            }
+            '?' => regex.push('.'),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                // 紧跟在 `[` 后的 `]` 是普通字符
+                let mut first = true;
+                loop {
+                    match chars.next()? {
+                        ']' if !first => break,
+                        c @ ('\\' | '[' | ']' | '^') => {
+                            regex.push('\\');
+                            regex.push(c);
+                        }
+                        c => regex.push(c),
+                    }
+                    first = false;
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&escape_term_regex(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    Some(regex)
+}
+
+/// 精确匹配单个词项
+fn text_term_query(field: Field, text: &str) -> Box<dyn tantivy::query::Query> {
+    Box::new(TermQuery::new(
+        tantivy::Term::from_field_text(field, text),
+        IndexRecordOption::Basic,
+    ))
+}
+
+/// 转义词典正则中的元字符
+fn escape_term_regex(literal: &str) -> String {
+    let mut escaped = String::with_capacity(literal.len());
+    for c in literal.chars() {
+        if matches!(c, '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// u64 FAST 字段上的闭区间范围查询
+fn u64_range_query(field: Field, min: Option<u64>, max: Option<u64>) -> Box<dyn tantivy::query::Query> {
+    if min.is_none() && max.is_none() {
+        return Box::new(AllQuery);
+    }
+    let bound = |value: Option<u64>| match value {
+        Some(v) => Bound::Included(tantivy::Term::from_field_u64(field, v)),
+        None => Bound::Unbounded,
     };
+    Box::new(RangeQuery::new(bound(min), bound(max)))
+}
+
+/// i64 FAST 字段（时间）上的闭区间范围查询
+fn i64_range_query(field: Field, min: Option<i64>, max: Option<i64>) -> Box<dyn tantivy::query::Query> {
+    if min.is_none() && max.is_none() {
+        return Box::new(AllQuery);
+    }
+    let bound = |value: Option<i64>| match value {
+        Some(v) => Bound::Included(tantivy::Term::from_field_i64(field, v)),
+        None => Bound::Unbounded,
+    };
+    Box::new(RangeQuery::new(bound(min), bound(max)))
+}
+
+/// 逐条校验时判断文档命中了哪些全文词项（关键词、短语、模糊词）
+///
+/// 每个词项预先创建一个 `Weight`，校验时在文档所在 segment 上 seek。
+/// 词项以 `text_term_key` 标识。
+struct KeywordMatcher {
+    weights: Vec<(String, Box<dyn Weight>)>,
+}
+
+impl KeywordMatcher {
+    fn new(compiler: &QueryCompiler, searcher: &Searcher, query: &Query) -> Result<Self> {
+        let mut terms = Vec::new();
+        collect_text_terms(query, &mut terms, true);
+        
+        let mut weights: Vec<(String, Box<dyn Weight>)> = Vec::with_capacity(terms.len());
+        for term in terms {
+            let Some(key) = text_term_key(term) else {
+                continue;
+            };
+            if weights.iter().any(|(existing, _)| *existing == key) {
+                continue;
+            }
+            let Some(query) = compiler.compile_term(term).query else {
+                continue;
+            };
+            let weight = query.weight(EnableScoring::disabled_from_searcher(searcher))?;
+            weights.push((key, weight));
+        }
+        Ok(Self { weights })
+    }
     
-    let top_docs = searcher.search(&tantivy_query, &TopDocs::with_limit(ctx.limit * 10))?;
+    fn matched(&self, searcher: &Searcher, address: DocAddress) -> Result<Vec<String>> {
+        let segment_reader = searcher.segment_reader(address.segment_ord);
+        let mut matched = Vec::new();
+        for (keyword, weight) in &self.weights {
+            let mut scorer = weight.scorer(segment_reader, 1.0)?;
+            if scorer.doc() <= address.doc_id && scorer.seek(address.doc_id) == address.doc_id {
+                matched.push(keyword.clone());
+            }
+        }
+        Ok(matched)
+    }
+}
+
+/// 从 Query AST 中收集所有全文词项的标识
+fn collect_keywords(query: &Query) -> Vec<String> {
+    let mut terms = Vec::new();
+    collect_text_terms(query, &mut terms, false);
+    terms.into_iter().filter_map(text_term_key).collect()
+}
+
+/// 全文词项的标识：关键词为其本身，短语为 `"text"~slop`，模糊词为 `word~distance`，
+/// 限定字段时加上 `field:` 前缀
+fn text_term_key(term: &Term) -> Option<String> {
+    let scoped = |field: &Option<TextField>, key: String| match field {
+        Some(field) => format!("{}:{}", field.as_str(), key),
+        None => key,
+    };
+    match term {
+        Term::KeyWord(kw) => Some(kw.clone()),
+        Term::FieldText { field, text } => Some(format!("{}:{}", field.as_str(), text)),
+        Term::Phrase { field, text, slop } => Some(scoped(field, format!("\"{}\"~{}", text, slop))),
+        Term::Fuzzy { field, word, distance } => Some(scoped(field, format!("{}~{}", word, distance))),
+        // 权重不影响是否命中
+        Term::Boosted { term, .. } => text_term_key(term),
+        _ => None,
+    }
+}
+
+fn collect_text_terms<'q>(query: &'q Query, terms: &mut Vec<&'q Term>, include_not: bool) {
+    match query {
+        Query::Term(term) => {
+            if term.is_text() {
+                terms.push(term);
+            }
+        }
+        Query::And(items) | Query::Or(items) => {
+            for item in items {
+                collect_text_terms(item, terms, include_not);
+            }
+        }
+        Query::Not(inner) => {
+            // NOT 中的词项只用于排除
+            if include_not {
+                collect_text_terms(inner, terms, include_not);
+            }
+        }
+    }
+}
+
+/// 从 Query AST 中收集正则，`include_not` 为 false 时跳过 NOT 分支
+fn collect_regexes(query: &Query, include_not: bool) -> Vec<&Regex> {
+    let mut regexes = Vec::new();
+    collect_regexes_recursive(query, &mut regexes, include_not);
+    regexes
+}
+
+fn collect_regexes_recursive<'q>(query: &'q Query, regexes: &mut Vec<&'q Regex>, include_not: bool) {
+    match query {
+        Query::Term(Term::Regex(re)) => regexes.push(re),
+        Query::Term(_) => {}
+        Query::And(items) | Query::Or(items) => {
+            for item in items {
+                collect_regexes_recursive(item, regexes, include_not);
+            }
+        }
+        Query::Not(inner) => {
+            // NOT 中的正则只用于排除，不参与高亮
+            if include_not {
+                collect_regexes_recursive(inner, regexes, include_not);
+            }
+        }
+    }
+}
+
+/// 将词级别的正则转换为词典上的 `RegexQuery`
+///
+/// Tantivy 的正则匹配整个词项，因此用 `.*(?:pattern).*` 模拟子串匹配。
+/// 模式可能跨越分词边界时返回 `None`，由调用方回退到全文扫描。
+fn regex_term_query(re: &Regex, fields: &[Field]) -> Option<Box<dyn tantivy::query::Query>> {
+    let pattern = re.as_str();
+    if !is_token_level_pattern(pattern) {
+        return None;
+    }
     
-    let mut results = Vec::new();
-    for (score, doc_address) in top_docs {
-        let doc: TantivyDocument = searcher.doc(doc_address)?;
-        
-        let title = doc.get_first(title_field)
-            .and_then(|v| v.as_str())
-            .unwrap_or("无标题")
-            .to_string();
-        
-        let path = doc.get_first(path_field)
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        
-        // 获取文件元数据（如果有）
-        let file_size = schema.get_field(FIELD_FILE_SIZE).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let modified_time = schema.get_field(FIELD_MODIFIED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let created_time = schema.get_field(FIELD_CREATED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let accessed_time = schema.get_field(FIELD_ACCESSED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        results.push(SearchHit {
-            title,
-            path,
-            score,
-            tags: None,
-            file_size,
-            modified_time,
-            created_time,
-            accessed_time,
-        });
+    let term_pattern = format!(".*(?:{}).*", pattern);
+    let mut subqueries: Vec<Box<dyn tantivy::query::Query>> = Vec::new();
+    for &field in fields {
+        match RegexQuery::from_pattern(&term_pattern, field) {
+            Ok(q) => subqueries.push(Box::new(q)),
+            Err(e) => {
+                tracing::debug!("[Query执行器] 词典正则不支持 '{}': {}", pattern, e);
+                return None;
+            }
+        }
+    }
+    Some(Box::new(BooleanQuery::union(subqueries)))
+}
+
+/// 判断正则是否只会匹配单个词项内部的文本
+///
+/// 凡是可能匹配空白或标点的构造（`.`、取反字符类、`\s`/`\W`/`\D`、
+/// 转义标点、锚点、内联标志）都视为可能跨词。
+fn is_token_level_pattern(pattern: &str) -> bool {
+    if pattern.is_empty() {
+        return false;
     }
     
-    Ok(results)
+    let mut chars = pattern.chars().peekable();
+    // 字符类 `[...]` 和重复次数 `{m,n}` 内部允许 `-` 和 `,`
+    let mut in_class = false;
+    let mut in_repetition = false;
+    while let Some(c) = chars.next() {
+        match c {
+            // 转义的标点在分词后不会出现在词项中，只接受 `\d` 和 `\w`
+            '\\' => {
+                if !matches!(chars.next(), Some('d' | 'w')) {
+                    return false;
+                }
+            }
+            '[' if !in_class => {
+                if chars.peek() == Some(&'^') {
+                    return false;
+                }
+                in_class = true;
+            }
+            ']' if in_class => in_class = false,
+            '{' if !in_class => in_repetition = true,
+            '}' if in_repetition => in_repetition = false,
+            '-' if in_class => {}
+            ',' if in_repetition => {}
+            '(' if !in_class => {
+                if chars.peek() == Some(&'?') {
+                    return false;
+                }
+            }
+            ')' | '|' | '*' | '+' | '?' if !in_class => {}
+            c if c.is_alphanumeric() || c == '_' => {}
+            _ => return false,
+        }
+    }
+    true
 }
 
-/// 获取所有文档（用于只有过滤条件没有关键词的情况）
-fn get_all_docs(ctx: &QueryContext, schema: &tantivy::schema::Schema) -> Result<Vec<SearchHit>> {
-    let searcher = ctx.reader.searcher();
+/// 查找所有正则在候选文本中的匹配（字节偏移）
+fn find_regex_matches(text: &str, regexes: &[&Regex]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = regexes
+        .iter()
+        .flat_map(|re| re.find_iter(text).map(|m| m.range()))
+        .collect();
+    ranges.sort_by_key(|r| (r.start, r.end));
+    ranges.dedup();
+    ranges
+}
+
+/// 读取文档字段，构造候选结果
+fn load_candidate(
+    searcher: &Searcher,
+    fields: &SchemaFields,
+    score: f32,
+    address: DocAddress,
+    with_body: bool,
+) -> Result<Candidate> {
+    let doc: TantivyDocument = searcher.doc(address)?;
+    let get_u64 = |field: Field| doc.get_first(field).and_then(|v| v.as_u64());
+    let get_i64 = |field: Field| doc.get_first(field).and_then(|v| v.as_i64());
     
-    let title_field = schema.get_field(FIELD_TITLE)?;
-    let path_field = schema.get_field(FIELD_PATH)?;
+    let title = doc.get_first(fields.title)
+        .and_then(|v| v.as_str())
+        .unwrap_or("无标题")
+        .to_string();
     
-    let all_query = AllQuery;
-    let fetch_limit = ctx.limit * 10;
-    tracing::info!("[Query执行器] get_all_docs: 获取所有文档，limit={}", fetch_limit);
-    let top_docs = searcher.search(&all_query, &TopDocs::with_limit(fetch_limit))?;
-    tracing::info!("[Query执行器] get_all_docs: 获取到 {} 个候选文档", top_docs.len());
+    let path = doc.get_first(fields.path)
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
     
-    let mut results = Vec::new();
-    for (_score, doc_address) in top_docs {
-        let doc: TantivyDocument = searcher.doc(doc_address)?;
-        
-        let title = doc.get_first(title_field)
-            .and_then(|v| v.as_str())
-            .unwrap_or("无标题")
-            .to_string();
-        
-        let path = doc.get_first(path_field)
+    let body = if with_body {
+        doc.get_first(fields.body)
             .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-        
-        let file_size = schema.get_field(FIELD_FILE_SIZE).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let modified_time = schema.get_field(FIELD_MODIFIED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let created_time = schema.get_field(FIELD_CREATED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let accessed_time = schema.get_field(FIELD_ACCESSED_TIME).ok()
-            .and_then(|f| doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        results.push(SearchHit {
+            .map(String::from)
+    } else {
+        None
+    };
+    
+    Ok(Candidate {
+        hit: SearchHit {
             title,
             path,
-            score: 1.0,
+            score,
             tags: None,
-            file_size,
-            modified_time,
-            created_time,
-            accessed_time,
-        });
-    }
-    
-    Ok(results)
-}
-
-/// 根据 Query AST 过滤候选结果
-fn filter_by_query(candidates: Vec<SearchHit>, query: &Query) -> Result<Vec<SearchHit>> {
-    let candidate_count = candidates.len();
-    let filtered: Vec<SearchHit> = candidates
-        .into_iter()
-        .filter(|hit| matches_query(hit, query))
-        .collect();
-    tracing::info!(
-        "[Query执行器] filter_by_query: 候选 {} 个, 过滤后 {} 个",
-        candidate_count, filtered.len()
-    );
-    Ok(filtered)
+            file_size: get_u64(fields.file_size),
+            modified_time: get_i64(fields.modified_time),
+            created_time: get_i64(fields.created_time),
+            accessed_time: get_i64(fields.accessed_time),
+            match_ranges: Vec::new(),
+            passage: None,
+            snippet: None,
+            duplicates: Vec::new(),
+        },
+        body,
+        matched_keywords: Vec::new(),
+        duplicate: false,
+    })
 }
 
-/// 检查单个搜索结果是否匹配 Query
-fn matches_query(hit: &SearchHit, query: &Query) -> bool {
+/// 检查单个候选文档是否匹配 Query，`type:` 按 `file_types` 和内置分组展开
+fn matches_query(candidate: &Candidate, query: &Query, file_types: &HashMap<String, Vec<String>>) -> bool {
     match query {
-        Query::Term(term) => matches_term(hit, term),
-        Query::And(items) => items.iter().all(|q| matches_query(hit, q)),
-        Query::Or(items) => items.iter().any(|q| matches_query(hit, q)),
-        Query::Not(inner) => !matches_query(hit, inner),
+        Query::Term(term) => matches_term(candidate, term, file_types),
+        Query::And(items) => items.iter().all(|q| matches_query(candidate, q, file_types)),
+        Query::Or(items) => items.iter().any(|q| matches_query(candidate, q, file_types)),
+        Query::Not(inner) => !matches_query(candidate, inner, file_types),
     }
 }
 
-/// 检查单个搜索结果是否匹配 Term
-fn matches_term(hit: &SearchHit, term: &Term) -> bool {
+/// 检查单个候选文档是否匹配 Term
+fn matches_term(candidate: &Candidate, term: &Term, file_types: &HashMap<String, Vec<String>>) -> bool {
+    let hit = &candidate.hit;
     match term {
-        Term::KeyWord(_) => {
-            // 关键词已在 Tantivy 搜索中匹配，这里直接返回 true
-            true
+        Term::KeyWord(_) | Term::FieldText { .. } | Term::Phrase { .. } | Term::Fuzzy { .. } | Term::Boosted { .. } => {
+            // 全文词项命中情况由 KeywordMatcher 在索引上预先计算
+            text_term_key(term).is_some_and(|key| candidate.matched_keywords.contains(&key))
         }
         Term::Root(root_path) => {
             // 检查文件是否在指定根目录下
@@ -255,14 +995,18 @@ fn matches_term(hit: &SearchHit, term: &Term) -> bool {
             let root = Path::new(root_path);
             path.starts_with(root)
         }
-        Term::Regex(_re) => {
-            // Regex 模式已作为关键词传给 Tantivy 进行全文搜索
-            // Tantivy 会在 title 和 body 中搜索匹配的内容
-            // 这里直接返回 true，因为候选结果已经是 Tantivy 匹配的
-            true
+        Term::Regex(re) => {
+            // 候选集只是正则的超集，这里在正文上做最终校验
+            let result = re.is_match(&candidate.text());
+            tracing::debug!(
+                "[Regex过滤] pattern='{}', path='{}', 匹配: {}",
+                re.as_str(), hit.path, result
+            );
+            result
         }
         Term::Glob(pattern) => {
             // Glob 模式匹配文件名
+            let (negated, pattern) = split_glob_negation(pattern);
             match glob::Pattern::new(pattern) {
                 Ok(p) => {
                     let file_name = Path::new(&hit.path)
@@ -271,7 +1015,7 @@ fn matches_term(hit: &SearchHit, term: &Term) -> bool {
                         .unwrap_or("");
                     let matches_name = p.matches(file_name);
                     let matches_path = p.matches(&hit.path);
-                    let result = matches_name || matches_path;
+                    let result = (matches_name || matches_path) != negated;
                     tracing::debug!(
                         "[Glob过滤] pattern='{}', file_name='{}', path='{}', matches_name={}, matches_path={}, result={}",
                         pattern, file_name, hit.path, matches_name, matches_path, result
@@ -284,150 +1028,193 @@ fn matches_term(hit: &SearchHit, term: &Term) -> bool {
                 }
             }
         }
+        Term::Extension(ext) => {
+            // 扩展名不区分大小写
+            Path::new(&hit.path)
+                .extension()
+                .is_some_and(|e| e.to_string_lossy().to_lowercase() == *ext)
+        }
+        Term::FileType(name) => {
+            let extensions = file_type_extensions(file_types, name);
+            Path::new(&hit.path)
+                .extension()
+                .is_some_and(|e| extensions.contains(&e.to_string_lossy().to_lowercase()))
+        }
+        // 与编译出的 `RangeQuery` 一致：索引中没有该字段的文件不匹配
         Term::AccessTime(range) => {
-            // 访问时间过滤 - 优先使用索引中的数据
-            let atime_secs = if let Some(atime) = hit.accessed_time {
-                atime
-            } else {
-                // 如果没有元数据，尝试从文件系统获取
-                if let Ok(metadata) = std::fs::metadata(&hit.path) {
-                    if let Ok(accessed) = metadata.accessed() {
-                        accessed
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .map(|d| d.as_secs())
-                            .unwrap_or(0)
-                    } else {
-                        return true; // 无法获取时间，不过滤
-                    }
-                } else {
-                    return true;
-                }
-            };
-            
-            let result = range.contains(atime_secs);
+            let result = hit.accessed_time.is_some_and(|atime| range.contains(atime));
             tracing::debug!(
-                "[AccessTime过滤] 文件: {}, atime: {}, range: {:?}, 匹配: {}",
-                hit.path, atime_secs, range, result
+                "[AccessTime过滤] 文件: {}, atime: {:?}, range: {:?}, 匹配: {}",
+                hit.path, hit.accessed_time, range, result
             );
             result
         }
         Term::ModifiedTime(range) => {
-            // 修改时间过滤
-            let mtime_secs = if let Some(mtime) = hit.modified_time {
-                mtime
-            } else {
-                // 如果没有元数据，尝试从文件系统获取
-                if let Ok(metadata) = std::fs::metadata(&hit.path) {
-                    if let Ok(modified) = metadata.modified() {
-                        modified
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .map(|d| d.as_secs())
-                            .unwrap_or(0)
-                    } else {
-                        return true; // 无法获取时间，不过滤
-                    }
-                } else {
-                    return true;
-                }
-            };
-            
-            let result = range.contains(mtime_secs);
+            let result = hit.modified_time.is_some_and(|mtime| range.contains(mtime));
             tracing::debug!(
-                "[ModifiedTime过滤] 文件: {}, mtime: {}, range: {:?}, 匹配: {}",
-                hit.path, mtime_secs, range, result
+                "[ModifiedTime过滤] 文件: {}, mtime: {:?}, range: {:?}, 匹配: {}",
+                hit.path, hit.modified_time, range, result
             );
             result
         }
         Term::CreatedTime(range) => {
-            // 创建时间过滤 - 优先使用索引中的数据
-            let ctime_secs = if let Some(ctime) = hit.created_time {
-                ctime
-            } else {
-                // 如果没有元数据，尝试从文件系统获取
-                if let Ok(metadata) = std::fs::metadata(&hit.path) {
-                    if let Ok(created) = metadata.created() {
-                        created
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .map(|d| d.as_secs())
-                            .unwrap_or(0)
-                    } else {
-                        return true; // 无法获取时间，不过滤
-                    }
-                } else {
-                    return true;
-                }
-            };
-            
-            let result = range.contains(ctime_secs);
+            let result = hit.created_time.is_some_and(|ctime| range.contains(ctime));
             tracing::debug!(
-                "[CreatedTime过滤] 文件: {}, ctime: {}, range: {:?}, 匹配: {}",
-                hit.path, ctime_secs, range, result
+                "[CreatedTime过滤] 文件: {}, ctime: {:?}, range: {:?}, 匹配: {}",
+                hit.path, hit.created_time, range, result
             );
             result
         }
-        Term::Size(range) => {
-            // 文件大小过滤
-            if let Some(size) = hit.file_size {
-                range.contains(size)
-            } else {
-                // 从文件系统获取
-                if let Ok(metadata) = std::fs::metadata(&hit.path) {
-                    range.contains(metadata.len())
-                } else {
-                    true
-                }
-            }
-        }
+        Term::Duplicates(wanted) => candidate.duplicate == *wanted,
+        Term::Size(range) => hit.file_size.is_some_and(|size| range.contains(size)),
     }
 }
 
 /// 解析并执行查询字符串
 /// 
-/// 这是主要的入口函数，将原始查询字符串解析为 Query AST，然后执行搜索
+/// 这是主要的入口函数，将原始查询字符串解析为 Query AST，然后执行搜索。
+/// 超时、候选文档上限和是否折叠重复文件按 `config.query`；
+/// 提供 `catalog` 时据此计算重复文件分组（`dupes:` 条件和折叠结果）。
 pub fn parse_and_execute(
     reader: &IndexReader,
     index: &Index,
+    config: &SearchConfig,
+    catalog: Option<&FileCatalog>,
     query_str: &str,
     limit: usize,
 ) -> Result<Vec<SearchHit>, QueryExecuteError> {
-    // 1. 解析查询字符串
-    let parsed = query::parse_query(query_str)
-        .map_err(|e| QueryExecuteError::ParseError(format!("{:?}", e)))?;
-    
-    // 2. 验证并转换为 Query AST
-    let query = query::validate_query(&parsed)
-        .map_err(QueryExecuteError::ValidationError)?;
-    
-    tracing::debug!("[Query执行器] 解析后的 Query: {:?}", query);
+    // 1. 解析并验证查询字符串
+    let query = parse_dsl(query_str)?;
     
-    // 3. 执行查询
-    let ctx = QueryContext { reader, index, limit };
-    let results = execute_query(&ctx, &query)
-        .map_err(|e| QueryExecuteError::ExecutionError(e.to_string()))?;
+    // 2. 执行查询
+    let mut ctx = QueryContext::new(reader, index, limit, &config.query).with_field_boosts(&config.field_boosts)
+        .with_file_types(&config.file_types);
+    if let Some(catalog) = catalog {
+        ctx = ctx.with_duplicates(catalog.duplicate_groups());
+    }
+    let results = execute_query(&ctx, &query)?;
     
-    // 4. 限制结果数量
+    // 3. 限制结果数量
     let results: Vec<_> = results.into_iter().take(limit).collect();
     
     Ok(results)
 }
 
+/// 解析 Query DSL 字符串，验证并优化为 Query AST
+///
+/// 有语法错误时返回第一个语法错误，否则一次返回所有验证错误（附带警告）。查询有效时警告
+/// （如匹配所有文件的正则、不存在的根目录）只记录到日志；优化时发现的
+/// 矛盾条件（如 `size:>1MB AND size:<1KB`）同样只记录警告，对应的子查询
+/// 被化简为不匹配任何文件。
+pub fn parse_dsl(query_str: &str) -> Result<Query, QueryExecuteError> {
+    let (query, diagnostics) = query::check_query(query_str);
+    let Some(query) = query else {
+        return Err(QueryExecuteError::from_diagnostics(diagnostics));
+    };
+    for warning in &diagnostics.warnings {
+        tracing::warn!("[Query执行器] 查询警告: {}", warning);
+    }
+    tracing::debug!("[Query执行器] 解析后的 Query: {:?}", query);
+    
+    let optimized = query::optimize(query);
+    for warning in &optimized.warnings {
+        tracing::warn!("[Query执行器] 查询条件矛盾: {}", warning);
+    }
+    tracing::debug!("[Query执行器] 优化后的 Query: {:?}", optimized.query);
+    Ok(optimized.query)
+}
+
 /// Query 执行错误
+/// 
+/// 各变体与 `rpc::search::SearchErrorKind` 一一对应。
 #[derive(Debug)]
 pub enum QueryExecuteError {
-    /// 解析错误
-    ParseError(String),
-    /// 验证错误
-    ValidationError(ValidationError),
+    /// 语法错误，`span`、`expected`、`found` 描述第一个语法错误，
+    /// `diagnostics` 包含所有语法错误、验证错误和警告
+    ParseError {
+        span: Range<usize>,
+        expected: Vec<String>,
+        found: Option<String>,
+        diagnostics: Diagnostics,
+    },
+    /// 语法正确但验证失败，包含所有验证错误
+    InvalidQuery(Diagnostics),
     /// 执行错误
-    ExecutionError(String),
+    ExecutionError { message: String },
+    /// 索引无法读取（文件缺失、损坏或版本不兼容）
+    IndexUnavailable,
+    /// 超过 `QueryContext::deadline` 仍未完成
+    Timeout,
+    /// 需要逐条校验的候选文档超过 `QueryContext::max_scanned`
+    TooManyResults,
+}
+
+impl QueryExecuteError {
+    /// 有语法错误时以第一个语法错误定位，所有错误和警告都保留
+    fn from_diagnostics(diagnostics: Diagnostics) -> Self {
+        let syntax_error = diagnostics.errors.iter().find_map(|error| match &error.kind {
+            ValidationErrorKind::UnexpectedToken { found, expected } => {
+                Some((error.range(), expected.clone(), found.clone()))
+            }
+            ValidationErrorKind::InvalidToken { token } => {
+                Some((error.range(), Vec::new(), Some(token.clone())))
+            }
+            _ => None,
+        });
+        match syntax_error {
+            Some((span, expected, found)) => QueryExecuteError::ParseError { span, expected, found, diagnostics },
+            None => QueryExecuteError::InvalidQuery(diagnostics),
+        }
+    }
+}
+
+impl From<TantivyError> for QueryExecuteError {
+    fn from(err: TantivyError) -> Self {
+        match err {
+            TantivyError::OpenDirectoryError(_)
+            | TantivyError::OpenReadError(_)
+            | TantivyError::IoError(_)
+            | TantivyError::DataCorruption(_)
+            | TantivyError::IncompatibleIndex(_) => {
+                tracing::error!("[Query执行器] 索引不可用: {}", err);
+                QueryExecuteError::IndexUnavailable
+            }
+            err => QueryExecuteError::ExecutionError { message: err.to_string() },
+        }
+    }
+}
+
+impl From<anyhow::Error> for QueryExecuteError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<TantivyError>() {
+            Ok(err) => QueryExecuteError::from(err),
+            Err(err) => QueryExecuteError::ExecutionError { message: err.to_string() },
+        }
+    }
 }
 
 impl std::fmt::Display for QueryExecuteError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            QueryExecuteError::ParseError(msg) => write!(f, "解析错误: {}", msg),
-            QueryExecuteError::ValidationError(e) => write!(f, "验证错误: {}", e),
-            QueryExecuteError::ExecutionError(msg) => write!(f, "执行错误: {}", msg),
+            QueryExecuteError::ParseError { span, expected, found, diagnostics } => {
+                write!(f, "语法错误 (位置 {}..{}): ", span.start, span.end)?;
+                match found {
+                    Some(found) => write!(f, "意外的 '{}'", found)?,
+                    None => write!(f, "查询意外结束")?,
+                }
+                if !expected.is_empty() {
+                    write!(f, "，期望 {}", expected.join(", "))?;
+                }
+                if diagnostics.errors.len() > 1 {
+                    write!(f, "（另有 {} 个错误）", diagnostics.errors.len() - 1)?;
+                }
+                Ok(())
+            }
+            QueryExecuteError::InvalidQuery(diagnostics) => write!(f, "查询无效: {}", diagnostics),
+            QueryExecuteError::ExecutionError { message } => write!(f, "执行错误: {}", message),
+            QueryExecuteError::IndexUnavailable => write!(f, "索引不可用"),
+            QueryExecuteError::Timeout => write!(f, "查询超时"),
+            QueryExecuteError::TooManyResults => write!(f, "需要校验的候选文档过多"),
         }
     }
 }
@@ -437,6 +1224,380 @@ impl std::error::Error for QueryExecuteError {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tantivy::IndexWriter;
+    use tantivy_jieba::JiebaTokenizer;
+    
+    use crate::config::PassageConfig;
+    use crate::models::FileDoc;
+    use crate::passage::split_passages;
+    use crate::schema::{build_schema, passage_document, IndexDocument};
+    
+    /// 构建内存索引，文档为 (路径, 正文, 文件大小)
+    fn build_test_index(docs: &[(&str, &str, u64)]) -> (Index, IndexReader) {
+        let documents: Vec<IndexDocument> = docs.iter()
+            .map(|(path, body, size)| test_document(path, body, *size))
+            .collect();
+        index_documents(&documents)
+    }
+    
+    fn test_document(path: &str, body: &str, size: u64) -> IndexDocument {
+        let path = Path::new(path);
+        IndexDocument {
+            title: path.file_stem().unwrap().to_string_lossy().to_string(),
+            content: body.to_string(),
+            path: path.to_string_lossy().to_string(),
+            tags: Vec::new(),
+            parent_path: path.parent().unwrap().to_string_lossy().to_string(),
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            file_type: path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default(),
+            file_size: size,
+            modified_time: Some(1_700_000_000),
+            created_time: Some(1_700_000_000),
+            accessed_time: Some(1_700_000_000),
+            indexed_time: 1_700_000_000,
+        }
+    }
+    
+    fn index_documents(documents: &[IndexDocument]) -> (Index, IndexReader) {
+        let index = Index::create_in_ram(build_schema());
+        index.tokenizers().register("jieba", JiebaTokenizer {});
+        let fields = SchemaFields::from_schema(&index.schema());
+        
+        let mut writer: IndexWriter = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for document in documents {
+            writer.add_document(document.to_tantivy(&fields)).unwrap();
+            // 与实际索引一致写入段落文档，规则查询不应返回它们
+            let file_doc = FileDoc::new(&document.title, &document.content, &document.path);
+            for passage in split_passages(&file_doc, &PassageConfig::default()) {
+                writer.add_document(passage_document(&document.path, &passage, &fields)).unwrap();
+            }
+        }
+        writer.commit().unwrap();
+        
+        let reader = index.reader().unwrap();
+        (index, reader)
+    }
+    
+    fn run(index: &Index, reader: &IndexReader, query_str: &str, limit: usize) -> Vec<String> {
+        let mut paths: Vec<String> = parse_and_execute(reader, index, &SearchConfig::default(), None, query_str, limit)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect();
+        paths.sort();
+        paths
+    }
+    
+    fn compile(query_str: &str) -> CompiledQuery {
+        let index = Index::create_in_ram(build_schema());
+        let parsed = query::parse_query(query_str).unwrap();
+        let query = query::validate_query(&parsed).unwrap();
+        compile_query(&index, &query, None, &FieldBoostConfig::default(), &HashMap::new())
+    }
+    
+    #[test]
+    fn test_compile_exactness() {
+        assert!(compile("foo AND size:>1MB").exact);
+        assert!(compile("foo OR NOT bar").exact);
+        assert!(compile("root:/home/dev AND mtime:<1w").exact);
+        assert!(compile("name:*.rs AND ext:rs AND size:<1KB").exact);
+        assert!(compile(r#""a b"~2 AND NOT qiuck~1"#).exact);
+        assert!(compile("title:foo^2 OR bar^0.5").exact);
+        assert!(!compile(r#"regex:"fn main" AND size:<1KB"#).exact);
+        assert!(!compile("NOT regex:ERROR").exact);
+        // 跨词正则不限制候选集
+        assert!(compile(r#"regex:"fn main""#).query.is_none());
+        assert!(compile(r#"foo OR regex:"fn main""#).query.is_none());
+    }
+    
+    #[test]
+    fn test_range_filter_scans_whole_index() {
+        // 大文件排在最后，旧实现的候选窗口（limit * 10）会漏掉它
+        let mut docs: Vec<(String, u64)> = (0..50)
+            .map(|i| (format!("/data/small{i}.txt"), 100))
+            .collect();
+        docs.push(("/data/big.bin".into(), 200 * 1024 * 1024));
+        let docs: Vec<(&str, &str, u64)> = docs.iter().map(|(p, s)| (p.as_str(), "", *s)).collect();
+        let (index, reader) = build_test_index(&docs);
+        
+        assert_eq!(run(&index, &reader, "size:>100MB", 1), vec!["/data/big.bin"]);
+    }
+    
+    #[test]
+    fn test_not_and_root_compiled() {
+        let (index, reader) = build_test_index(&[
+            ("/home/dev/a.rs", "tokio async runtime", 10),
+            ("/home/dev/b.rs", "tokio blocking", 10),
+            ("/home/devops/c.rs", "tokio async", 10),
+        ]);
+        
+        assert_eq!(run(&index, &reader, "tokio AND NOT async", 10), vec!["/home/dev/b.rs"]);
+        assert_eq!(
+            run(&index, &reader, "tokio root:/home/dev", 10),
+            vec!["/home/dev/a.rs", "/home/dev/b.rs"]
+        );
+    }
+    
+    #[test]
+    fn test_optimized_queries() {
+        let (index, reader) = build_test_index(&[
+            ("/home/dev/a.rs", "tokio async runtime", 10),
+            ("/home/dev/b.rs", "tokio blocking", 2000),
+        ]);
+
+        // 矛盾的条件不匹配任何文件，其余分支照常执行
+        assert!(run(&index, &reader, "tokio size:>1KB size:<100", 10).is_empty());
+        assert!(run(&index, &reader, "tokio AND NOT tokio", 10).is_empty());
+        assert_eq!(
+            run(&index, &reader, "(ext:rs AND ext:md) OR blocking", 10),
+            vec!["/home/dev/b.rs"]
+        );
+        assert_eq!(run(&index, &reader, "NOT NOT async", 10), vec!["/home/dev/a.rs"]);
+        assert_eq!(
+            run(&index, &reader, "regex:tok AND root:/home AND root:/home/dev size:<1KB", 10),
+            vec!["/home/dev/a.rs"]
+        );
+    }
+
+    #[test]
+    fn test_inexact_query_checks_until_limit() {
+        let mut docs: Vec<(String, &str)> = (0..30)
+            .map(|i| (format!("/src/note{i}.txt"), "tokio"))
+            .collect();
+        docs.push(("/src/main.rs".into(), "fn main tokio"));
+        let docs: Vec<(&str, &str, u64)> = docs.iter().map(|(p, b)| (p.as_str(), *b, 1)).collect();
+        let (index, reader) = build_test_index(&docs);
+        
+        assert_eq!(run(&index, &reader, r#"tokio AND regex:"fn main""#, 2), vec!["/src/main.rs"]);
+        assert_eq!(run(&index, &reader, r#"regex:"fn main" OR NOT tokio"#, 2), vec!["/src/main.rs"]);
+    }
+    
+    #[test]
+    fn test_execution_limits() {
+        let docs: Vec<String> = (0..10).map(|i| format!("/src/note{i}.txt")).collect();
+        let docs: Vec<(&str, &str, u64)> = docs.iter().map(|p| (p.as_str(), "tokio", 1)).collect();
+        let (index, reader) = build_test_index(&docs);
+        let query = parse_dsl(r#"tokio AND regex:"fn main""#).unwrap();
+        let ctx = |deadline, max_scanned| QueryContext {
+            reader: &reader,
+            index: &index,
+            limit: 5,
+            deadline,
+            max_scanned,
+            duplicates: None,
+            collapse_duplicates: false,
+            field_boosts: FieldBoostConfig::default(),
+            file_types: HashMap::new(),
+        };
+        
+        assert!(execute_query(&ctx(None, Some(10)), &query).unwrap().is_empty());
+        assert!(matches!(execute_query(&ctx(None, Some(3)), &query), Err(QueryExecuteError::TooManyResults)));
+        assert!(matches!(execute_query(&ctx(Some(Instant::now()), None), &query), Err(QueryExecuteError::Timeout)));
+    }
+    
+    #[test]
+    fn test_duplicates_filter_and_collapse() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = FileCatalog::open(&sled::open(dir.path()).unwrap()).unwrap();
+        let stat = crate::catalog::FileStat { inode: 0, file_size: 10, mtime_ns: 0 };
+        for (path, hash) in [("/d/a.txt", 1), ("/d/b.txt", 1), ("/d/c.txt", 2)] {
+            catalog.record(path, &stat, hash).unwrap();
+        }
+        let (index, reader) = build_test_index(&[
+            ("/d/a.txt", "tokio", 10),
+            ("/d/b.txt", "tokio", 10),
+            ("/d/c.txt", "tokio", 10),
+        ]);
+        let config = QueryConfig { collapse_duplicates: true, ..QueryConfig::default() };
+        let ctx = QueryContext::new(&reader, &index, 10, &config).with_duplicates(catalog.duplicate_groups());
+        let paths = |query_str: &str| {
+            let mut paths: Vec<String> = execute_query(&ctx, &parse_dsl(query_str).unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        
+        // 包含 dupes: 条件时不折叠
+        assert_eq!(paths("dupes:yes"), vec!["/d/a.txt", "/d/b.txt"]);
+        assert_eq!(paths("tokio AND dupes:no"), vec!["/d/c.txt"]);
+        assert_eq!(paths("regex:tok AND NOT dupes:yes"), vec!["/d/c.txt"]);
+        
+        // 每组只保留一个结果，其余路径列在 duplicates 中
+        let hits = execute_query(&ctx, &parse_dsl("tokio").unwrap()).unwrap();
+        assert_eq!(hits.len(), 2);
+        let kept = hits.iter().find(|hit| hit.path != "/d/c.txt").unwrap();
+        assert_eq!(kept.duplicates.len(), 1);
+        assert_ne!(kept.duplicates[0], kept.path);
+        
+        // 是否折叠由传入的配置决定
+        let config = QueryConfig { collapse_duplicates: false, ..QueryConfig::default() };
+        let ctx = QueryContext::new(&reader, &index, 10, &config).with_duplicates(catalog.duplicate_groups());
+        assert_eq!(execute_query(&ctx, &parse_dsl("tokio").unwrap()).unwrap().len(), 3);
+    }
+    
+    #[test]
+    fn test_parse_errors_are_structured() {
+        // 以第一个语法错误定位，验证错误和其余语法错误都保留在 diagnostics 中
+        match parse_dsl("size:abc AND )") {
+            Err(QueryExecuteError::ParseError { span, expected, found, .. }) => {
+                assert_eq!(span, 13..14);
+                assert_eq!(found.as_deref(), Some(")"));
+                assert!(expected.contains(&"term".to_string()));
+            }
+            other => panic!("期望语法错误，实际为 {:?}", other),
+        }
+        match parse_dsl("a & b AND size:abc AND )") {
+            Err(QueryExecuteError::ParseError { span, found, diagnostics, .. }) => {
+                assert_eq!(span, 2..3);
+                assert_eq!(found.as_deref(), Some("&"));
+                let spans: Vec<_> = diagnostics.errors.iter().map(|error| error.range()).collect();
+                assert!(spans.contains(&(2..3)) && spans.contains(&(15..18)) && spans.contains(&(23..24)), "{:?}", spans);
+            }
+            other => panic!("期望语法错误，实际为 {:?}", other),
+        }
+        assert!(matches!(parse_dsl("size:abc"), Err(QueryExecuteError::InvalidQuery(d)) if d.errors.len() == 1));
+    }
+    
+    #[test]
+    fn test_file_fields_use_indexed_terms() {
+        let (index, reader) = build_test_index(&[
+            ("/src/a.rs", "", 1),
+            ("/src/b.MD", "", 1),
+            ("/src/sub/c.rs", "", 1),
+        ]);
+        
+        assert_eq!(run(&index, &reader, "name:*.rs", 10), vec!["/src/a.rs", "/src/sub/c.rs"]);
+        assert_eq!(run(&index, &reader, "glob:!*.rs", 10), vec!["/src/b.MD"]);
+        assert_eq!(run(&index, &reader, "ext:md", 10), vec!["/src/b.MD"]);
+        assert_eq!(run(&index, &reader, "root:/src/sub/", 10), vec!["/src/sub/c.rs"]);
+        assert_eq!(run(&index, &reader, "root:/", 10).len(), 3);
+    }
+    
+    #[test]
+    fn test_file_type_groups() {
+        let (index, reader) = build_test_index(&[
+            ("/src/a.rs", "tokio", 1),
+            ("/docs/b.pdf", "tokio", 1),
+            ("/docs/c.DOCX", "", 1),
+            ("/docs/d.txt", "", 1),
+        ]);
+        
+        assert_eq!(run(&index, &reader, "type:document", 10), vec!["/docs/b.pdf", "/docs/c.DOCX"]);
+        assert_eq!(run(&index, &reader, "type:code", 10), vec!["/src/a.rs"]);
+        // 不是分组名时按单个扩展名匹配
+        assert_eq!(run(&index, &reader, "type:txt", 10), vec!["/docs/d.txt"]);
+        assert_eq!(run(&index, &reader, "tokio AND NOT type:code", 10), vec!["/docs/b.pdf"]);
+        assert_eq!(
+            run(&index, &reader, r#"type:document AND regex:"tok""#, 10),
+            vec!["/docs/b.pdf"]
+        );
+        
+        // 配置的分组覆盖同名内置分组，编译和逐条校验使用同一份配置
+        let config = SearchConfig {
+            file_types: HashMap::from([("code".to_string(), vec!["txt".to_string()])]),
+            ..SearchConfig::default()
+        };
+        let run_configured = |query_str: &str| {
+            let mut paths: Vec<String> = parse_and_execute(&reader, &index, &config, None, query_str, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(run_configured("type:code"), vec!["/docs/d.txt"]);
+        assert_eq!(run_configured(r#"regex:"tok" AND NOT type:code"#), vec!["/docs/b.pdf", "/src/a.rs"]);
+    }
+    
+    #[test]
+    fn test_time_filters_before_epoch() {
+        let mut old = test_document("/old/apollo.txt", "moon", 1);
+        old.modified_time = Some(-170_000_000); // 1964 年 8 月
+        let mut unknown = test_document("/old/unknown.txt", "moon", 1);
+        unknown.modified_time = None;
+        let (index, reader) = index_documents(&[old, unknown, test_document("/new/a.txt", "moon", 1)]);
+        
+        // 没有修改时间的文件不匹配任何时间条件
+        assert_eq!(run(&index, &reader, "mtime:<1970", 10), vec!["/old/apollo.txt"]);
+        assert_eq!(run(&index, &reader, "mtime:1964", 10), vec!["/old/apollo.txt"]);
+        assert_eq!(run(&index, &reader, "mtime:>=1970", 10), vec!["/new/a.txt"]);
+        
+        // 含正则时逐条校验，结果与直接执行范围查询一致
+        assert_eq!(run(&index, &reader, "mtime:<1970 AND regex:moo", 10), vec!["/old/apollo.txt"]);
+        assert_eq!(run(&index, &reader, "mtime:>=1970 AND regex:moo", 10), vec!["/new/a.txt"]);
+        assert_eq!(
+            run(&index, &reader, "NOT mtime:>=1970", 10),
+            run(&index, &reader, "NOT mtime:>=1970 AND regex:moo", 10)
+        );
+    }
+    
+    #[test]
+    fn test_phrase_and_fuzzy_terms() {
+        let (index, reader) = build_test_index(&[
+            ("/a.txt", "quick brown fox", 1),
+            ("/b.txt", "quick fox", 1),
+            ("/c.txt", "fox quick brown", 1),
+        ]);
+        
+        assert_eq!(run(&index, &reader, r#""quick fox""#, 10), vec!["/b.txt"]);
+        assert_eq!(run(&index, &reader, r#""quick fox"~1"#, 10), vec!["/b.txt"]);
+        assert_eq!(run(&index, &reader, r#""quick fox"~6"#, 10), vec!["/a.txt", "/b.txt"]);
+        assert_eq!(run(&index, &reader, r#"fox NOT "quick fox""#, 10), vec!["/a.txt", "/c.txt"]);
+        assert_eq!(run(&index, &reader, "brwn~1", 10), vec!["/a.txt", "/c.txt"]);
+        assert!(run(&index, &reader, "brwn", 10).is_empty());
+        // 含正则时逐条校验，短语按标识判断是否命中
+        assert_eq!(run(&index, &reader, r#""quick fox" AND regex:"k f""#, 10), vec!["/b.txt"]);
+    }
+    
+    #[test]
+    fn test_field_scoped_terms() {
+        let (index, reader) = build_test_index(&[
+            ("/docs/report.txt", "budget", 1),
+            ("/docs/notes.txt", "report budget", 1),
+        ]);
+        
+        assert_eq!(run(&index, &reader, "report", 10), vec!["/docs/notes.txt", "/docs/report.txt"]);
+        assert_eq!(run(&index, &reader, "key:report", 10), vec!["/docs/notes.txt", "/docs/report.txt"]);
+        assert_eq!(run(&index, &reader, "title:report", 10), vec!["/docs/report.txt"]);
+        assert_eq!(run(&index, &reader, "body:report", 10), vec!["/docs/notes.txt"]);
+        assert_eq!(run(&index, &reader, "content:reprot~1", 10), vec!["/docs/notes.txt"]);
+        assert!(run(&index, &reader, "tag:report", 10).is_empty());
+        // 逐条校验时按带字段前缀的标识判断是否命中
+        assert_eq!(run(&index, &reader, "title:report AND regex:budget", 10), vec!["/docs/report.txt"]);
+    }
+    
+    #[test]
+    fn test_boost_orders_hits() {
+        let (index, reader) = build_test_index(&[
+            ("/docs/report.txt", "budget", 1),
+            ("/docs/notes.txt", "report", 1),
+        ]);
+        let ranked = |query_str: &str| -> Vec<String> {
+            parse_and_execute(&reader, &index, &SearchConfig::default(), None, query_str, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.path)
+                .collect()
+        };
+        
+        assert_eq!(ranked("title:report^10 OR body:report"), vec!["/docs/report.txt", "/docs/notes.txt"]);
+        assert_eq!(ranked("title:report OR body:report^10"), vec!["/docs/notes.txt", "/docs/report.txt"]);
+        // 权重只影响排序，不影响是否命中
+        assert_eq!(run(&index, &reader, "body:report^0.1", 10), vec!["/docs/notes.txt"]);
+        assert_eq!(run(&index, &reader, "report^2 AND regex:budget", 10), vec!["/docs/report.txt"]);
+    }
+    
+    #[test]
+    fn test_glob_to_term_regex() {
+        assert_eq!(glob_to_term_regex("*.rs").as_deref(), Some(r".*\.rs"));
+        assert_eq!(glob_to_term_regex("a?[!0-9]").as_deref(), Some("a.[^0-9]"));
+        assert_eq!(glob_to_term_regex("[]x]").as_deref(), Some(r"[\]x]"));
+        assert_eq!(glob_to_term_regex("[abc"), None);
+    }
     
     #[test]
     fn test_collect_keywords() {
@@ -446,6 +1607,17 @@ mod tests {
         assert_eq!(keywords, vec!["foo", "bar"]);
     }
     
+    #[test]
+    fn test_collect_phrase_and_fuzzy_keys() {
+        let parsed = query::parse_query(r#""a b"~2 OR word~1"#).unwrap();
+        let query = query::validate_query(&parsed).unwrap();
+        assert_eq!(collect_keywords(&query), vec![r#""a b"~2"#, "word~1"]);
+        
+        let parsed = query::parse_query(r#"title:report OR body:"a b" OR tag:word~1"#).unwrap();
+        let query = query::validate_query(&parsed).unwrap();
+        assert_eq!(collect_keywords(&query), vec!["title:report", r#"body:"a b"~0"#, "tag:word~1"]);
+    }
+    
     #[test]
     fn test_collect_keywords_with_field() {
         let parsed = query::parse_query("keyword AND size:>1MB").unwrap();
@@ -462,4 +1634,79 @@ mod tests {
         // NOT 中的关键词不应该加入搜索
         assert_eq!(keywords, vec!["foo"]);
     }
+    
+    #[test]
+    fn test_regex_not_collected_as_keyword() {
+        let parsed = query::parse_query("foo AND regex:ERROR|WARN").unwrap();
+        let query = query::validate_query(&parsed).unwrap();
+        assert_eq!(collect_keywords(&query), vec!["foo"]);
+        let regexes: Vec<&str> = collect_regexes(&query, false).iter().map(|r| r.as_str()).collect();
+        assert_eq!(regexes, vec!["ERROR|WARN"]);
+    }
+    
+    #[test]
+    fn test_regex_in_not_not_collected() {
+        let parsed = query::parse_query("foo AND NOT regex:bar").unwrap();
+        let query = query::validate_query(&parsed).unwrap();
+        assert!(collect_regexes(&query, false).is_empty());
+        assert_eq!(collect_regexes(&query, true).len(), 1);
+    }
+    
+    #[test]
+    fn test_negated_regex_checks_stored_body() {
+        // 唯一的正则在 NOT 中时也要加载正文，索引中的路径不存在，无法重新读取文件
+        let (index, reader) = build_test_index(&[
+            ("/d/a.txt", "error code 404", 1),
+            ("/d/b.txt", "all good", 1),
+        ]);
+        assert_eq!(run(&index, &reader, "ext:txt AND NOT regex:code.404", 10), vec!["/d/b.txt"]);
+        assert_eq!(run(&index, &reader, "ext:txt AND regex:code.404", 10), vec!["/d/a.txt"]);
+    }
+    
+    #[test]
+    fn test_token_level_pattern() {
+        assert!(is_token_level_pattern("ERROR|WARN"));
+        assert!(is_token_level_pattern("colou?r"));
+        assert!(is_token_level_pattern(r"v\d+"));
+        assert!(is_token_level_pattern("[A-Z]{3}[0-9]{2,4}"));
+        assert!(is_token_level_pattern("错误|警告"));
+        
+        assert!(!is_token_level_pattern(r"^fn\s+\w+"));
+        assert!(!is_token_level_pattern("ERROR.*timeout"));
+        assert!(!is_token_level_pattern("a[^x]b"));
+        assert!(!is_token_level_pattern(r"foo\.bar"));
+        assert!(!is_token_level_pattern("foo-bar"));
+        assert!(!is_token_level_pattern("(?i)error"));
+        assert!(!is_token_level_pattern(""));
+    }
+    
+    #[test]
+    fn test_find_regex_matches_offsets() {
+        let candidate = Candidate {
+            hit: SearchHit {
+                title: "log".into(),
+                path: "/tmp/log.txt".into(),
+                score: 1.0,
+                tags: None,
+                file_size: None,
+                modified_time: None,
+                created_time: None,
+                accessed_time: None,
+                match_ranges: Vec::new(),
+                passage: None,
+                snippet: None,
+                duplicates: Vec::new(),
+            },
+            body: Some("ok\nERROR disk\nWARN cpu\nfn main() {}".into()),
+            matched_keywords: Vec::new(),
+            duplicate: false,
+        };
+        
+        let level = Regex::new("ERROR|WARN").unwrap();
+        let func = Regex::new(r"(?m)^fn\s+\w+").unwrap();
+        let ranges = find_regex_matches(&candidate.text(), &[&level, &func]);
+        assert_eq!(ranges, vec![3..8, 14..18, 23..30]);
+        assert!(matches_term(&candidate, &Term::Regex(func), &HashMap::new()));
+        assert!(!matches_term(&candidate, &Term::Regex(Regex::new("panic").unwrap()), &HashMap::new()));
+    }
 }
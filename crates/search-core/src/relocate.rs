@@ -0,0 +1,219 @@
+// search-core/src/relocate.rs
+//! 路径迁移模块 - 文件或目录移动后改写索引
+//!
+//! 移动不改变文件内容，按索引中存储的正文、标签和段落在新路径下重新写入文档，
+//! 并把关键词、文档向量和元数据缓存迁移到新路径，不重新提取文本也不重新运行 BERT。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use tantivy::collector::DocSetCollector;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value};
+use tantivy::{IndexReader, Searcher, TantivyDocument, Term};
+
+use crate::cache::{EmbeddingCache, FileStatus};
+use crate::catalog::FileStat;
+use crate::passage::{Passage, PassageLocation};
+use crate::schema::{passage_document, IndexDocument, SchemaFields, DOC_KIND_FILE, DOC_KIND_PASSAGE};
+use crate::writer::IndexWriterHandle;
+
+/// 把索引中 `from`（文件或目录）及其子树下的文件改写到 `to` 下的对应路径
+///
+/// 返回已改写的新路径。移动前后内容有变化、或元数据尚未保存（例如还未提交）的文件
+/// 只删除旧路径的文档，由调用方重新索引。
+pub fn relocate_indexed(
+    from: &Path,
+    to: &Path,
+    reader: &IndexReader,
+    writer: &IndexWriterHandle,
+    schema: &Schema,
+    cache: &EmbeddingCache,
+) -> Result<Vec<PathBuf>> {
+    let from_str = canonical_moved_path(from);
+    let to_str = to.canonicalize().unwrap_or_else(|_| to.to_path_buf()).to_string_lossy().to_string();
+    let fields = SchemaFields::from_schema(schema);
+
+    reader.reload()?;
+    let searcher = reader.searcher();
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, term_query(fields.doc_kind, DOC_KIND_FILE)),
+        (Occur::Must, Box::new(BooleanQuery::new(vec![
+            (Occur::Should, term_query(fields.path, &from_str)),
+            // parent_path 包含所有祖先目录，一个词项即可匹配整棵子树
+            (Occur::Should, term_query(fields.parent_path, &from_str)),
+        ]))),
+    ]);
+
+    let mut relocated = Vec::new();
+    for address in searcher.search(&query, &DocSetCollector)? {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let Some(old_path) = read_str(&doc, fields.path) else {
+            continue;
+        };
+        let Some(suffix) = old_path.strip_prefix(&from_str) else {
+            continue;
+        };
+        let new_path = format!("{}{}", to_str, suffix);
+
+        cache.rename(&old_path, &new_path)?;
+        writer.delete(old_path.clone())?;
+        let content_hash = cache.catalog().get(&new_path).map_or(0, |entry| entry.content_hash);
+        let stat = match FileStat::from_path(Path::new(&new_path)) {
+            Ok(stat) if matches!(cache.catalog().status(&new_path, &stat), FileStatus::Unchanged) => stat,
+            _ => {
+                tracing::debug!("[路径迁移] 文件内容已变化，需重新索引: {}", new_path);
+                continue;
+            }
+        };
+
+        let body = read_str(&doc, fields.body).unwrap_or_default();
+        let tags = cache.get_keywords(&new_path, &body).unwrap_or_else(|| {
+            read_str(&doc, fields.tags)
+                .map(|tags| tags.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default()
+        });
+        let passages: Vec<TantivyDocument> = load_passages(&searcher, &fields, &old_path)?
+            .iter()
+            .map(|passage| passage_document(&new_path, passage, &fields))
+            .collect();
+
+        // 标题为文件名（不含扩展名），时间和大小按新路径重新读取
+        let new_file = PathBuf::from(&new_path);
+        let title = new_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let document = IndexDocument::from_path(&new_file, title, body)?.with_tags(tags);
+        writer.upsert_with_passages(new_path, document.to_tantivy(&fields), passages, stat, content_hash)?;
+        relocated.push(new_file);
+    }
+
+    tracing::info!("[路径迁移] {:?} -> {:?}: 已改写 {} 个文件", from, to, relocated.len());
+    Ok(relocated)
+}
+
+/// 索引中 `dir` 下的所有文件路径，用于删除目录时清理子树
+pub fn indexed_paths_under(dir: &Path, reader: &IndexReader, schema: &Schema) -> Result<Vec<String>> {
+    let fields = SchemaFields::from_schema(schema);
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, term_query(fields.doc_kind, DOC_KIND_FILE)),
+        (Occur::Must, term_query(fields.parent_path, &canonical_moved_path(dir))),
+    ]);
+    reader.reload()?;
+    let searcher = reader.searcher();
+    let mut paths = Vec::new();
+    for address in searcher.search(&query, &DocSetCollector)? {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        paths.extend(read_str(&doc, fields.path));
+    }
+    Ok(paths)
+}
+
+/// 已不存在的路径无法直接规范化，规范化其父目录后拼接文件名
+fn canonical_moved_path(path: &Path) -> String {
+    let canonical = match (path.parent().and_then(|p| p.canonicalize().ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    };
+    canonical.to_string_lossy().to_string()
+}
+
+/// 读取文件的所有段落
+fn load_passages(searcher: &Searcher, fields: &SchemaFields, path: &str) -> Result<Vec<Passage>> {
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, term_query(fields.path, path)),
+        (Occur::Must, term_query(fields.doc_kind, DOC_KIND_PASSAGE)),
+    ]);
+    let mut passages = Vec::new();
+    for address in searcher.search(&query, &DocSetCollector)? {
+        let doc: TantivyDocument = searcher.doc(address)?;
+        let Some(text) = read_str(&doc, fields.passage) else {
+            continue;
+        };
+        let read_u64 = |field: Field| doc.get_first(field).and_then(|v| v.as_u64());
+        let location = match read_u64(fields.passage_page) {
+            Some(page) => PassageLocation::Page(page as u32),
+            None => PassageLocation::Line(read_u64(fields.passage_line).unwrap_or(1) as u32),
+        };
+        passages.push(Passage {
+            ordinal: read_u64(fields.passage_ordinal).unwrap_or(0) as usize,
+            text,
+            location,
+        });
+    }
+    passages.sort_by_key(|passage| passage.ordinal);
+    Ok(passages)
+}
+
+fn read_str(doc: &TantivyDocument, field: Field) -> Option<String> {
+    doc.get_first(field).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+fn term_query(field: Field, text: &str) -> Box<dyn Query> {
+    Box::new(TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Arc;
+    use tantivy::Index;
+    use tantivy::collector::Count;
+    use tantivy_jieba::JiebaTokenizer;
+
+    use crate::config::{IndexConfig, PassageConfig};
+    use crate::models::FileDoc;
+    use crate::passage::split_passages;
+    use crate::schema::build_schema;
+
+    fn count(reader: &IndexReader, field: Field, text: &str) -> usize {
+        reader.reload().unwrap();
+        reader.searcher().search(&TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic), &Count).unwrap()
+    }
+
+    #[test]
+    fn test_relocate_directory_keeps_content_and_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("a/sub")).unwrap();
+        let file = root.join("a/sub/notes.txt");
+        fs::write(&file, "alpha beta").unwrap();
+        let old_path = file.to_string_lossy().to_string();
+
+        let index = Index::create_in_ram(build_schema());
+        index.tokenizers().register("jieba", JiebaTokenizer {});
+        let schema = index.schema();
+        let fields = SchemaFields::from_schema(&schema);
+        let cache = Arc::new(EmbeddingCache::new(&root.join("cache")).unwrap());
+        let config = IndexConfig { writer_memory: 15_000_000, ..Default::default() };
+        let writer = IndexWriterHandle::spawn(&index, cache.clone(), &config).unwrap();
+        let reader = index.reader().unwrap();
+
+        let file_doc = FileDoc::new("notes", "alpha beta", old_path.clone());
+        let passages = split_passages(&file_doc, &PassageConfig::default())
+            .iter()
+            .map(|passage| passage_document(&old_path, passage, &fields))
+            .collect();
+        let document = IndexDocument::from_path(&file, "notes".into(), "alpha beta".into()).unwrap()
+            .with_tags(vec!["greek".into()]);
+        let content_hash = crate::catalog::hash_file(&file).unwrap();
+        writer.upsert_with_passages(old_path.clone(), document.to_tantivy(&fields), passages, FileStat::from_path(&file).unwrap(), content_hash).unwrap();
+        cache.set_keywords(&old_path, "alpha beta", vec!["greek".into()]).unwrap();
+        writer.commit().unwrap();
+
+        fs::rename(root.join("a"), root.join("b")).unwrap();
+        let relocated = relocate_indexed(&root.join("a"), &root.join("b"), &reader, &writer, &schema, &cache).unwrap();
+        writer.commit().unwrap();
+
+        let new_path = root.join("b/sub/notes.txt").to_string_lossy().to_string();
+        assert_eq!(relocated, vec![PathBuf::from(&new_path)]);
+        assert_eq!(count(&reader, fields.path, &old_path), 0);
+        // 文件文档和段落文档都已迁移
+        assert_eq!(count(&reader, fields.path, &new_path), 2);
+        assert_eq!(count(&reader, fields.parent_path, &root.join("b").to_string_lossy()), 1);
+        assert_eq!(count(&reader, fields.tags, "greek"), 1);
+        assert_eq!(cache.get_keywords(&new_path, "alpha beta"), Some(vec!["greek".to_string()]));
+        assert!(indexed_paths_under(&root.join("a"), &reader, &schema).unwrap().is_empty());
+        // 内容哈希随记录迁移，不重新读取文件
+        assert_eq!(cache.catalog().get(&new_path).unwrap().content_hash, content_hash);
+    }
+}
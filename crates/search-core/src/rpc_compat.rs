@@ -6,9 +6,12 @@
 //! - `*.rs size:>1MB` - Rust 文件且大于 1MB  
 //! - `root:/home/dev AND mtime:<1w` - 指定目录下最近一周修改的文件
 
-use rpc::search::{SearchRequest as RpcSearchRequest, SearchMode};
-use crate::{SearchEngine, SearchHit};
-use crate::query_executor::{parse_and_execute, QueryExecuteError};
+use rpc::search::{PreviewLocation, SearchErrorKind, SearchRequest as RpcSearchRequest, SearchMode};
+use crate::{PassageLocation, SearchEngine, SearchHit};
+use crate::query_executor::{parse_and_execute, parse_dsl, QueryExecuteError};
+use query::validator::FieldDef;
+use query::{Diagnostics, Query, ValidationError, ValidationErrorKind};
+use std::ops::Range;
 use std::path::PathBuf;
 
 /// 搜索结果项（用于流式返回）
@@ -18,38 +21,45 @@ pub struct SearchResultItem {
     pub title: String,
     pub score: f32,
     pub preview: String,
+    /// 查询词在 `preview` 中的位置（字节偏移）
+    pub highlights: Vec<std::ops::Range<usize>>,
+    /// 预览段落在文件中的位置，没有命中段落时为 `None`
+    pub preview_location: Option<PreviewLocation>,
     pub tags: Vec<String>,
     pub file_size: u64,
-    pub modified_time: std::time::SystemTime,
-    pub created_time: std::time::SystemTime,
-    pub accessed_time: std::time::SystemTime,
+    /// 修改时间（Unix 时间戳秒，1970 年以前为负数），索引中没有时为 `None`
+    pub modified_time: Option<i64>,
+    pub created_time: Option<i64>,
+    pub accessed_time: Option<i64>,
+    /// 内容相同、折叠到该结果下的其他文件
+    pub duplicates: Vec<PathBuf>,
 }
 
 impl From<SearchHit> for SearchResultItem {
     fn from(hit: SearchHit) -> Self {
-        // 将 Unix 时间戳转换为 SystemTime
-        let modified_time = hit.modified_time
-            .map(|secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
-            .unwrap_or_else(|| std::time::SystemTime::now());
-        
-        let created_time = hit.created_time
-            .map(|secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
-            .unwrap_or(std::time::UNIX_EPOCH);
-        
-        let accessed_time = hit.accessed_time
-            .map(|secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
-            .unwrap_or(std::time::UNIX_EPOCH);
+        // 有预览片段时使用片段，否则使用 title
+        let (preview, highlights) = match hit.snippet {
+            Some(snippet) => (snippet.text, snippet.highlights),
+            None => (hit.title.clone(), Vec::new()),
+        };
+        let preview_location = hit.passage.map(|passage| match passage.location {
+            PassageLocation::Page(page) => PreviewLocation::Page(page),
+            PassageLocation::Line(line) => PreviewLocation::Line(line),
+        });
         
         Self {
             path: PathBuf::from(&hit.path),
             title: hit.title.clone(),
             score: hit.score,
-            preview: hit.title.clone(), // SearchHit 没有 preview，使用 title
+            preview,
+            highlights,
+            preview_location,
             tags: hit.tags.map(|t| t.split_whitespace().map(String::from).collect()).unwrap_or_default(),
             file_size: hit.file_size.unwrap_or(0),
-            modified_time,
-            created_time,
-            accessed_time,
+            modified_time: hit.modified_time,
+            created_time: hit.created_time,
+            accessed_time: hit.accessed_time,
+            duplicates: hit.duplicates.into_iter().map(PathBuf::from).collect(),
         }
     }
 }
@@ -67,7 +77,9 @@ pub fn handle_search(
     let query_str = &req.query;
     
     if query_str.is_empty() {
-        return Err(QuerySearchError::ParseError("查询字符串不能为空".to_string()));
+        return Err(QuerySearchError::InvalidQuery(
+            ValidationError::new(query::empty_span(), ValidationErrorKind::EmptyValue).into(),
+        ));
     }
     
     match req.search_mode {
@@ -98,7 +110,7 @@ pub fn search_with_query_dsl(
     tracing::info!("[Query DSL] 执行查询: '{}'", query_str);
     
     // 使用 Query 执行器解析并执行查询
-    let results = parse_and_execute(&engine.reader, &engine.index, query_str, limit)
+    let results = parse_and_execute(&engine.reader, &engine.index, &engine.config, Some(engine.cache.catalog()), query_str, limit)
         .map_err(QuerySearchError::from)?;
     
     tracing::info!("[Query DSL] 找到 {} 个结果", results.len());
@@ -114,7 +126,7 @@ pub fn search_with_query_dsl(
 
 /// 使用 AI 语义搜索
 /// 
-/// 使用 BERT 模型提取关键词，结合传统全文搜索和向量相似度
+/// 使用 BERT 模型提取关键词做全文搜索，并与文档向量的相似度排名融合
 pub fn search_with_semantic(
     engine: &SearchEngine,
     query_str: &str,
@@ -122,18 +134,29 @@ pub fn search_with_semantic(
 ) -> Result<Vec<SearchResultItem>, QuerySearchError> {
     tracing::info!("[语义搜索] 执行查询: '{}'", query_str);
     
-    // 使用 AI 优化查询
-    let refined_query = engine.refine_query(query_str);
-    tracing::info!("[语义搜索] AI 提取的关键词: '{}'", refined_query);
+    // 分离字段过滤条件，只剩过滤条件时按规则搜索处理
+    let (text, filter_dsl) = split_semantic_filters(query_str);
+    if text.is_empty() {
+        return search_with_query_dsl(engine, query_str, limit);
+    }
+    let filter = filter_dsl.as_deref()
+        .map(parse_dsl)
+        .transpose()
+        .map_err(QuerySearchError::from)?;
+    if filter.as_ref().is_some_and(Query::matches_nothing) {
+        tracing::info!("[语义搜索] 过滤条件矛盾，不会匹配任何文件");
+        return Ok(Vec::new());
+    }
     
-    // 混合搜索：结合传统全文搜索和语义匹配
+    // 混合搜索：关键词全文搜索 + 向量相似度
     let results = engine.hybrid_search(
-        &refined_query,
+        &text,
+        filter.as_ref(),
         true,   // use_semantic
         0.5,    // text_weight
         0.5,    // semantic_weight
         limit,
-    ).map_err(|e| QuerySearchError::ExecutionError(e.to_string()))?;
+    ).map_err(|e| QuerySearchError::from(QueryExecuteError::from(e)))?;
     
     tracing::info!("[语义搜索] 找到 {} 个结果", results.len());
     
@@ -145,6 +168,21 @@ pub fn search_with_semantic(
     Ok(items)
 }
 
+/// 从自然语言查询中分离字段过滤条件（如 `root:`、`size:`、`mtime:`）
+/// 
+/// 返回剩余的自然语言文本和过滤条件组成的 Query DSL（各条件之间为 AND）。
+fn split_semantic_filters(query_str: &str) -> (String, Option<String>) {
+    let (filters, words): (Vec<&str>, Vec<&str>) = query_str
+        .split_whitespace()
+        .partition(|token| {
+            token.split_once(':')
+                .is_some_and(|(name, value)| !value.is_empty() && FieldDef::find_by_alias(name).is_some())
+        });
+    
+    let filter_dsl = (!filters.is_empty()).then(|| filters.join(" AND "));
+    (words.join(" "), filter_dsl)
+}
+
 /// 智能搜索：根据查询内容自动选择搜索模式
 /// 
 /// 判断规则：
@@ -165,7 +203,7 @@ pub fn smart_search(
         tracing::info!("[智能搜索] 使用传统全文搜索模式");
         // 使用传统搜索
         let results = engine.search(query_str)
-            .map_err(|e| QuerySearchError::ExecutionError(e.to_string()))?;
+            .map_err(|e| QuerySearchError::from(QueryExecuteError::from(e)))?;
         
         let items: Vec<SearchResultItem> = results
             .into_iter()
@@ -201,22 +239,56 @@ fn is_query_dsl_syntax(query: &str) -> bool {
 }
 
 /// Query 搜索错误
+/// 
+/// 各变体与 `rpc::search::SearchErrorKind` 一一对应，原样传给客户端。
 #[derive(Debug)]
 pub enum QuerySearchError {
-    /// 查询解析错误
-    ParseError(String),
-    /// 查询验证错误
-    ValidationError(String),
+    /// 语法错误，`span` 为第一个语法错误在查询字符串中的字节范围，
+    /// `diagnostics` 包含所有错误和警告
+    ParseError {
+        span: Range<usize>,
+        expected: Vec<String>,
+        found: Option<String>,
+        diagnostics: Diagnostics,
+    },
+    /// 查询无效，包含所有验证错误
+    InvalidQuery(Diagnostics),
     /// 执行错误
-    ExecutionError(String),
+    ExecutionError { message: String },
+    /// 索引无法读取
+    IndexUnavailable,
+    /// 查询超时
+    Timeout,
+    /// 需要校验的候选文档过多
+    TooManyResults,
 }
 
 impl From<QueryExecuteError> for QuerySearchError {
     fn from(err: QueryExecuteError) -> Self {
         match err {
-            QueryExecuteError::ParseError(msg) => QuerySearchError::ParseError(msg),
-            QueryExecuteError::ValidationError(e) => QuerySearchError::ValidationError(e.to_string()),
-            QueryExecuteError::ExecutionError(msg) => QuerySearchError::ExecutionError(msg),
+            QueryExecuteError::ParseError { span, expected, found, diagnostics } => {
+                QuerySearchError::ParseError { span, expected, found, diagnostics }
+            }
+            QueryExecuteError::InvalidQuery(diagnostics) => QuerySearchError::InvalidQuery(diagnostics),
+            QueryExecuteError::ExecutionError { message } => QuerySearchError::ExecutionError { message },
+            QueryExecuteError::IndexUnavailable => QuerySearchError::IndexUnavailable,
+            QueryExecuteError::Timeout => QuerySearchError::Timeout,
+            QueryExecuteError::TooManyResults => QuerySearchError::TooManyResults,
+        }
+    }
+}
+
+impl From<QuerySearchError> for SearchErrorKind {
+    fn from(err: QuerySearchError) -> Self {
+        match err {
+            QuerySearchError::ParseError { span, expected, found, diagnostics } => {
+                SearchErrorKind::ParseError { span, expected, found, diagnostics }
+            }
+            QuerySearchError::InvalidQuery(diagnostics) => SearchErrorKind::InvalidQuery(diagnostics),
+            QuerySearchError::ExecutionError { message } => SearchErrorKind::ExecutionError { message },
+            QuerySearchError::IndexUnavailable => SearchErrorKind::IndexUnavailable,
+            QuerySearchError::Timeout => SearchErrorKind::Timeout,
+            QuerySearchError::TooManyResults => SearchErrorKind::TooManyResults,
         }
     }
 }
@@ -224,11 +296,45 @@ impl From<QueryExecuteError> for QuerySearchError {
 impl std::fmt::Display for QuerySearchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            QuerySearchError::ParseError(msg) => write!(f, "查询解析错误: {}", msg),
-            QuerySearchError::ValidationError(msg) => write!(f, "查询验证错误: {}", msg),
-            QuerySearchError::ExecutionError(msg) => write!(f, "搜索执行错误: {}", msg),
+            QuerySearchError::ParseError { span, expected, found, diagnostics } => {
+                write!(f, "语法错误 (位置 {}..{}): ", span.start, span.end)?;
+                match found {
+                    Some(found) => write!(f, "意外的 '{}'", found)?,
+                    None => write!(f, "查询意外结束")?,
+                }
+                if !expected.is_empty() {
+                    write!(f, "，期望 {}", expected.join(", "))?;
+                }
+                if diagnostics.errors.len() > 1 {
+                    write!(f, "（另有 {} 个错误）", diagnostics.errors.len() - 1)?;
+                }
+                Ok(())
+            }
+            QuerySearchError::InvalidQuery(diagnostics) => write!(f, "查询无效: {}", diagnostics),
+            QuerySearchError::ExecutionError { message } => write!(f, "搜索执行错误: {}", message),
+            QuerySearchError::IndexUnavailable => write!(f, "索引不可用"),
+            QuerySearchError::Timeout => write!(f, "查询超时"),
+            QuerySearchError::TooManyResults => write!(f, "需要校验的候选文档过多"),
         }
     }
 }
 
 impl std::error::Error for QuerySearchError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_semantic_filters() {
+        assert_eq!(
+            split_semantic_filters("如何解析 JSON root:/home/dev mtime:<1w"),
+            ("如何解析 JSON".to_string(), Some("root:/home/dev AND mtime:<1w".to_string()))
+        );
+        // 未知字段和空值保留在自然语言文本中
+        assert_eq!(
+            split_semantic_filters("note: meeting at 10:30"),
+            ("note: meeting at 10:30".to_string(), None)
+        );
+    }
+}
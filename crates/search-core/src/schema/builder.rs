@@ -8,16 +8,25 @@ use super::fields::*;
 
 /// 构建 Tantivy Schema
 /// 
-/// # 已启用字段
+/// # 字段
 /// - `title`: 文件标题，中文分词，存储
 /// - `body`: 文件内容，中文分词，存储
 /// - `path`: 文件路径，精确匹配，存储
 /// - `tags`: AI 标签，中文分词，存储
+/// - `parent_path`: 父目录及祖先目录，精确匹配，存储
+/// - `filename`: 文件名，精确匹配，存储
+/// - `file_type`: 扩展名，精确匹配，存储
 /// - `file_size`: 文件大小，快速过滤，存储
-/// - `modified_time`: 修改时间，快速过滤，存储
+/// - `modified_time` / `created_time` / `accessed_time`: 文件时间，快速过滤，存储
+/// - `indexed_time`: 索引时间，快速过滤，存储
+/// - `doc_kind`: 文件文档或段落文档，精确匹配
+/// - `passage`: 段落文本，中文分词，存储
+/// - `passage_ordinal`: 段落序号，精确匹配，存储
+/// - `passage_page` / `passage_line`: 段落所在页码或起始行号，存储
 /// 
-/// # 待启用字段
-/// 见 `fields.rs` 中的注释
+/// 文件文档和段落文档共用一个索引，段落文档只有 `path`、`doc_kind` 和 `passage*` 字段。
+/// 
+/// 修改字段后已有索引会在启动时自动重建，见 `indexer::migrate_index_schema`
 pub fn build_schema() -> Schema {
     let mut schema_builder = Schema::builder();
 
@@ -30,8 +39,6 @@ pub fn build_schema() -> Schema {
         )
         .set_stored();
 
-    // ============== 已启用字段 ==============
-    
     // 文本字段（支持中文分词）
     schema_builder.add_text_field(FIELD_TITLE, text_options.clone());
     schema_builder.add_text_field(FIELD_BODY, text_options.clone());
@@ -39,27 +46,24 @@ pub fn build_schema() -> Schema {
     
     // 路径字段（精确匹配，不分词）
     schema_builder.add_text_field(FIELD_PATH, STRING | STORED);
+    schema_builder.add_text_field(FIELD_PARENT_PATH, STRING | STORED);
+    schema_builder.add_text_field(FIELD_FILENAME, STRING | STORED);
+    schema_builder.add_text_field(FIELD_FILE_TYPE, STRING | STORED);
     
     // 数值字段（支持范围查询和排序）
     schema_builder.add_u64_field(FIELD_FILE_SIZE, FAST | STORED);
-    schema_builder.add_u64_field(FIELD_MODIFIED_TIME, FAST | STORED);
-
-    // ============== 待启用字段 ==============
-    // 取消下方注释并在 document.rs 中添加对应字段即可启用
+    // 时间为有符号 Unix 时间戳秒，1970 年以前为负数
+    schema_builder.add_i64_field(FIELD_MODIFIED_TIME, FAST | STORED);
+    schema_builder.add_i64_field(FIELD_CREATED_TIME, FAST | STORED);
+    schema_builder.add_i64_field(FIELD_ACCESSED_TIME, FAST | STORED);
+    schema_builder.add_i64_field(FIELD_INDEXED_TIME, FAST | STORED);
     
-    // // 父目录路径（精确匹配，用于目录过滤）
-    // schema_builder.add_text_field(FIELD_PARENT_PATH, STRING | STORED);
-    // 
-    // // 文件名（精确匹配）
-    // schema_builder.add_text_field(FIELD_FILENAME, STRING | STORED);
-    // 
-    // // 文件类型（精确匹配，用于类型过滤）
-    // schema_builder.add_text_field(FIELD_FILE_TYPE, STRING | STORED);
-    // 
-    // // 时间字段（支持范围查询）
-    schema_builder.add_u64_field(FIELD_CREATED_TIME, FAST | STORED);
-    schema_builder.add_u64_field(FIELD_ACCESSED_TIME, FAST | STORED);
-    // schema_builder.add_u64_field(FIELD_INDEXED_TIME, FAST | STORED);
+    // 段落字段（段落文档通过 path 关联所属文件）
+    schema_builder.add_text_field(FIELD_DOC_KIND, STRING);
+    schema_builder.add_text_field(FIELD_PASSAGE, text_options);
+    schema_builder.add_u64_field(FIELD_PASSAGE_ORDINAL, INDEXED | STORED);
+    schema_builder.add_u64_field(FIELD_PASSAGE_PAGE, STORED);
+    schema_builder.add_u64_field(FIELD_PASSAGE_LINE, STORED);
 
     schema_builder.build()
 }
@@ -72,16 +76,19 @@ pub struct SchemaFields {
     pub body: Field,
     pub path: Field,
     pub tags: Field,
+    pub parent_path: Field,
+    pub filename: Field,
+    pub file_type: Field,
     pub file_size: Field,
     pub modified_time: Field,
     pub created_time: Field,
     pub accessed_time: Field,
-    
-    // 待启用
-    // pub parent_path: Field,
-    // pub filename: Field,
-    // pub file_type: Field,
-    // pub indexed_time: Field,
+    pub indexed_time: Field,
+    pub doc_kind: Field,
+    pub passage: Field,
+    pub passage_ordinal: Field,
+    pub passage_page: Field,
+    pub passage_line: Field,
 }
 
 impl SchemaFields {
@@ -92,14 +99,19 @@ impl SchemaFields {
             body: schema.get_field(FIELD_BODY).expect("missing body field"),
             path: schema.get_field(FIELD_PATH).expect("missing path field"),
             tags: schema.get_field(FIELD_TAGS).expect("missing tags field"),
+            parent_path: schema.get_field(FIELD_PARENT_PATH).expect("missing parent_path field"),
+            filename: schema.get_field(FIELD_FILENAME).expect("missing filename field"),
+            file_type: schema.get_field(FIELD_FILE_TYPE).expect("missing file_type field"),
             file_size: schema.get_field(FIELD_FILE_SIZE).expect("missing file_size field"),
             modified_time: schema.get_field(FIELD_MODIFIED_TIME).expect("missing modified_time field"),
             created_time: schema.get_field(FIELD_CREATED_TIME).expect("missing created_time field"),
             accessed_time: schema.get_field(FIELD_ACCESSED_TIME).expect("missing accessed_time field"),
-            // parent_path: schema.get_field(FIELD_PARENT_PATH).expect("missing parent_path field"),
-            // filename: schema.get_field(FIELD_FILENAME).expect("missing filename field"),
-            // file_type: schema.get_field(FIELD_FILE_TYPE).expect("missing file_type field"),
-            // indexed_time: schema.get_field(FIELD_INDEXED_TIME).expect("missing indexed_time field"),
+            indexed_time: schema.get_field(FIELD_INDEXED_TIME).expect("missing indexed_time field"),
+            doc_kind: schema.get_field(FIELD_DOC_KIND).expect("missing doc_kind field"),
+            passage: schema.get_field(FIELD_PASSAGE).expect("missing passage field"),
+            passage_ordinal: schema.get_field(FIELD_PASSAGE_ORDINAL).expect("missing passage_ordinal field"),
+            passage_page: schema.get_field(FIELD_PASSAGE_PAGE).expect("missing passage_page field"),
+            passage_line: schema.get_field(FIELD_PASSAGE_LINE).expect("missing passage_line field"),
         }
     }
 }
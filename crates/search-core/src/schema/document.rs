@@ -7,12 +7,15 @@ use std::path::Path;
 use std::time::SystemTime;
 use serde::{Serialize, Deserialize};
 use anyhow::Result;
+use tantivy::TantivyDocument;
+
+use super::builder::SchemaFields;
+use super::fields::{DOC_KIND_FILE, DOC_KIND_PASSAGE};
+use crate::passage::{Passage, PassageLocation};
 
 /// 索引文档 - 待写入 Tantivy 的文件信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexDocument {
-    // ============== 已启用字段 ==============
-    
     /// 文件标题（通常是文件名，不含扩展名）
     pub title: String,
     
@@ -25,32 +28,29 @@ pub struct IndexDocument {
     /// AI 生成的标签
     pub tags: Vec<String>,
     
-    /// 文件大小（字节）
-    pub file_size: u64,
-    
-    /// 修改时间（Unix 时间戳秒）
-    pub modified_time: u64,
+    /// 父目录路径
+    pub parent_path: String,
     
-    // ============== 待启用字段 ==============
-    // 取消注释并在 builder.rs 中添加对应字段即可启用
+    /// 文件名（含扩展名）
+    pub filename: String,
     
-    // /// 父目录路径
-    // pub parent_path: String,
+    /// 文件类型/扩展名（小写，不含点号）
+    pub file_type: String,
     
-    // /// 文件名（含扩展名）
-    // pub filename: String,
+    /// 文件大小（字节）
+    pub file_size: u64,
     
-    // /// 文件类型/扩展名
-    // pub file_type: String,
+    /// 修改时间（Unix 时间戳秒，1970 年以前为负数），文件系统不提供时为 `None`
+    pub modified_time: Option<i64>,
     
-    // /// 创建时间（Unix 时间戳秒）
-    // pub created_time: u64,
+    /// 创建时间（Unix 时间戳秒），文件系统不提供时为 `None`
+    pub created_time: Option<i64>,
     
-    // /// 访问时间（Unix 时间戳秒）
-    // pub accessed_time: u64,
+    /// 访问时间（Unix 时间戳秒），文件系统不提供时为 `None`
+    pub accessed_time: Option<i64>,
     
-    // /// 索引时间（Unix 时间戳秒）
-    // pub indexed_time: u64,
+    /// 索引时间（Unix 时间戳秒）
+    pub indexed_time: i64,
 }
 
 impl IndexDocument {
@@ -63,55 +63,36 @@ impl IndexDocument {
         
         let file_size = metadata.len();
         
-        let modified_time = metadata.modified()
-            .unwrap_or(SystemTime::UNIX_EPOCH)
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let parent_path = canonical_path.parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        
+        let filename = canonical_path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        
+        let file_type = canonical_path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
         
-        // === 待启用字段的提取逻辑 ===
-        // let parent_path = canonical_path.parent()
-        //     .map(|p| p.to_string_lossy().to_string())
-        //     .unwrap_or_default();
-        // 
-        // let filename = canonical_path.file_name()
-        //     .map(|n| n.to_string_lossy().to_string())
-        //     .unwrap_or_default();
-        // 
-        // let file_type = canonical_path.extension()
-        //     .map(|e| e.to_string_lossy().to_lowercase())
-        //     .unwrap_or_default();
-        // 
-        // let created_time = metadata.created()
-        //     .unwrap_or(SystemTime::UNIX_EPOCH)
-        //     .duration_since(SystemTime::UNIX_EPOCH)
-        //     .unwrap_or_default()
-        //     .as_secs();
-        // 
-        // let accessed_time = metadata.accessed()
-        //     .unwrap_or(SystemTime::UNIX_EPOCH)
-        //     .duration_since(SystemTime::UNIX_EPOCH)
-        //     .unwrap_or_default()
-        //     .as_secs();
-        // 
-        // let indexed_time = SystemTime::now()
-        //     .duration_since(SystemTime::UNIX_EPOCH)
-        //     .unwrap_or_default()
-        //     .as_secs();
+        let modified_time = metadata.modified().ok().map(unix_secs);
+        let created_time = metadata.created().ok().map(unix_secs);
+        let accessed_time = metadata.accessed().ok().map(unix_secs);
+        let indexed_time = unix_secs(SystemTime::now());
         
         Ok(Self {
             title,
             content,
             path: path_str,
             tags: Vec::new(),
+            parent_path,
+            filename,
+            file_type,
             file_size,
             modified_time,
-            // parent_path,
-            // filename,
-            // file_type,
-            // created_time,
-            // accessed_time,
-            // indexed_time,
+            created_time,
+            accessed_time,
+            indexed_time,
         })
     }
     
@@ -125,4 +106,62 @@ impl IndexDocument {
     pub fn tags_string(&self) -> String {
         self.tags.join(" ")
     }
+    
+    /// 转换为 Tantivy 文档
+    /// 
+    /// `parent_path` 除直接父目录外还会写入所有祖先目录，
+    /// 使 `root:` 查询可以用单个词项匹配整棵子树。
+    pub fn to_tantivy(&self, fields: &SchemaFields) -> TantivyDocument {
+        let mut doc = TantivyDocument::default();
+        doc.add_text(fields.doc_kind, DOC_KIND_FILE);
+        doc.add_text(fields.title, &self.title);
+        doc.add_text(fields.body, &self.content);
+        doc.add_text(fields.path, &self.path);
+        doc.add_text(fields.tags, self.tags_string());
+        for ancestor in Path::new(&self.path).ancestors().skip(1) {
+            if !ancestor.as_os_str().is_empty() {
+                doc.add_text(fields.parent_path, ancestor.to_string_lossy());
+            }
+        }
+        doc.add_text(fields.filename, &self.filename);
+        doc.add_text(fields.file_type, &self.file_type);
+        doc.add_u64(fields.file_size, self.file_size);
+        // 缺失的时间不写入，查询时按字段不存在处理
+        let times = [
+            (fields.modified_time, self.modified_time),
+            (fields.created_time, self.created_time),
+            (fields.accessed_time, self.accessed_time),
+            (fields.indexed_time, Some(self.indexed_time)),
+        ];
+        for (field, time) in times {
+            if let Some(time) = time {
+                doc.add_i64(field, time);
+            }
+        }
+        doc
+    }
+}
+
+/// 构建段落文档，通过 `path` 关联所属文件
+/// 
+/// 按 path 删除文件时段落文档一并删除。
+pub fn passage_document(path: &str, passage: &Passage, fields: &SchemaFields) -> TantivyDocument {
+    let mut doc = TantivyDocument::default();
+    doc.add_text(fields.doc_kind, DOC_KIND_PASSAGE);
+    doc.add_text(fields.path, path);
+    doc.add_text(fields.passage, &passage.text);
+    doc.add_u64(fields.passage_ordinal, passage.ordinal as u64);
+    match passage.location {
+        PassageLocation::Page(page) => doc.add_u64(fields.passage_page, u64::from(page)),
+        PassageLocation::Line(line) => doc.add_u64(fields.passage_line, u64::from(line)),
+    }
+    doc
+}
+
+/// 转换为有符号 Unix 时间戳秒，1970 年以前的时间为负数
+fn unix_secs(time: SystemTime) -> i64 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_secs()).unwrap_or(i64::MAX),
+        Err(before) => i64::try_from(before.duration().as_secs()).map_or(i64::MIN, |secs| -secs),
+    }
 }
@@ -23,50 +23,69 @@ pub const FIELD_FILE_SIZE: &str = "file_size";
 /// 文件修改时间（Unix 时间戳秒）
 pub const FIELD_MODIFIED_TIME: &str = "modified_time";
 
-// ============== 待启用字段 ==============
-// 以下字段已在 RPC SearchRequest 中定义过滤条件，但 Schema 尚未支持
-// 启用后需要同步修改: builder.rs, document.rs, indexer.rs, rpc_compat.rs
-
-/// 父目录路径（用于目录过滤）
+/// 父目录路径（多值：直接父目录及所有祖先目录，用于目录过滤）
 /// 
-/// **状态**: 🔴 未启用
-/// **用途**: 支持 "只搜索某目录下的文件" 功能
-/// **RPC 对应**: SearchRequest.root_directories（当前在内存中过滤）
-#[allow(dead_code)]
+/// **状态**: 🟢 已启用
+/// **用途**: 支持 "只搜索某目录下的文件" 功能，`root:` 查询直接命中词项
+/// **RPC 对应**: SearchRequest.root_directories
 pub const FIELD_PARENT_PATH: &str = "parent_path";
 
 /// 文件名（不含路径，含扩展名）
 /// 
-/// **状态**: 🔴 未启用
-/// **用途**: 支持按文件名精确搜索
-#[allow(dead_code)]
+/// **状态**: 🟢 已启用
+/// **用途**: 支持按文件名精确搜索，`glob:`/`name:` 查询在该字段的词典上匹配
 pub const FIELD_FILENAME: &str = "filename";
 
-/// 文件类型/扩展名（不含点号，如 "rs", "md"）
+/// 文件类型/扩展名（小写，不含点号，如 "rs", "md"）
 /// 
-/// **状态**: 🔴 未启用
-/// **用途**: 支持按文件类型过滤
-/// **RPC 对应**: SearchRequest.include_globs（当前用 glob 匹配）
-#[allow(dead_code)]
+/// **状态**: 🟢 已启用
+/// **用途**: 支持按文件类型过滤（`ext:`/`type:`）
+/// **RPC 对应**: SearchRequest.include_globs
 pub const FIELD_FILE_TYPE: &str = "file_type";
 
 /// 文件创建时间（Unix 时间戳秒）
 /// 
-/// **状态**: � 已启用
+/// **状态**: 🟢 已启用
 /// **用途**: 支持按创建时间范围过滤
 /// **RPC 对应**: SearchRequest.time_created_range
 pub const FIELD_CREATED_TIME: &str = "created_time";
 
 /// 文件访问时间（Unix 时间戳秒）
 /// 
-/// **状态**: � 已启用
+/// **状态**: 🟢 已启用
 /// **用途**: 支持按访问时间范围过滤
 /// **RPC 对应**: SearchRequest.time_accessed_range
 pub const FIELD_ACCESSED_TIME: &str = "accessed_time";
 
 /// 索引时间（文档被索引的时间）
 /// 
-/// **状态**: 🔴 未启用
+/// **状态**: 🟢 已启用
 /// **用途**: 追踪索引更新，增量同步
-#[allow(dead_code)]
 pub const FIELD_INDEXED_TIME: &str = "indexed_time";
+
+/// 文档类型：`file` 为文件文档，`passage` 为段落文档
+/// 
+/// **状态**: 🟢 已启用
+/// **用途**: 长文件切分出的段落作为独立文档写入索引，规则查询只匹配文件文档
+pub const FIELD_DOC_KIND: &str = "doc_kind";
+
+/// 段落文本（仅段落文档，通过 `path` 关联所属文件）
+/// 
+/// **状态**: 🟢 已启用
+/// **用途**: 按段落做全文检索，最相关的段落作为搜索结果预览
+pub const FIELD_PASSAGE: &str = "passage";
+
+/// 段落在文件中的序号（从 0 开始，与文档向量的分块序号一致）
+pub const FIELD_PASSAGE_ORDINAL: &str = "passage_ordinal";
+
+/// 段落所在页码（分页文档，从 1 开始）
+pub const FIELD_PASSAGE_PAGE: &str = "passage_page";
+
+/// 段落起始行号（非分页文档，从 1 开始）
+pub const FIELD_PASSAGE_LINE: &str = "passage_line";
+
+/// `doc_kind` 取值：文件文档
+pub const DOC_KIND_FILE: &str = "file";
+
+/// `doc_kind` 取值：段落文档
+pub const DOC_KIND_PASSAGE: &str = "passage";
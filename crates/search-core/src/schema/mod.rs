@@ -6,7 +6,9 @@
 pub mod fields;
 pub mod document;
 pub mod builder;
+pub mod version;
 
 pub use fields::*;
-pub use document::IndexDocument;
+pub use document::{passage_document, IndexDocument};
 pub use builder::{build_schema, SchemaFields};
+pub use version::{check_index_schema, SchemaCheck, SchemaStamp, SCHEMA_VERSION};
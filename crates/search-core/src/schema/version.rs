@@ -0,0 +1,157 @@
+// search-core/src/schema/version.rs
+//! Schema 版本管理
+//!
+//! 在索引目录中记录 Schema 版本号和指纹，启动时据此判断旧索引能否继续使用
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tantivy::schema::Schema;
+
+use super::builder::build_schema;
+
+/// 当前 Schema 版本
+///
+/// 修改 `build_schema()` 时递增，便于在日志和错误信息中定位索引来自哪个版本
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// 版本文件名（位于索引目录内，随索引一起删除）
+pub const SCHEMA_STAMP_FILE: &str = "schema_version.json";
+
+/// 索引目录中记录的 Schema 版本信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaStamp {
+    /// Schema 版本号
+    pub version: u32,
+    /// Schema 指纹（字段定义序列化后的哈希）
+    pub fingerprint: String,
+}
+
+impl SchemaStamp {
+    /// 当前代码对应的版本信息
+    pub fn current() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            fingerprint: schema_fingerprint(&build_schema()),
+        }
+    }
+
+    /// 读取索引目录中的版本文件，不存在时返回 `None`
+    pub fn load(index_path: &Path) -> Result<Option<Self>> {
+        let stamp_path = index_path.join(SCHEMA_STAMP_FILE);
+        if !stamp_path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(stamp_path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// 写入版本文件
+    pub fn save(&self, index_path: &Path) -> Result<()> {
+        fs::create_dir_all(index_path)?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(index_path.join(SCHEMA_STAMP_FILE), content)?;
+        Ok(())
+    }
+}
+
+/// 计算 Schema 指纹
+///
+/// 对字段定义的 JSON 序列化结果做 FNV-1a 哈希，不依赖标准库哈希的实现细节，
+/// 跨编译器版本保持稳定。
+pub fn schema_fingerprint(schema: &Schema) -> String {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let json = serde_json::to_vec(schema).expect("schema is always serializable");
+    let hash = json.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+/// 索引 Schema 检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaCheck {
+    /// 索引不存在，将按当前 Schema 新建
+    Missing,
+    /// 索引与当前 Schema 兼容
+    Compatible,
+    /// 索引来自不兼容的旧版本（`None` 表示没有版本文件的旧索引）
+    Outdated { found: Option<SchemaStamp> },
+}
+
+/// 检查索引目录中的 Schema 是否与当前版本一致
+///
+/// 没有版本文件的旧索引会比较实际的字段定义，一致时补写版本文件。
+pub fn check_index_schema(index_path: &Path) -> Result<SchemaCheck> {
+    if !index_path.join("meta.json").exists() {
+        return Ok(SchemaCheck::Missing);
+    }
+
+    let current = SchemaStamp::current();
+    match SchemaStamp::load(index_path) {
+        Ok(Some(stamp)) if stamp == current => Ok(SchemaCheck::Compatible),
+        Ok(Some(stamp)) => Ok(SchemaCheck::Outdated { found: Some(stamp) }),
+        Ok(None) | Err(_) => {
+            let compatible = tantivy::Index::open_in_dir(index_path)
+                .map(|index| index.schema() == build_schema())
+                .unwrap_or(false);
+            if compatible {
+                current.save(index_path)?;
+                Ok(SchemaCheck::Compatible)
+            } else {
+                Ok(SchemaCheck::Outdated { found: None })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::schema::{STORED, STRING};
+
+    #[test]
+    fn test_fingerprint_tracks_schema_changes() {
+        let current = schema_fingerprint(&build_schema());
+        assert_eq!(current, schema_fingerprint(&build_schema()));
+
+        let mut builder = Schema::builder();
+        builder.add_text_field("path", STRING | STORED);
+        assert_ne!(current, schema_fingerprint(&builder.build()));
+    }
+
+    #[test]
+    fn test_check_index_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(check_index_schema(dir.path()).unwrap(), SchemaCheck::Missing);
+
+        // 没有版本文件但字段一致的旧索引会补写版本文件
+        tantivy::Index::create_in_dir(dir.path(), build_schema()).unwrap();
+        assert_eq!(check_index_schema(dir.path()).unwrap(), SchemaCheck::Compatible);
+        assert_eq!(SchemaStamp::load(dir.path()).unwrap(), Some(SchemaStamp::current()));
+
+        let old = SchemaStamp { version: 1, fingerprint: "0".into() };
+        old.save(dir.path()).unwrap();
+        assert_eq!(
+            check_index_schema(dir.path()).unwrap(),
+            SchemaCheck::Outdated { found: Some(old) }
+        );
+    }
+
+    #[test]
+    fn test_legacy_index_with_different_fields_is_outdated() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut builder = Schema::builder();
+        builder.add_text_field("path", STRING | STORED);
+        tantivy::Index::create_in_dir(dir.path(), builder.build()).unwrap();
+
+        assert_eq!(
+            check_index_schema(dir.path()).unwrap(),
+            SchemaCheck::Outdated { found: None }
+        );
+    }
+}
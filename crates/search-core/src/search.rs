@@ -1,15 +1,25 @@
 // search-core/src/search.rs
 //! 搜索模块
 
+use std::collections::HashMap;
+
 use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
-use tantivy::{Index, IndexReader, TantivyDocument};
+use tantivy::query::{BooleanQuery, EnableScoring, Occur, Query, QueryParser, TermQuery};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::{DocSet, Index, IndexReader, Searcher, TantivyDocument, Term, TERMINATED};
 use tantivy::schema::*;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::cache::EmbeddingCache;
+use crate::config::{FieldBoostConfig, CONFIG};
+use crate::passage::{Passage, PassageLocation};
+use crate::snippet::Snippet;
 use crate::SearchHit;
-use crate::schema::{FIELD_TITLE, FIELD_BODY, FIELD_PATH, FIELD_TAGS};
+use crate::schema::{
+    DOC_KIND_FILE, DOC_KIND_PASSAGE, FIELD_BODY, FIELD_DOC_KIND, FIELD_PASSAGE, FIELD_PASSAGE_LINE,
+    FIELD_PASSAGE_ORDINAL, FIELD_PASSAGE_PAGE, FIELD_PATH, FIELD_TAGS, FIELD_TITLE,
+};
 
 /// 排序模式
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -25,8 +35,8 @@ pub enum SortMode {
 }
 
 /// 搜索索引（打印结果版本，用于 CLI）
-pub fn search_index(reader: &IndexReader, index: &Index, query_str: &str) -> Result<()> {
-    let results = search_with_results(reader, index, query_str)?;
+pub fn search_index(reader: &IndexReader, index: &Index, boosts: &FieldBoostConfig, query_str: &str) -> Result<()> {
+    let results = search_with_results(reader, index, boosts, query_str)?;
     
     if results.is_empty() {
         println!("     没有找到相关文档");
@@ -39,25 +49,51 @@ pub fn search_index(reader: &IndexReader, index: &Index, query_str: &str) -> Res
     Ok(())
 }
 
+/// 全文搜索默认返回的文件数
+const DEFAULT_TEXT_LIMIT: usize = 20;
+
+/// 每个结果文件预取的候选文档数（同一个长文件可能有多个段落同时命中）
+const CANDIDATES_PER_HIT: usize = 5;
+
 /// 搜索索引（返回结果版本，用于 API）
-pub fn search_with_results(reader: &IndexReader, index: &Index, query_str: &str) -> Result<Vec<SearchHit>> {
-    tracing::debug!("[Tantivy 搜索] 查询字符串: '{}'", query_str);
-    
+pub fn search_with_results(
+    reader: &IndexReader,
+    index: &Index,
+    boosts: &FieldBoostConfig,
+    query_str: &str,
+) -> Result<Vec<SearchHit>> {
     let searcher = reader.searcher();
+    text_search(&searcher, index, boosts, query_str, None, DEFAULT_TEXT_LIMIT)
+}
+
+/// 全文搜索：同时检索文件文档（标题、正文、标签）和段落文档，按文件聚合，
+/// 各字段按 `boosts` 加权
+/// 
+/// 文件得分取其命中文档中的最高分，结果附带该文件得分最高的段落，
+/// 以及从该段落中截取的包含查询词的预览片段。
+/// `path_filter` 不为空时只返回满足过滤条件的文件。
+fn text_search(
+    searcher: &Searcher,
+    index: &Index,
+    boosts: &FieldBoostConfig,
+    query_str: &str,
+    path_filter: Option<&PathFilter>,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    tracing::debug!("[Tantivy 搜索] 查询字符串: '{}'", query_str);
     
     let schema = index.schema();
-    let title_field = schema.get_field(FIELD_TITLE).unwrap();
-    let body_field = schema.get_field(FIELD_BODY).unwrap();
-    let path_field = schema.get_field(FIELD_PATH).unwrap();
-    let tags_field = schema.get_field(FIELD_TAGS).ok();
-    
-    // 获取时间和大小字段
-    let file_size_field = schema.get_field(crate::schema::FIELD_FILE_SIZE).ok();
-    let modified_time_field = schema.get_field(crate::schema::FIELD_MODIFIED_TIME).ok();
-    let created_time_field = schema.get_field(crate::schema::FIELD_CREATED_TIME).ok();
-    let accessed_time_field = schema.get_field(crate::schema::FIELD_ACCESSED_TIME).ok();
+    let hit_fields = HitFields::from_schema(&schema);
 
-    let query_parser = QueryParser::for_index(index, vec![title_field, body_field]);
+    let mut targets = vec![hit_fields.title, hit_fields.body, hit_fields.passage];
+    targets.extend(hit_fields.tags);
+    let mut query_parser = QueryParser::for_index(index, targets);
+    query_parser.set_field_boost(hit_fields.title, boosts.title);
+    query_parser.set_field_boost(hit_fields.body, boosts.body);
+    query_parser.set_field_boost(hit_fields.passage, boosts.body);
+    if let Some(tags) = hit_fields.tags {
+        query_parser.set_field_boost(tags, boosts.tags);
+    }
     
     let query = match query_parser.parse_query(query_str) {
         Ok(q) => {
@@ -70,57 +106,184 @@ pub fn search_with_results(reader: &IndexReader, index: &Index, query_str: &str)
         }
     };
 
-    let top_docs = searcher.search(&query, &TopDocs::with_limit(20))?;
-    tracing::debug!("[Tantivy 搜索] 找到 {} 个文档", top_docs.len());
+    // 按得分从高到低分页读取命中文档，直到凑够 `limit` 个文件
+    let mut matches: Vec<FileMatch> = Vec::new();
+    let mut positions: HashMap<String, Option<usize>> = HashMap::new();
+    let page_size = limit * CANDIDATES_PER_HIT;
+    let mut offset = 0;
+    while matches.len() < limit {
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(page_size).and_offset(offset))?;
+        let fetched = top_docs.len();
+        
+        for (score, address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let Some(path) = doc.get_first(hit_fields.path).and_then(|v| v.as_str()) else {
+                continue;
+            };
+            // 文件第一次出现时的得分即最高分，被过滤掉的文件记为 `None`
+            let position = *positions.entry(path.to_string()).or_insert_with(|| {
+                path_filter.is_none_or(|filter| filter.allows(path)).then(|| {
+                    matches.push(FileMatch { path: path.to_string(), score, file: None, passage: None });
+                    matches.len() - 1
+                })
+            });
+            let Some(position) = position else {
+                continue;
+            };
+            
+            let file_match = &mut matches[position];
+            match hit_fields.to_passage(&doc) {
+                Some(passage) => {
+                    file_match.passage.get_or_insert(passage);
+                }
+                None => {
+                    file_match.file.get_or_insert_with(|| hit_fields.to_hit(&doc, score));
+                }
+            }
+        }
+        
+        if fetched < page_size {
+            break;
+        }
+        offset += fetched;
+    }
+    matches.truncate(limit);
+    tracing::debug!("[Tantivy 搜索] 找到 {} 个文件", matches.len());
     
-    let mut results = Vec::new();
-    for (score, doc_address) in top_docs {
-        let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
+    let snippet_generator = snippet_generator(searcher, query.as_ref(), hit_fields.passage)?;
+    let mut results = Vec::with_capacity(matches.len());
+    for file_match in matches {
+        let hit = match file_match.file {
+            Some(hit) => Some(hit),
+            None => load_hit_by_path(searcher, &hit_fields, &file_match.path)?,
+        };
+        let Some(mut hit) = hit else {
+            continue;
+        };
+        hit.score = file_match.score;
+        hit.passage = match file_match.passage {
+            Some(passage) => Some(passage),
+            None => best_passage(searcher, &hit_fields, query.as_ref(), &file_match.path)?,
+        };
+        hit.snippet = hit.passage.as_ref()
+            .map(|passage| highlighted_snippet(&snippet_generator, &passage.text).unwrap_or_else(|| Snippet::leading(&passage.text)));
+        results.push(hit);
+    }
 
-        let title = retrieved_doc.get_first(title_field)
+    Ok(results)
+}
+
+/// 全文搜索中按文件聚合的命中
+struct FileMatch {
+    path: String,
+    /// 文件命中文档中的最高分
+    score: f32,
+    /// 文件文档本身命中时直接使用，否则按路径读取
+    file: Option<SearchHit>,
+    /// 得分最高的段落
+    passage: Option<Passage>,
+}
+
+/// 构建 `SearchHit` 所需的字段
+struct HitFields {
+    title: Field,
+    body: Field,
+    path: Field,
+    tags: Option<Field>,
+    file_size: Option<Field>,
+    modified_time: Option<Field>,
+    created_time: Option<Field>,
+    accessed_time: Option<Field>,
+    doc_kind: Field,
+    passage: Field,
+    passage_ordinal: Field,
+    passage_page: Field,
+    passage_line: Field,
+}
+
+impl HitFields {
+    fn from_schema(schema: &Schema) -> Self {
+        Self {
+            title: schema.get_field(FIELD_TITLE).unwrap(),
+            body: schema.get_field(FIELD_BODY).unwrap(),
+            path: schema.get_field(FIELD_PATH).unwrap(),
+            tags: schema.get_field(FIELD_TAGS).ok(),
+            file_size: schema.get_field(crate::schema::FIELD_FILE_SIZE).ok(),
+            modified_time: schema.get_field(crate::schema::FIELD_MODIFIED_TIME).ok(),
+            created_time: schema.get_field(crate::schema::FIELD_CREATED_TIME).ok(),
+            accessed_time: schema.get_field(crate::schema::FIELD_ACCESSED_TIME).ok(),
+            doc_kind: schema.get_field(FIELD_DOC_KIND).unwrap(),
+            passage: schema.get_field(FIELD_PASSAGE).unwrap(),
+            passage_ordinal: schema.get_field(FIELD_PASSAGE_ORDINAL).unwrap(),
+            passage_page: schema.get_field(FIELD_PASSAGE_PAGE).unwrap(),
+            passage_line: schema.get_field(FIELD_PASSAGE_LINE).unwrap(),
+        }
+    }
+
+    /// 从索引文档读取标题、路径、标签以及时间和大小字段
+    fn to_hit(&self, doc: &TantivyDocument, score: f32) -> SearchHit {
+        let title = doc.get_first(self.title)
             .and_then(|v| v.as_str())
             .unwrap_or("无标题")
             .to_string();
         
-        let path = retrieved_doc.get_first(path_field)
+        let path = doc.get_first(self.path)
             .and_then(|v| v.as_str())
             .unwrap_or("无路径")
             .to_string();
         
-        let tags = tags_field.and_then(|f| {
-            retrieved_doc.get_first(f).and_then(|v| v.as_str()).map(|s| s.to_string())
+        let tags = self.tags.and_then(|f| {
+            doc.get_first(f).and_then(|v| v.as_str()).map(|s| s.to_string())
         });
         
-        // 从索引中读取时间和大小字段
-        let file_size = file_size_field
-            .and_then(|f| retrieved_doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let modified_time = modified_time_field
-            .and_then(|f| retrieved_doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let created_time = created_time_field
-            .and_then(|f| retrieved_doc.get_first(f))
-            .and_then(|v| v.as_u64());
-        
-        let accessed_time = accessed_time_field
-            .and_then(|f| retrieved_doc.get_first(f))
+        let read_u64 = |field: Option<Field>| field
+            .and_then(|f| doc.get_first(f))
             .and_then(|v| v.as_u64());
+        let read_i64 = |field: Option<Field>| field
+            .and_then(|f| doc.get_first(f))
+            .and_then(|v| v.as_i64());
         
-        results.push(SearchHit {
+        SearchHit {
             title,
             path,
             score,
             tags,
-            file_size,
-            modified_time,
-            created_time,
-            accessed_time,
-        });
+            file_size: read_u64(self.file_size),
+            modified_time: read_i64(self.modified_time),
+            created_time: read_i64(self.created_time),
+            accessed_time: read_i64(self.accessed_time),
+            match_ranges: Vec::new(),
+            passage: None,
+            snippet: None,
+            duplicates: Vec::new(),
+        }
     }
 
-    Ok(results)
+    /// 从段落文档读取段落，文件文档返回 `None`
+    fn to_passage(&self, doc: &TantivyDocument) -> Option<Passage> {
+        let text = doc.get_first(self.passage)?.as_str()?.to_string();
+        let read_u64 = |field: Field| doc.get_first(field).and_then(|v| v.as_u64());
+        let location = match read_u64(self.passage_page) {
+            Some(page) => PassageLocation::Page(page as u32),
+            None => PassageLocation::Line(read_u64(self.passage_line).unwrap_or(1) as u32),
+        };
+        Some(Passage {
+            ordinal: read_u64(self.passage_ordinal).unwrap_or(0) as usize,
+            text,
+            location,
+        })
+    }
+
+    /// 指定路径和文档类型的文档
+    fn path_query(&self, path: &str, kind: &str) -> BooleanQuery {
+        let term_query = |field: Field, text: &str| -> Box<dyn Query> {
+            Box::new(TermQuery::new(Term::from_field_text(field, text), IndexRecordOption::Basic))
+        };
+        BooleanQuery::new(vec![
+            (Occur::Must, term_query(self.path, path)),
+            (Occur::Must, term_query(self.doc_kind, kind)),
+        ])
+    }
 }
 
 /// 搜索结果（带分页）
@@ -135,11 +298,12 @@ pub struct SearchResults {
 pub fn search_with_pagination(
     reader: &IndexReader, 
     index: &Index, 
+    boosts: &FieldBoostConfig,
     query_str: &str,
     offset: usize,
     limit: usize,
 ) -> Result<SearchResults> {
-    let all_results = search_with_results(reader, index, query_str)?;
+    let all_results = search_with_results(reader, index, boosts, query_str)?;
     let total = all_results.len();
     
     let hits: Vec<SearchHit> = all_results
@@ -156,174 +320,278 @@ pub fn search_with_pagination(
     })
 }
 
+/// RRF（倒数排名融合）的平滑常数
+const RRF_K: f32 = 60.0;
+
+/// 语义召回的最少候选数
+const MIN_SEMANTIC_CANDIDATES: usize = 50;
+
+/// 混合搜索的参数
+pub struct HybridOptions<'a> {
+    /// `root:`、时间、大小等过滤条件编译出的查询，两路结果都只保留满足条件的文档
+    pub filter: Option<&'a dyn Query>,
+    /// 传统搜索权重（0.0-1.0）
+    pub text_weight: f32,
+    /// 语义搜索权重（0.0-1.0）
+    pub semantic_weight: f32,
+    /// 返回结果数量上限
+    pub limit: usize,
+}
+
 /// 混合搜索：结合传统全文搜索和语义向量搜索
 /// 
 /// # 搜索策略
-/// 1. 传统搜索：使用 Tantivy QueryParser 进行精确关键词匹配
-/// 2. 语义搜索：使用 BERT embeddings 计算向量相似度（需要提供查询向量）
-/// 3. 结果融合：使用加权平均合并两种搜索的分数
+/// 1. 传统搜索：使用 Tantivy QueryParser 进行 BM25 关键词匹配，文件按最相关的段落排序
+/// 2. 语义搜索：在向量索引中查找与查询向量最相似的文档（取最相似的段落作为文档得分）
+/// 3. 结果融合：按两路排名做加权 RRF，分数与 BM25 和余弦相似度的量纲无关
 /// 
-/// # 注意
-/// 由于完整的向量相似度搜索需要遍历所有文档并计算相似度，在大规模数据集上性能较差。
-/// 实际生产环境应该使用专门的向量数据库（如 Qdrant、Milvus）或 Tantivy 的自定义评分器。
+/// 融合后的分数归一化到 0-1（两路都排第一时为 1）。
+/// 结果的 `passage` 优先取全文命中的段落，其次取语义最相似的段落；
+/// 只被语义搜索召回的文件以最相似段落的开头作为预览片段。
 pub fn hybrid_search(
     reader: &IndexReader,
     index: &Index,
+    cache: &EmbeddingCache,
+    boosts: &FieldBoostConfig,
     query_str: &str,
-    query_embedding: Option<&[f32]>,  // 查询的向量表示
-    text_weight: f32,   // 传统搜索权重（0.0-1.0）
-    semantic_weight: f32, // 语义搜索权重（0.0-1.0）
-    limit: usize,
+    query_embedding: Option<&[f32]>,  // 查询的归一化向量表示
+    options: &HybridOptions,
 ) -> Result<Vec<SearchHit>> {
-    use std::collections::HashMap;
+    let &HybridOptions { filter, text_weight, semantic_weight, limit } = options;
+    let searcher = reader.searcher();
+    let schema = index.schema();
+    let hit_fields = HitFields::from_schema(&schema);
+    let path_filter = filter
+        .map(|filter| PathFilter::new(&searcher, hit_fields.path, filter))
+        .transpose()?;
     
     // 1. 传统全文搜索
-    let text_results = search_with_results(reader, index, query_str)?;
+    let text_results = text_search(&searcher, index, boosts, query_str, path_filter.as_ref(), limit.max(DEFAULT_TEXT_LIMIT))?;
     
     // 如果没有提供查询向量或语义权重为0，只返回传统搜索结果
-    if query_embedding.is_none() || semantic_weight == 0.0 {
-        let mut results = text_results;
-        results.truncate(limit);
-        return Ok(results);
-    }
-    
-    // 注意：这里虽然有查询向量，但当前简化实现并未使用
-    // 完整实现应该：计算文档向量并与查询向量做余弦相似度
-    // let _query_vec = query_embedding.unwrap();
+    let query_vec = match query_embedding {
+        Some(vec) if semantic_weight > 0.0 => vec,
+        _ => {
+            let mut results = text_results;
+            results.truncate(limit);
+            return Ok(results);
+        }
+    };
     
     // 2. 语义向量搜索
-    // 注意：这是一个简化实现，实际应该：
-    // - 预先计算并存储所有文档的向量
-    // - 使用向量数据库或近似最近邻算法（ANN）加速搜索
-    // - 或者使用 Tantivy 的自定义评分器
+    let accept = |path: &str| path_filter.as_ref().is_none_or(|f| f.allows(path));
+    let semantic_ranking = cache.nearest_documents(query_vec, limit.max(MIN_SEMANTIC_CANDIDATES), &accept);
+    tracing::debug!("[语义搜索] 召回 {} 个候选文档", semantic_ranking.len());
     
-    let searcher = reader.searcher();
-    let schema = index.schema();
-    let title_field = schema.get_field(FIELD_TITLE).unwrap();
-    let body_field = schema.get_field(FIELD_BODY).unwrap();
-    let path_field = schema.get_field(FIELD_PATH).unwrap();
-    let tags_field = schema.get_field(FIELD_TAGS).ok();
-    let file_size_field = schema.get_field(crate::schema::FIELD_FILE_SIZE).ok();
-    let modified_time_field = schema.get_field(crate::schema::FIELD_MODIFIED_TIME).ok();
-    let created_time_field = schema.get_field(crate::schema::FIELD_CREATED_TIME).ok();
-    let accessed_time_field = schema.get_field(crate::schema::FIELD_ACCESSED_TIME).ok();
+    // 3. 融合两种搜索结果
+    let text_ranking: Vec<&str> = text_results.iter().map(|hit| hit.path.as_str()).collect();
+    let semantic_paths: Vec<&str> = semantic_ranking.iter().map(|m| m.path.as_str()).collect();
+    let fused = fuse_rankings(&text_ranking, &semantic_paths, text_weight, semantic_weight);
     
-    let mut semantic_results: Vec<SearchHit> = Vec::new();
+    // 4. 组装结果：只在语义结果中出现的文档从索引中读取
+    let mut text_hits: HashMap<String, SearchHit> = text_results.into_iter()
+        .map(|hit| (hit.path.clone(), hit))
+        .collect();
+    let best_chunks: HashMap<&str, usize> = semantic_ranking.iter()
+        .map(|m| (m.path.as_str(), m.chunk))
+        .collect();
     
-    // 这里使用简化的语义匹配：基于标签和关键词的软匹配
-    // 实际应该计算文档向量和查询向量的余弦相似度
-    for segment_reader in searcher.segment_readers() {
-        let store_reader = segment_reader.get_store_reader(1)?;
-        for doc_id in 0..segment_reader.num_docs() {
-            if let Ok(doc) = store_reader.get::<TantivyDocument>(doc_id) {
-                let title = doc.get_first(title_field)
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                let path = doc.get_first(path_field)
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                let body = doc.get_first(body_field)
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-                
-                let tags = tags_field.and_then(|f| {
-                    doc.get_first(f).and_then(|v| v.as_str()).map(String::from)
-                });
-                
-                // 从索引中读取时间和大小字段
-                let file_size = file_size_field
-                    .and_then(|f| doc.get_first(f))
-                    .and_then(|v| v.as_u64());
-                
-                let modified_time = modified_time_field
-                    .and_then(|f| doc.get_first(f))
-                    .and_then(|v| v.as_u64());
-                
-                let created_time = created_time_field
-                    .and_then(|f| doc.get_first(f))
-                    .and_then(|v| v.as_u64());
-                
-                let accessed_time = accessed_time_field
-                    .and_then(|f| doc.get_first(f))
-                    .and_then(|v| v.as_u64());
-                
-                // 简化的语义相似度：基于关键词覆盖率
-                let mut score = 0.0f32;
-                let query_terms: Vec<&str> = query_str.split_whitespace().collect();
-                
-                for term in &query_terms {
-                    if title.to_lowercase().contains(&term.to_lowercase()) {
-                        score += 0.5;
-                    }
-                    if body.to_lowercase().contains(&term.to_lowercase()) {
-                        score += 0.3;
-                    }
-                    if let Some(ref t) = tags {
-                        if t.to_lowercase().contains(&term.to_lowercase()) {
-                            score += 0.7; // 标签匹配权重更高
-                        }
-                    }
+    let mut results = Vec::with_capacity(limit.min(fused.len()));
+    for (path, score) in fused {
+        if results.len() >= limit {
+            break;
+        }
+        let hit = match text_hits.remove(&path) {
+            Some(hit) => Some(hit),
+            None => load_hit_by_path(&searcher, &hit_fields, &path)?,
+        };
+        // 向量缓存中可能残留已删除文件，索引里找不到时跳过
+        if let Some(mut hit) = hit {
+            hit.score = score;
+            if hit.passage.is_none() && let Some(&chunk) = best_chunks.get(path.as_str()) {
+                hit.passage = load_passage(&searcher, &hit_fields, &path, chunk)?;
+                hit.snippet = hit.passage.as_ref().map(|passage| Snippet::leading(&passage.text));
+            }
+            results.push(hit);
+        }
+    }
+    
+    Ok(results)
+}
+
+/// 语义召回的路径过滤器
+/// 
+/// 预先计算每个段中满足过滤条件的文档，候选文档按路径查到文档 ID 后判断。
+struct PathFilter<'a> {
+    searcher: &'a Searcher,
+    path_field: Field,
+    /// 每个段一个标记数组，下标为段内文档 ID
+    matches: Vec<Vec<bool>>,
+}
+
+impl<'a> PathFilter<'a> {
+    fn new(searcher: &'a Searcher, path_field: Field, filter: &dyn Query) -> Result<Self> {
+        let weight = filter.weight(EnableScoring::disabled_from_searcher(searcher))?;
+        let mut matches = Vec::with_capacity(searcher.segment_readers().len());
+        for segment_reader in searcher.segment_readers() {
+            let mut segment_matches = vec![false; segment_reader.max_doc() as usize];
+            weight.for_each_no_score(segment_reader, &mut |docs| {
+                for &doc in docs {
+                    segment_matches[doc as usize] = true;
                 }
-                
-                if score > 0.0 {
-                    semantic_results.push(SearchHit {
-                        title,
-                        path,
-                        score,
-                        tags,
-                        file_size,
-                        modified_time,
-                        created_time,
-                        accessed_time,
-                    });
+            })?;
+            matches.push(segment_matches);
+        }
+        Ok(Self { searcher, path_field, matches })
+    }
+
+    fn allows(&self, path: &str) -> bool {
+        let term = Term::from_field_text(self.path_field, path);
+        self.searcher.segment_readers().iter().zip(&self.matches).any(|(segment_reader, segment_matches)| {
+            let Ok(inverted_index) = segment_reader.inverted_index(self.path_field) else {
+                return false;
+            };
+            let Ok(Some(mut postings)) = inverted_index.read_postings(&term, IndexRecordOption::Basic) else {
+                return false;
+            };
+            let mut doc = postings.doc();
+            while doc != TERMINATED {
+                if segment_matches[doc as usize] && !segment_reader.is_deleted(doc) {
+                    return true;
                 }
+                doc = postings.advance();
             }
+            false
+        })
+    }
+}
+
+/// 加权 RRF 融合两路排名，返回 `(路径, 归一化分数)`，按分数降序
+fn fuse_rankings(
+    text_ranking: &[&str],
+    semantic_ranking: &[&str],
+    text_weight: f32,
+    semantic_weight: f32,
+) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    for (ranking, weight) in [(text_ranking, text_weight), (semantic_ranking, semantic_weight)] {
+        for (rank, path) in ranking.iter().enumerate() {
+            *scores.entry(*path).or_default() += weight / (RRF_K + rank as f32 + 1.0);
         }
     }
     
-    // 3. 融合两种搜索结果
-    let mut combined_results: HashMap<String, SearchHit> = HashMap::new();
-    
-    // 标准化传统搜索分数
-    let max_text_score = text_results.iter()
-        .map(|h| h.score)
-        .fold(0.0f32, f32::max)
-        .max(1.0);
+    let max_score = (text_weight + semantic_weight) / (RRF_K + 1.0);
+    let mut fused: Vec<(String, f32)> = scores.into_iter()
+        .map(|(path, score)| {
+            let normalized = if max_score > 0.0 { score / max_score } else { 0.0 };
+            (path.to_string(), normalized)
+        })
+        .collect();
     
-    for mut hit in text_results {
-        hit.score = (hit.score / max_text_score) * text_weight;
-        combined_results.insert(hit.path.clone(), hit);
+    fused.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    fused
+}
+
+/// 创建在 `field` 中截取查询词片段的生成器，片段长度使用显示配置
+pub(crate) fn snippet_generator(searcher: &Searcher, query: &dyn Query, field: Field) -> Result<SnippetGenerator> {
+    let mut generator = SnippetGenerator::create(searcher, query, field)?;
+    generator.set_max_num_chars(CONFIG.display.preview_max_length);
+    Ok(generator)
+}
+
+/// 截取 `text` 中包含查询词的片段，文本不含查询词时返回 `None`
+pub(crate) fn highlighted_snippet(generator: &SnippetGenerator, text: &str) -> Option<Snippet> {
+    let snippet = generator.snippet(text);
+    if snippet.highlighted().is_empty() {
+        return None;
     }
-    
-    // 标准化语义搜索分数
-    let max_semantic_score = semantic_results.iter()
-        .map(|h| h.score)
-        .fold(0.0f32, f32::max)
-        .max(1.0);
-    
-    for mut hit in semantic_results {
-        let normalized_score = (hit.score / max_semantic_score) * semantic_weight;
-        
-        combined_results.entry(hit.path.clone())
-            .and_modify(|existing| {
-                // 已存在：合并分数
-                existing.score += normalized_score;
-            })
-            .or_insert_with(|| {
-                // 新结果
-                hit.score = normalized_score;
-                hit
-            });
+    Some(Snippet {
+        text: snippet.fragment().to_string(),
+        highlights: snippet.highlighted().to_vec(),
+    })
+}
+
+/// 按路径从索引中读取文件文档
+fn load_hit_by_path(searcher: &Searcher, hit_fields: &HitFields, path: &str) -> Result<Option<SearchHit>> {
+    let query = hit_fields.path_query(path, DOC_KIND_FILE);
+    let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.into_iter().next() else {
+        return Ok(None);
+    };
+    let doc: TantivyDocument = searcher.doc(address)?;
+    Ok(Some(hit_fields.to_hit(&doc, 0.0)))
+}
+
+/// 读取文件中与全文查询最相关的段落
+fn best_passage(searcher: &Searcher, hit_fields: &HitFields, query: &dyn Query, path: &str) -> Result<Option<Passage>> {
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, query.box_clone()),
+        (Occur::Must, Box::new(hit_fields.path_query(path, DOC_KIND_PASSAGE))),
+    ]);
+    let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.into_iter().next() else {
+        return Ok(None);
+    };
+    let doc: TantivyDocument = searcher.doc(address)?;
+    Ok(hit_fields.to_passage(&doc))
+}
+
+/// 按序号读取文件的段落
+fn load_passage(searcher: &Searcher, hit_fields: &HitFields, path: &str, ordinal: usize) -> Result<Option<Passage>> {
+    let ordinal = TermQuery::new(
+        Term::from_field_u64(hit_fields.passage_ordinal, ordinal as u64),
+        IndexRecordOption::Basic,
+    );
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, Box::new(hit_fields.path_query(path, DOC_KIND_PASSAGE))),
+        (Occur::Must, Box::new(ordinal)),
+    ]);
+    let Some((_, address)) = searcher.search(&query, &TopDocs::with_limit(1))?.into_iter().next() else {
+        return Ok(None);
+    };
+    let doc: TantivyDocument = searcher.doc(address)?;
+    Ok(hit_fields.to_passage(&doc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuse_rankings() {
+        let fused = fuse_rankings(&["/a", "/b"], &["/c", "/a"], 0.5, 0.5);
+        let paths: Vec<&str> = fused.iter().map(|(path, _)| path.as_str()).collect();
+
+        // 两路都命中的文档排在前面，只在一路排第一的文档次之
+        assert_eq!(paths, vec!["/a", "/c", "/b"]);
+        assert!(fused.iter().all(|(_, score)| *score > 0.0 && *score <= 1.0));
+
+        // 语义权重为 0 时保持全文排名
+        let fused = fuse_rankings(&["/a", "/b"], &["/b"], 1.0, 0.0);
+        assert_eq!(fused[0].0, "/a");
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let mut builder = Schema::builder();
+        let path = builder.add_text_field(FIELD_PATH, STRING | STORED);
+        let size = builder.add_u64_field("file_size", FAST | INDEXED);
+        let index = Index::create_in_ram(builder.build());
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        writer.add_document(tantivy::doc!(path => "/small", size => 10u64)).unwrap();
+        writer.add_document(tantivy::doc!(path => "/large", size => 1000u64)).unwrap();
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let large = tantivy::query::RangeQuery::new(
+            std::ops::Bound::Included(Term::from_field_u64(size, 100)),
+            std::ops::Bound::Unbounded,
+        );
+        let filter = PathFilter::new(&searcher, path, &large).unwrap();
+        assert!(filter.allows("/large"));
+        assert!(!filter.allows("/small"));
+        assert!(!filter.allows("/missing"));
     }
-    
-    // 4. 按分数排序
-    let mut results: Vec<SearchHit> = combined_results.into_values().collect();
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    results.truncate(limit);
-    
-    Ok(results)
 }
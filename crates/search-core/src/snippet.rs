@@ -0,0 +1,101 @@
+// search-core/src/snippet.rs
+//! 预览片段模块
+//!
+//! 搜索结果的预览文本及其中需要高亮的位置：
+//! - 全文命中：在最相关的段落中截取包含查询词的片段（Tantivy `SnippetGenerator`）
+//! - 正则命中：截取正文中第一个匹配附近的文本
+//! - 仅语义命中：取最相似段落的开头，不高亮
+//!
+//! 高亮位置均为片段文本内的字节偏移。
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::extract::format_content_preview;
+
+/// 带高亮位置的预览片段
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    /// 需要高亮的范围（`text` 内的字节偏移），按起始位置升序
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// 不带高亮的预览，取文本开头
+    pub fn leading(text: &str) -> Self {
+        Self { text: format_content_preview(text), highlights: Vec::new() }
+    }
+
+    /// 截取第一个匹配附近不超过 `max_len` 字节的文本
+    ///
+    /// 片段从第一个匹配所在行的行首开始（最多往前回退 `max_len / 4` 字节），
+    /// 完整落在片段内的匹配转换为片段内的偏移。没有匹配时返回 `None`。
+    pub fn around_matches(text: &str, ranges: &[Range<usize>], max_len: usize) -> Option<Self> {
+        let first = ranges.first()?;
+        let mut lookback = first.start.saturating_sub(max_len / 4);
+        while !text.is_char_boundary(lookback) {
+            lookback += 1;
+        }
+        let start = match text[lookback..first.start].rfind('\n') {
+            Some(newline) => lookback + newline + 1,
+            None => lookback,
+        };
+
+        let mut end = (start + max_len).max(first.end).min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut highlights: Vec<Range<usize>> = Vec::new();
+        for range in ranges.iter().filter(|r| r.start >= start && r.end <= end) {
+            // 多个正则的匹配可能重叠，只保留不与前一个重叠的
+            if highlights.last().is_none_or(|last| range.start >= last.end + start) {
+                highlights.push(range.start - start..range.end - start);
+            }
+        }
+
+        let mut snippet = text[start..end].to_string();
+        if end < text.len() {
+            snippet.push_str("...");
+        }
+        Some(Self { text: snippet, highlights })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_around_matches() {
+        let text = "ok\nERROR disk full\nWARN cpu";
+        let snippet = Snippet::around_matches(text, &[3..8, 19..23], 100).unwrap();
+        // 从匹配所在行的行首开始，偏移换算到片段内
+        assert_eq!(snippet.text, "ERROR disk full\nWARN cpu");
+        assert_eq!(snippet.highlights, vec![0..5, 16..20]);
+
+        // 超出长度的部分截断，截断后不完整的匹配不高亮
+        let snippet = Snippet::around_matches(text, &[3..8, 19..23], 12).unwrap();
+        assert_eq!(snippet.text, "ERROR disk f...");
+        assert_eq!(snippet.highlights, vec![0..5]);
+
+        // 重叠的匹配只保留前一个
+        let snippet = Snippet::around_matches(text, &[3..8, 5..10], 100).unwrap();
+        assert_eq!(snippet.highlights, vec![0..5]);
+
+        assert!(Snippet::around_matches(text, &[], 100).is_none());
+    }
+
+    #[test]
+    fn test_around_matches_respects_char_boundaries() {
+        let text = "中文内容中文内容 ERROR WARN";
+        let error = text.find("ERROR").unwrap();
+        let warn = text.find("WARN").unwrap();
+        let snippet = Snippet::around_matches(text, &[error..error + 5, warn..warn + 4], 20).unwrap();
+        assert_eq!(snippet.text, "容 ERROR WARN");
+        let highlighted: Vec<&str> = snippet.highlights.iter().map(|r| &snippet.text[r.clone()]).collect();
+        assert_eq!(highlighted, vec!["ERROR", "WARN"]);
+    }
+}
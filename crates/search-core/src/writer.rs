@@ -0,0 +1,286 @@
+// search-core/src/writer.rs
+//! 索引写入模块
+//!
+//! 进程内只持有一个长期存在的 `IndexWriter`，由后台写入线程独占。
+//! 扫描、文件监控等调用方通过 channel 提交新增/删除操作，
+//! 写入线程按操作数量或时间阈值批量提交，避免逐文件 commit 和写锁争用。
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use tantivy::schema::Field;
+use tantivy::{Index, IndexWriter, TantivyDocument, Term};
+
+use crate::cache::EmbeddingCache;
+use crate::catalog::FileStat;
+use crate::config::IndexConfig;
+use crate::schema::FIELD_PATH;
+
+/// 提交给写入线程的操作
+enum WriteOp {
+    /// 新增或替换文件的文档（按 path 先删除旧的文件文档和段落文档）
+    Upsert {
+        path: String,
+        documents: Vec<TantivyDocument>,
+        /// 读取文件内容前的文件状态，提交成功后记录到文件目录
+        stat: FileStat,
+        /// 源文件原始字节的内容哈希，未知时为 0
+        content_hash: u64,
+    },
+    /// 按 path 删除文档
+    Delete { path: String },
+    /// 立即提交，并回传提交结果
+    Commit { done: Sender<Result<()>> },
+}
+
+/// 索引写入句柄
+///
+/// 可以自由克隆并在线程间共享；所有句柄都被释放后，写入线程提交剩余操作并退出。
+#[derive(Clone)]
+pub struct IndexWriterHandle {
+    tx: Sender<WriteOp>,
+}
+
+impl IndexWriterHandle {
+    /// 创建 `IndexWriter` 并启动写入线程
+    ///
+    /// 写入内存使用 `IndexConfig::writer_memory`，
+    /// 提交阈值使用 `commit_batch_size` 和 `commit_interval_ms`。
+    pub fn spawn(index: &Index, cache: Arc<EmbeddingCache>, config: &IndexConfig) -> Result<Self> {
+        let writer: IndexWriter = index.writer(config.writer_memory)?;
+        let path_field = index.schema().get_field(FIELD_PATH)?;
+        let (tx, rx) = channel();
+
+        let actor = WriterActor {
+            writer,
+            path_field,
+            cache,
+            batch_size: config.commit_batch_size.max(1),
+            interval: Duration::from_millis(config.commit_interval_ms),
+            pending_ops: 0,
+            pending_sources: Vec::new(),
+        };
+        thread::Builder::new()
+            .name("index-writer".into())
+            .spawn(move || actor.run(rx))?;
+
+        tracing::info!(
+            "[索引写入] 写入线程已启动: 内存 {} 字节, 批量 {} 条, 间隔 {} ms",
+            config.writer_memory, config.commit_batch_size, config.commit_interval_ms
+        );
+        Ok(Self { tx })
+    }
+
+    /// 新增或替换文档，提交成功后把 `stat` 及内容哈希记录到文件目录
+    pub fn upsert(&self, path: String, document: TantivyDocument, stat: FileStat, content_hash: u64) -> Result<()> {
+        self.upsert_with_passages(path, document, Vec::new(), stat, content_hash)
+    }
+
+    /// 新增或替换文件文档及其段落文档，提交成功后把 `stat` 及内容哈希记录到文件目录
+    ///
+    /// `stat` 应在读取文件内容之前获取，读取期间文件被修改时下次扫描能发现变化。
+    pub fn upsert_with_passages(
+        &self,
+        path: String,
+        document: TantivyDocument,
+        passages: Vec<TantivyDocument>,
+        stat: FileStat,
+        content_hash: u64,
+    ) -> Result<()> {
+        let mut documents = Vec::with_capacity(passages.len() + 1);
+        documents.push(document);
+        documents.extend(passages);
+        self.send(WriteOp::Upsert { path, documents, stat, content_hash })
+    }
+
+    /// 删除指定路径的文档
+    pub fn delete(&self, path: String) -> Result<()> {
+        self.send(WriteOp::Delete { path })
+    }
+
+    /// 立即提交所有待写入的操作，阻塞直到提交完成
+    pub fn commit(&self) -> Result<()> {
+        let (done, result) = channel();
+        self.send(WriteOp::Commit { done })?;
+        result.recv().map_err(|_| anyhow!("索引写入线程已退出"))?
+    }
+
+    fn send(&self, op: WriteOp) -> Result<()> {
+        self.tx.send(op).map_err(|_| anyhow!("索引写入线程已退出"))
+    }
+}
+
+/// 写入线程状态
+struct WriterActor {
+    writer: IndexWriter,
+    path_field: Field,
+    cache: Arc<EmbeddingCache>,
+    batch_size: usize,
+    interval: Duration,
+    /// 上次提交后累计的操作数
+    pending_ops: usize,
+    /// 上次提交后新增的文档 `(路径, 文件状态, 内容哈希)`，提交成功后记录到文件目录
+    pending_sources: Vec<(String, FileStat, u64)>,
+}
+
+impl WriterActor {
+    fn run(mut self, rx: Receiver<WriteOp>) {
+        let mut last_commit = Instant::now();
+        loop {
+            // 没有待提交的操作时一直阻塞，否则最多等到下一次定时提交
+            let received = if self.pending_ops == 0 {
+                rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                let deadline = last_commit + self.interval;
+                rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            };
+
+            match received {
+                Ok(WriteOp::Upsert { path, documents, stat, content_hash }) => {
+                    self.writer.delete_term(Term::from_field_text(self.path_field, &path));
+                    let added = documents.into_iter()
+                        .try_for_each(|document| self.writer.add_document(document).map(|_| ()));
+                    if let Err(e) = added {
+                        tracing::error!("[索引写入] 写入文档失败 {}: {}", path, e);
+                        continue;
+                    }
+                    self.pending_sources.push((path, stat, content_hash));
+                    self.pending_ops += 1;
+                }
+                Ok(WriteOp::Delete { path }) => {
+                    self.writer.delete_term(Term::from_field_text(self.path_field, &path));
+                    self.pending_ops += 1;
+                }
+                Ok(WriteOp::Commit { done }) => {
+                    let _ = done.send(self.commit());
+                    last_commit = Instant::now();
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = self.commit();
+                    last_commit = Instant::now();
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let _ = self.commit();
+                    tracing::info!("[索引写入] 所有句柄已释放，写入线程退出");
+                    return;
+                }
+            }
+
+            if self.pending_ops >= self.batch_size {
+                let _ = self.commit();
+                last_commit = Instant::now();
+            }
+        }
+    }
+
+    /// 提交所有待写入的操作，成功后把新增文档记录到文件目录
+    fn commit(&mut self) -> Result<()> {
+        if self.pending_ops == 0 {
+            return Ok(());
+        }
+
+        let ops = std::mem::take(&mut self.pending_ops);
+        let sources = std::mem::take(&mut self.pending_sources);
+        // 提交信息中写入文件目录的代数，启动时据此判断目录记录是否仍对应当前索引
+        let committed = self.writer.prepare_commit().and_then(|mut prepared| {
+            prepared.set_payload(&self.cache.catalog().generation().to_string());
+            prepared.commit()
+        });
+        if let Err(e) = committed {
+            // 文件目录未记录，下次扫描时这些文件会被重新索引
+            tracing::error!("[索引写入] 提交失败 ({} 个操作): {}", ops, e);
+            return Err(e.into());
+        }
+
+        for (path, stat, content_hash) in &sources {
+            let _ = self.cache.save_file_meta(path, stat, *content_hash);
+        }
+        if let Err(e) = self.cache.catalog().flush() {
+            tracing::warn!("[索引写入] 文件目录保存失败: {}", e);
+        }
+        tracing::debug!("[索引写入] 已提交 {} 个操作 ({} 个文档)", ops, sources.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::doc;
+
+    use crate::schema::build_schema;
+
+    fn spawn_writer(batch_size: usize) -> (Index, IndexWriterHandle, tempfile::TempDir) {
+        let index = Index::create_in_ram(build_schema());
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(EmbeddingCache::new(&dir.path().join("cache")).unwrap());
+        let config = IndexConfig {
+            writer_memory: 15_000_000,
+            commit_batch_size: batch_size,
+            commit_interval_ms: 60_000,
+            ..Default::default()
+        };
+        let writer = IndexWriterHandle::spawn(&index, cache, &config).unwrap();
+        (index, writer, dir)
+    }
+
+    fn path_doc(index: &Index, path: &str) -> TantivyDocument {
+        let path_field = index.schema().get_field(FIELD_PATH).unwrap();
+        doc!(path_field => path)
+    }
+
+    fn stat() -> FileStat {
+        FileStat { inode: 0, file_size: 0, mtime_ns: 0 }
+    }
+
+    fn num_docs(index: &Index) -> u64 {
+        index.reader().unwrap().searcher().num_docs()
+    }
+
+    #[test]
+    fn test_upsert_replaces_and_delete_removes() {
+        let (index, writer, _dir) = spawn_writer(1000);
+        for path in ["/a.txt", "/b.txt", "/a.txt"] {
+            writer.upsert(path.into(), path_doc(&index, path), stat(), 0).unwrap();
+        }
+        writer.commit().unwrap();
+        assert_eq!(num_docs(&index), 2);
+
+        writer.delete("/b.txt".into()).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(num_docs(&index), 1);
+    }
+
+    #[test]
+    fn test_upsert_replaces_passages() {
+        let (index, writer, _dir) = spawn_writer(1000);
+        let passages = vec![path_doc(&index, "/a.txt"), path_doc(&index, "/a.txt")];
+        writer.upsert_with_passages("/a.txt".into(), path_doc(&index, "/a.txt"), passages, stat(), 0).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(num_docs(&index), 3);
+
+        // 重新写入时旧的段落文档一并删除
+        writer.upsert("/a.txt".into(), path_doc(&index, "/a.txt"), stat(), 0).unwrap();
+        writer.commit().unwrap();
+        assert_eq!(num_docs(&index), 1);
+    }
+
+    #[test]
+    fn test_commits_when_batch_is_full() {
+        let (index, writer, _dir) = spawn_writer(2);
+        for path in ["/a.txt", "/b.txt"] {
+            writer.upsert(path.into(), path_doc(&index, path), stat(), 0).unwrap();
+        }
+        // 不显式提交，等待写入线程按批量阈值自动提交
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while num_docs(&index) < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(num_docs(&index), 2);
+    }
+}